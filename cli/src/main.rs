@@ -0,0 +1,245 @@
+//! MicroFlow命令行工具
+//!
+//! 用法:
+//!   microflow validate <workflow.mflow>   输出完整验证报告
+//!   microflow run <workflow.mflow> [名称=值...]
+//!                                         验证后执行工作流，可覆盖工作流参数
+//!   microflow batch <workflow.mflow> <params.csv|params.jsonl> <results.jsonl>
+//!                                         对每组参数运行一次，结果逐行写入results.jsonl
+//!   microflow runs list|show <运行ID>|diff <运行ID> <运行ID>|replay <运行ID>
+//!                                         查看、比较和重放运行历史
+//!
+//! run/batch的每次运行记录到$MICROFLOW_HISTORY_DIR（默认~/.microflow/history）
+
+mod progress;
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use microflow_core::engine::CancellationToken;
+use progress::Progress;
+use microflow_core::workflow::{diff_runs, read_parameter_sets, ExecutionContext, ExecutionResult, ParameterValues, RunDiff, ValidationReport, ValueChange, WorkflowData, WorkflowExecutor};
+
+const USAGE: &str = "用法: microflow <validate|run|batch> <workflow.mflow> [参数...] | microflow runs <list|show|diff|replay> [运行ID...]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [command, rest @ ..] = args.as_slice() {
+        if command == "runs" {
+            return runs(rest);
+        }
+    }
+    let (command, path, rest) = match args.as_slice() {
+        [command, path, rest @ ..] => (command.as_str(), Path::new(path), rest),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let workflow = match std::fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|json| WorkflowData::from_json(&json)) {
+        Ok((workflow, warnings)) => {
+            for warning in warnings {
+                eprintln!("警告: {}", warning);
+            }
+            workflow
+        }
+        Err(e) => {
+            eprintln!("加载{}失败: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut executor = WorkflowExecutor::new(ExecutionContext::new());
+    executor.set_history_dir(history_dir());
+    // 子图引用和模型文件相对于工作流文件所在目录
+    if let Some(dir) = path.parent() {
+        executor.set_subgraph_dir(dir);
+    }
+
+    let report = executor.validate_workflow(&workflow);
+    print_report(&report);
+    if report.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    match (command, rest) {
+        ("validate", []) => ExitCode::SUCCESS,
+        ("run", overrides) => match parse_overrides(overrides) {
+            Ok(overrides) => run(&executor, &workflow, &overrides),
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::from(2)
+            }
+        },
+        ("batch", [params, results]) => batch(&executor, &workflow, Path::new(params), Path::new(results)),
+        ("validate" | "batch", _) => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+        (other, _) => {
+            eprintln!("未知命令: {}", other);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// 运行历史目录：$MICROFLOW_HISTORY_DIR，否则~/.microflow/history
+fn history_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("MICROFLOW_HISTORY_DIR") {
+        return PathBuf::from(dir);
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).unwrap_or_default();
+    PathBuf::from(home).join(".microflow").join("history")
+}
+
+fn print_report(report: &ValidationReport) {
+    if report.issues.is_empty() {
+        println!("验证通过");
+    } else {
+        print!("{}", report);
+    }
+}
+
+// "名称=值"，值能解析为JSON时按JSON（数字、布尔等），否则为文本
+fn parse_overrides(args: &[String]) -> Result<ParameterValues, String> {
+    args.iter()
+        .map(|arg| {
+            let (name, value) = arg.split_once('=').ok_or_else(|| format!("参数格式应为名称=值: {}", arg))?;
+            let value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+            Ok((name.to_string(), value))
+        })
+        .collect()
+}
+
+fn runtime() -> Option<tokio::runtime::Runtime> {
+    tokio::runtime::Runtime::new()
+        .map_err(|e| eprintln!("无法创建运行时: {}", e))
+        .ok()
+}
+
+// 运行future，期间在stderr渲染执行器的事件
+fn block_on_with_progress<F: Future>(runtime: &tokio::runtime::Runtime, executor: &WorkflowExecutor, future: F) -> F::Output {
+    let mut events = executor.subscribe();
+    let mut progress = Progress::default();
+    let output = runtime.block_on(async {
+        tokio::pin!(future);
+        loop {
+            tokio::select! {
+                output = &mut future => break output,
+                Ok(event) = events.recv() => progress.render(&event),
+            }
+        }
+    });
+    while let Ok(event) = events.try_recv() {
+        progress.render(&event);
+    }
+    output
+}
+
+fn run(executor: &WorkflowExecutor, workflow: &WorkflowData, overrides: &ParameterValues) -> ExitCode {
+    let Some(runtime) = runtime() else { return ExitCode::FAILURE };
+    let cancel = CancellationToken::new();
+    match block_on_with_progress(&runtime, executor, executor.execute_workflow_with_params(workflow, overrides, &cancel)) {
+        Ok(result) => {
+            print_outputs(&result);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("执行失败: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_outputs(result: &ExecutionResult) {
+    for (node_id, outputs) in &result.outputs {
+        for (port, value) in outputs {
+            println!("{}.{}: {:?}", node_id, port, value);
+        }
+    }
+}
+
+fn batch(executor: &WorkflowExecutor, workflow: &WorkflowData, params: &Path, results: &Path) -> ExitCode {
+    let parameter_sets = match read_parameter_sets(params) {
+        Ok(sets) => sets,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(runtime) = runtime() else { return ExitCode::FAILURE };
+    let cancel = CancellationToken::new();
+    match block_on_with_progress(&runtime, executor, executor.run_batch(workflow, &parameter_sets, results, &cancel)) {
+        Ok(summary) => {
+            println!("共{}组，成功{}，失败{}，结果已写入{}", summary.total, summary.succeeded, summary.failed, results.display());
+            if summary.failed > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+        }
+        Err(e) => {
+            eprintln!("批量运行失败: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn runs(args: &[String]) -> ExitCode {
+    let mut executor = WorkflowExecutor::new(ExecutionContext::new());
+    executor.set_history_dir(history_dir());
+    let Some(store) = executor.history() else { return ExitCode::FAILURE };
+
+    let outcome = match args {
+        [command] if command == "list" => store.list().map(|runs| {
+            for run in runs {
+                let replay = run.replay_of.map(|id| format!(" 重放自{}", id)).unwrap_or_default();
+                println!("{}  {}  {}ms  {}{}", run.run_id, run.status, run.duration_ms, run.workflow_hash, replay);
+            }
+        }),
+        [command, run_id] if command == "show" => store.get(run_id).map(|record| {
+            println!("{}", serde_json::to_string_pretty(&record).unwrap_or_default());
+        }),
+        [command, left, right] if command == "diff" => store.get(left)
+            .and_then(|left| Ok((left, store.get(right)?)))
+            .map(|(left, right)| print_diff(&diff_runs(&left, &right))),
+        [command, run_id] if command == "replay" => {
+            let Some(runtime) = runtime() else { return ExitCode::FAILURE };
+            let cancel = CancellationToken::new();
+            block_on_with_progress(&runtime, &executor, executor.replay_run(run_id, &cancel)).map(|result| {
+                println!("新运行: {}", result.run_id);
+                print_outputs(&result);
+            })
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_diff(diff: &RunDiff) {
+    if diff.is_empty() {
+        println!("{}与{}没有差异", diff.left, diff.right);
+        return;
+    }
+    if diff.workflow_changed {
+        println!("工作流已修改");
+    }
+    let print_changes = |title: &str, changes: &[ValueChange]| {
+        for change in changes {
+            let show = |value: &Option<serde_json::Value>| value.as_ref().map_or("（无）".to_string(), |value| value.to_string());
+            println!("{} {}: {} -> {}", title, change.key, show(&change.left), show(&change.right));
+        }
+    };
+    print_changes("参数", &diff.parameters);
+    for node_id in &diff.models {
+        println!("模型 {}: 文件不同", node_id);
+    }
+    print_changes("输出", &diff.outputs);
+}
@@ -0,0 +1,76 @@
+//! 执行事件的终端进度视图（输出到stderr，不影响stdout上的结果）
+
+use std::io::Write;
+
+use microflow_core::engine::RunningSubState;
+use microflow_core::workflow::ExecutionEvent;
+
+#[derive(Default)]
+pub struct Progress {
+    total: usize,
+    finished: usize,
+    /// 正在逐token输出的节点，其他事件到来前先换行
+    streaming: Option<String>,
+}
+
+impl Progress {
+    pub fn render(&mut self, event: &ExecutionEvent) {
+        if let ExecutionEvent::TokenGenerated { node_id, token, .. } = event {
+            if self.streaming.as_deref() != Some(node_id.as_str()) {
+                self.end_stream();
+                eprint!("  {} > ", node_id);
+                self.streaming = Some(node_id.clone());
+            }
+            eprint!("{}", token);
+            let _ = std::io::stderr().flush();
+            return;
+        }
+        self.end_stream();
+
+        match event {
+            ExecutionEvent::RunStarted { run_id, node_ids } => {
+                self.total = node_ids.len();
+                self.finished = 0;
+                eprintln!("运行 {}（{}个节点）", run_id, self.total);
+            }
+            ExecutionEvent::NodeStarted { node_id, .. } => eprintln!("  ▶ {}", node_id),
+            ExecutionEvent::NodeProgress { node_id, substate, .. } => match substate {
+                RunningSubState::Iterating { current, total: Some(total) } => eprintln!("  … {} 迭代 {}/{}", node_id, current, total),
+                RunningSubState::Iterating { current, total: None } => eprintln!("  … {} 迭代 {}", node_id, current),
+                RunningSubState::Branching { condition, .. } => eprintln!("  … {} 分支 {}", node_id, condition),
+                other => eprintln!("  … {} {:?}", node_id, other),
+            },
+            ExecutionEvent::NodeCompleted { node_id, duration_ms, .. } => {
+                self.finished += 1;
+                eprintln!("  ✔ [{}/{}] {} {}ms", self.finished, self.total, node_id, duration_ms.unwrap_or(0));
+            }
+            ExecutionEvent::NodeFailed { node_id, error, will_retry: true, .. } => eprintln!("  ↻ {} 重试: {}", node_id, error),
+            ExecutionEvent::NodeFailed { node_id, error, .. } => {
+                self.finished += 1;
+                eprintln!("  ✘ [{}/{}] {}: {}", self.finished, self.total, node_id, error);
+            }
+            ExecutionEvent::NodeSkipped { node_id, .. } => {
+                self.finished += 1;
+                eprintln!("  - [{}/{}] {} 跳过", self.finished, self.total, node_id);
+            }
+            ExecutionEvent::NodeCancelled { node_id, .. } => eprintln!("  ■ {} 已取消", node_id),
+            ExecutionEvent::ModelLoaded { model_id } => eprintln!("  模型已加载: {}", model_id),
+            ExecutionEvent::ModelEvicted { model_id } => eprintln!("  模型已淘汰: {}", model_id),
+            ExecutionEvent::ModelReleased { model_id } => eprintln!("  已释放模型显存: {}", model_id),
+            ExecutionEvent::ModelDemotionFailed { model_id, error } => eprintln!("  模型{}降级到内存失败: {}", model_id, error),
+            ExecutionEvent::ModelPrefetchFailed { model_id, error } => eprintln!("  预取模型{}失败: {}", model_id, error),
+            ExecutionEvent::ModelTierChanged { model_id, from, to, duration_ms } => {
+                eprintln!("  模型{}: {:?} -> {:?}（{}ms）", model_id, from, to, duration_ms)
+            }
+            ExecutionEvent::OutputProduced { node_id, port, value, .. } => eprintln!("  = {}.{}: {}", node_id, port, value),
+            ExecutionEvent::RunFinished { status, duration_ms, .. } => eprintln!("运行结束: {}（{}ms）", status, duration_ms),
+            ExecutionEvent::TokenGenerated { .. } => {}
+        }
+    }
+
+    fn end_stream(&mut self) {
+        if self.streaming.take().is_some() {
+            eprintln!();
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+struct CancelInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// 协作式取消令牌：克隆后共享同一取消状态
+///
+/// 异步代码通过`cancelled().await`等待取消，阻塞代码（FFI生成循环、Python调用）
+/// 通过`as_flag()`轮询。
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<CancelInner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(CancelInner {
+                cancelled: AtomicBool::new(false),
+                notify: Notify::new(),
+            }),
+        }
+    }
+
+    /// 请求取消（可重复调用）
+    pub fn cancel(&self) {
+        if !self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            self.inner.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 底层取消标志，供阻塞代码轮询
+    pub fn as_flag(&self) -> &AtomicBool {
+        &self.inner.cancelled
+    }
+
+    /// 等待取消
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
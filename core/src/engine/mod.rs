@@ -1,3 +1,5 @@
 pub mod state_machine;
+pub mod cancel;
 
 pub use state_machine::*;
+pub use cancel::CancellationToken;
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
+use serde::Serialize;
+
 use crate::types::{DataValue, Error as MicroFlowError};
 
 // 边ID类型（用于Branching子状态）
@@ -42,8 +44,9 @@ pub struct ErrorInfo {
     pub suggested_action: RecoveryAction,
 }
 
-// 子状态枚举
-#[derive(Debug, Clone, PartialEq)]
+// 子状态枚举（序列化供执行事件使用）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum RunningSubState {
     Normal,
     AwaitingInput,
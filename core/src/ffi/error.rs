@@ -33,6 +33,9 @@ pub enum FfiError {
     
     #[error("Context创建失败")]
     ContextCreationFailed,
+    
+    #[error("操作已取消")]
+    Cancelled,
 }
 
 impl FfiError {
@@ -4,22 +4,30 @@
 //! - 所有unsafe代码限制在此模块内
 //! - LlamaModel线程安全，LlamaContext绑定单线程
 //! - RAII确保C资源正确释放
+//!
+//! llama.cpp绑定（wrapper）仅在启用`llama`特性时编译，错误与参数类型始终可用
 
+#[cfg(feature = "llama")]
 use std::sync::Once;
 
 pub mod error;
 pub mod types;
+#[cfg(feature = "llama")]
 pub mod wrapper;
 pub mod lora;
 
 pub use error::FfiError;
-pub use types::{LoadParams, ContextParams};
+pub use types::{LoadParams, ContextParams, GenerateParams};
+#[cfg(feature = "llama")]
 pub use wrapper::{LlamaModel, LlamaContext};
 pub use lora::{LoRAState, validate_lora_header, estimate_lora_vram};
 
+#[cfg(feature = "llama")]
 static BACKEND_INIT: Once = Once::new();
+#[cfg(feature = "llama")]
 static mut BACKEND_INIT_SUCCESS: bool = false;
 
+#[cfg(feature = "llama")]
 pub fn initialize_backend() -> Result<(), FfiError> {
     unsafe {
         BACKEND_INIT.call_once(|| {
@@ -36,6 +44,7 @@ pub fn initialize_backend() -> Result<(), FfiError> {
     }
 }
 
+#[cfg(feature = "llama")]
 pub fn is_backend_initialized() -> bool {
     unsafe { BACKEND_INIT_SUCCESS }
 }
@@ -38,6 +38,21 @@ impl Default for ContextParams {
     }
 }
 
+/// 文本生成参数
+#[derive(Debug, Clone, Copy)]
+pub struct GenerateParams {
+    pub max_tokens: usize,
+}
+
+impl Default for GenerateParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: 512,
+        }
+    }
+}
+
+#[cfg(feature = "llama")]
 impl From<LoadParams> for llama_cpp_rs::LlamaModelParams {
     fn from(p: LoadParams) -> Self {
         let mut params = llama_cpp_rs::LlamaModelParams::default();
@@ -49,6 +64,7 @@ impl From<LoadParams> for llama_cpp_rs::LlamaModelParams {
     }
 }
 
+#[cfg(feature = "llama")]
 impl From<ContextParams> for llama_cpp_rs::LlamaContextParams {
     fn from(p: ContextParams) -> Self {
         let mut params = llama_cpp_rs::LlamaContextParams::default();
@@ -6,10 +6,10 @@ use std::marker::PhantomData;
 use std::path::Path;
 use std::time::{Instant, Duration};
 use crate::ffi::error::FfiError;
-use crate::ffi::types::{LoadParams, ContextParams};
+use crate::ffi::types::{LoadParams, ContextParams, GenerateParams};
 use crate::ffi::{initialize_backend, is_backend_initialized};
 use crate::ffi::lora::{LoRAState, validate_lora_header};
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub(crate) struct InnerModel {
     ptr: NonNull<llama_cpp_rs::llama_model>,
@@ -213,6 +213,56 @@ impl LlamaContext {
     }
 }
 
+impl LlamaContext {
+    /// 贪心解码生成文本（阻塞操作），每个token回调一次on_token
+    /// 
+    /// 每步解码前检查cancel，已取消时返回FfiError::Cancelled
+    pub fn generate<F>(
+        &mut self,
+        prompt: &str,
+        params: GenerateParams,
+        cancel: &AtomicBool,
+        mut on_token: F,
+    ) -> Result<String, FfiError>
+    where F: FnMut(&str),
+    {
+        let model = self.model.lock().map_err(|_| FfiError::Internal("锁中毒".into()))?;
+        
+        // SAFETY: model.ptr是有效的llama_model指针，在持有锁期间不会被释放
+        let mut tokens = unsafe {
+            llama_cpp_rs::llama_tokenize(model.ptr.as_ptr(), prompt, true)
+                .map_err(|e| FfiError::Internal(format!("分词失败: {}", e)))?
+        };
+        // SAFETY: 同上
+        let eos = unsafe { llama_cpp_rs::llama_token_eos(model.ptr.as_ptr()) };
+        
+        let mut output = String::new();
+        for _ in 0..params.max_tokens {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(FfiError::Cancelled);
+            }
+            
+            // SAFETY: ctx_ptr在self生命周期内有效，tokens为本模型的合法token
+            let next = unsafe {
+                llama_cpp_rs::llama_decode(self.ctx_ptr.as_ptr(), &tokens)
+                    .map_err(|e| FfiError::Internal(format!("解码失败: {}", e)))?;
+                llama_cpp_rs::llama_sample_token_greedy(self.ctx_ptr.as_ptr())
+            };
+            if next == eos {
+                break;
+            }
+            
+            // SAFETY: next由本模型采样得到
+            let piece = unsafe { llama_cpp_rs::llama_token_to_piece(model.ptr.as_ptr(), next) };
+            on_token(&piece);
+            output.push_str(&piece);
+            tokens = vec![next];
+        }
+        
+        Ok(output)
+    }
+}
+
 impl Drop for LlamaContext {
     fn drop(&mut self) {
         // 阻塞项修复#2：SAFETY注释
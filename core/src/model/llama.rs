@@ -0,0 +1,50 @@
+//! llama.cpp推理后端
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use crate::ffi::{ContextParams, FfiError, GenerateParams, LlamaContext, LlamaModel, LoadParams};
+use crate::model::{InferenceBackend, ModelProvider};
+
+/// 通过FFI层加载GGUF模型
+pub struct LlamaBackend;
+
+impl InferenceBackend for LlamaBackend {
+    fn name(&self) -> &'static str {
+        "llama"
+    }
+
+    fn load(&self, path: &Path, params: LoadParams) -> Result<Box<dyn ModelProvider>, FfiError> {
+        Ok(Box::new(LlamaModel::from_file(path, params)?))
+    }
+}
+
+impl ModelProvider for LlamaModel {
+    fn size_bytes(&self) -> Result<usize, FfiError> {
+        LlamaModel::size_bytes(self)
+    }
+
+    fn apply_lora(&self, lora_path: &Path) -> Result<(), FfiError> {
+        LlamaModel::apply_lora(self, lora_path)
+    }
+
+    fn remove_lora(&self) -> Result<(), FfiError> {
+        self.unload_lora()
+    }
+
+    fn current_lora(&self) -> Option<PathBuf> {
+        self.lora_state.lock().ok()?.active_lora.as_ref().map(PathBuf::from)
+    }
+
+    // 每次生成使用新的上下文，不保留KV缓存
+    fn generate(
+        &self,
+        prompt: &str,
+        params: GenerateParams,
+        cancel: &AtomicBool,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<String, FfiError> {
+        let mut context = LlamaContext::new(self, ContextParams::default())?;
+        context.generate(prompt, params, cancel, on_token)
+    }
+}
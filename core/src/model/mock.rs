@@ -0,0 +1,174 @@
+//! 纯Rust的模拟推理后端：输出确定，大小和失败可配置，用于在没有GPU的机器上测试VRAM池和执行器
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::ffi::{FfiError, GenerateParams, LoadParams};
+use crate::model::{InferenceBackend, ModelProvider};
+use crate::vram::GgufInfo;
+
+/// 一个模拟模型的配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockModelSpec {
+    /// 全部层卸载到GPU时的显存占用，也是位于CPU内存层时的占用（字节）
+    pub size_bytes: usize,
+    /// 层数，显存占用按n_gpu_layers折算（同GGUF估算）；None表示不分层
+    pub block_count: Option<u64>,
+    /// 每次加载前等待的时间，模拟加载耗时
+    pub load_delay: Duration,
+    /// 生成每个token前等待的时间，模拟生成耗时
+    pub token_delay: Duration,
+    pub fail_load: bool,
+    pub fail_generate: bool,
+    pub fail_lora: bool,
+}
+
+impl MockModelSpec {
+    pub fn new(size_bytes: usize) -> Self {
+        Self {
+            size_bytes,
+            block_count: None,
+            load_delay: Duration::ZERO,
+            token_delay: Duration::ZERO,
+            fail_load: false,
+            fail_generate: false,
+            fail_lora: false,
+        }
+    }
+}
+
+/// 按路径注册模拟模型；未注册的路径使用默认模型，没有默认模型时加载返回ModelNotFound，估算占用按GGUF文件
+#[derive(Default)]
+pub struct MockBackend {
+    models: HashMap<PathBuf, MockModelSpec>,
+    default_model: Option<MockModelSpec>,
+    loads: Mutex<HashMap<PathBuf, usize>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model(mut self, path: impl Into<PathBuf>, spec: MockModelSpec) -> Self {
+        self.models.insert(path.into(), spec);
+        self
+    }
+
+    /// 任意未注册的路径都按spec加载，便于在没有模型文件时运行工作流
+    pub fn with_default_model(mut self, spec: MockModelSpec) -> Self {
+        self.default_model = Some(spec);
+        self
+    }
+
+    /// path被加载（含降级/升级时的重新加载）的次数
+    pub fn load_count(&self, path: &Path) -> usize {
+        self.loads.lock().map(|loads| loads.get(path).copied().unwrap_or(0)).unwrap_or(0)
+    }
+
+    fn spec(&self, path: &Path) -> Result<&MockModelSpec, FfiError> {
+        self.models.get(path)
+            .or(self.default_model.as_ref())
+            .ok_or_else(|| FfiError::ModelNotFound(path.to_path_buf()))
+    }
+}
+
+impl InferenceBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn load(&self, path: &Path, _params: LoadParams) -> Result<Box<dyn ModelProvider>, FfiError> {
+        let spec = self.spec(path)?;
+        if spec.fail_load {
+            return Err(FfiError::Internal(format!("模型加载失败: {}", path.display())));
+        }
+        std::thread::sleep(spec.load_delay);
+        if let Ok(mut loads) = self.loads.lock() {
+            *loads.entry(path.to_path_buf()).or_insert(0) += 1;
+        }
+        Ok(Box::new(MockModel {
+            name: path.display().to_string(),
+            spec: spec.clone(),
+            lora: Mutex::new(None),
+        }))
+    }
+
+    // 未注册的路径同默认实现，读取GGUF元数据
+    fn estimate_footprint(&self, path: &Path, params: &LoadParams) -> Result<usize, FfiError> {
+        match self.spec(path) {
+            Ok(spec) => Ok(GgufInfo { file_size: spec.size_bytes as u64, block_count: spec.block_count }.footprint(params)),
+            Err(_) => crate::vram::estimate_footprint(path, params),
+        }
+    }
+
+    fn model_size(&self, path: &Path) -> usize {
+        match self.spec(path) {
+            Ok(spec) => spec.size_bytes,
+            Err(_) => std::fs::metadata(path).map(|metadata| metadata.len() as usize).unwrap_or(0),
+        }
+    }
+}
+
+/// 模拟模型：生成"[模型<路径>响应] 你输入了: <提示词>"，逐字符作为token输出
+pub struct MockModel {
+    name: String,
+    spec: MockModelSpec,
+    lora: Mutex<Option<PathBuf>>,
+}
+
+impl ModelProvider for MockModel {
+    fn size_bytes(&self) -> Result<usize, FfiError> {
+        Ok(self.spec.size_bytes)
+    }
+
+    fn apply_lora(&self, lora_path: &Path) -> Result<(), FfiError> {
+        if self.spec.fail_lora {
+            return Err(FfiError::Internal(format!("LoRA失败(已回滚): {}", lora_path.display())));
+        }
+        *self.lora.lock().map_err(|_| FfiError::Internal("锁中毒".into()))? = Some(lora_path.to_path_buf());
+        Ok(())
+    }
+
+    fn remove_lora(&self) -> Result<(), FfiError> {
+        *self.lora.lock().map_err(|_| FfiError::Internal("锁中毒".into()))? = None;
+        Ok(())
+    }
+
+    fn current_lora(&self) -> Option<PathBuf> {
+        self.lora.lock().ok()?.clone()
+    }
+
+    // 应用LoRA时响应中带上LoRA文件名，便于断言切换生效
+    fn generate(
+        &self,
+        prompt: &str,
+        params: GenerateParams,
+        cancel: &AtomicBool,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<String, FfiError> {
+        if self.spec.fail_generate {
+            return Err(FfiError::Internal(format!("模型{}生成失败", self.name)));
+        }
+        let model = match self.current_lora() {
+            Some(lora) => format!("{}+{}", self.name, lora.file_stem().unwrap_or_default().to_string_lossy()),
+            None => self.name.clone(),
+        };
+        let generated = format!("[模型{}响应] 你输入了: {}", model, prompt);
+
+        let mut output = String::new();
+        for token in generated.chars().take(params.max_tokens) {
+            std::thread::sleep(self.spec.token_delay);
+            if cancel.load(Ordering::SeqCst) {
+                return Err(FfiError::Cancelled);
+            }
+            let piece = token.to_string();
+            on_token(&piece);
+            output.push_str(&piece);
+        }
+        Ok(output)
+    }
+}
@@ -1,13 +1,72 @@
 pub mod lora_loader;
+#[cfg(feature = "llama")]
+pub mod llama;
+pub mod mock;
+
+#[cfg(test)]
+mod test_mock;
+
 pub use lora_loader::{LoraLoader, ModelError, LoraMetadata};
+#[cfg(feature = "llama")]
+pub use llama::LlamaBackend;
+pub use mock::{MockBackend, MockModel, MockModelSpec};
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::ffi::{FfiError, GenerateParams, LoadParams};
 
+/// 已加载的模型：VRAM池和节点只通过此接口使用模型，不依赖具体推理后端
 pub trait ModelProvider: Send + Sync {
+    /// 加载后的模型大小（字节）
+    fn size_bytes(&self) -> Result<usize, FfiError>;
+
     /// 热切换 LoRA（0.1 秒目标）
-    fn apply_lora(&self, lora_path: &std::path::Path) -> Result<(), ModelError>;
-    
+    fn apply_lora(&self, lora_path: &Path) -> Result<(), FfiError>;
+
     /// 卸载当前 LoRA，恢复 Base
-    fn remove_lora(&self) -> Result<(), ModelError>;
-    
+    fn remove_lora(&self) -> Result<(), FfiError>;
+
     /// 获取当前 LoRA 状态
-    fn current_lora(&self) -> Option<std::path::PathBuf>;
+    fn current_lora(&self) -> Option<PathBuf>;
+
+    /// 生成文本（阻塞操作），每个token回调一次on_token；cancel置位时返回FfiError::Cancelled
+    fn generate(
+        &self,
+        prompt: &str,
+        params: GenerateParams,
+        cancel: &AtomicBool,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<String, FfiError>;
+}
+
+/// 推理后端：按路径和加载参数创建模型，并在加载前估算占用
+pub trait InferenceBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 加载模型（阻塞操作）
+    fn load(&self, path: &Path, params: LoadParams) -> Result<Box<dyn ModelProvider>, FfiError>;
+
+    /// 以params加载时的显存占用（字节），默认按GGUF元数据估算
+    fn estimate_footprint(&self, path: &Path, params: &LoadParams) -> Result<usize, FfiError> {
+        crate::vram::estimate_footprint(path, params)
+    }
+
+    /// 模型完整大小（字节），即位于CPU内存层时的占用；默认为文件大小
+    fn model_size(&self, path: &Path) -> usize {
+        std::fs::metadata(path).map(|metadata| metadata.len() as usize).unwrap_or(0)
+    }
+}
+
+/// 默认推理后端：启用`llama`特性时为llama.cpp，否则为模拟后端
+pub fn default_backend() -> Arc<dyn InferenceBackend> {
+    #[cfg(feature = "llama")]
+    {
+        Arc::new(LlamaBackend)
+    }
+    #[cfg(not(feature = "llama"))]
+    {
+        Arc::new(MockBackend::new())
+    }
 }
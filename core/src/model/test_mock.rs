@@ -0,0 +1,76 @@
+use super::{InferenceBackend, MockBackend, MockModelSpec};
+use crate::ffi::{FfiError, GenerateParams, LoadParams};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+
+    fn generate(backend: &MockBackend, path: &str, prompt: &str, max_tokens: usize) -> Result<(String, Vec<String>), FfiError> {
+        let model = backend.load(Path::new(path), LoadParams::default())?;
+        let mut tokens = Vec::new();
+        let output = model.generate(prompt, GenerateParams { max_tokens }, &AtomicBool::new(false), &mut |token: &str| {
+            tokens.push(token.to_string())
+        })?;
+        Ok((output, tokens))
+    }
+
+    #[test]
+    fn test_mock_generation_is_deterministic() {
+        let backend = MockBackend::new().with_model("base", MockModelSpec::new(MIB));
+
+        let (output, tokens) = generate(&backend, "base", "你好", 512).unwrap();
+        assert_eq!(output, "[模型base响应] 你输入了: 你好");
+        assert_eq!(tokens.concat(), output);
+        assert_eq!(generate(&backend, "base", "你好", 512).unwrap().0, output);
+        assert_eq!(generate(&backend, "base", "你好", 3).unwrap().0, "[模型");
+        assert_eq!(backend.load_count(Path::new("base")), 3);
+
+        // 应用LoRA后响应带上LoRA名称，卸载后恢复
+        let model = backend.load(Path::new("base"), LoadParams::default()).unwrap();
+        model.apply_lora(Path::new("/loras/style.gguf")).unwrap();
+        assert_eq!(model.current_lora().as_deref(), Some(Path::new("/loras/style.gguf")));
+        let styled = model.generate("hi", GenerateParams::default(), &AtomicBool::new(false), &mut |_| {}).unwrap();
+        assert_eq!(styled, "[模型base+style响应] 你输入了: hi");
+        model.remove_lora().unwrap();
+        assert_eq!(model.current_lora(), None);
+    }
+
+    #[test]
+    fn test_mock_sizes_follow_gpu_layers() {
+        let spec = MockModelSpec { block_count: Some(31), ..MockModelSpec::new(32 * MIB) };
+        let backend = MockBackend::new().with_model("layered", spec);
+        let path = Path::new("layered");
+        let layers = |n_gpu_layers| LoadParams { n_gpu_layers, ..LoadParams::default() };
+
+        assert_eq!(backend.estimate_footprint(path, &layers(0)).unwrap(), 0);
+        assert_eq!(backend.estimate_footprint(path, &layers(16)).unwrap(), 16 * MIB);
+        assert_eq!(backend.estimate_footprint(path, &layers(-1)).unwrap(), 32 * MIB);
+        assert_eq!(backend.model_size(path), 32 * MIB);
+        assert_eq!(backend.load(path, layers(-1)).unwrap().size_bytes().unwrap(), 32 * MIB);
+        assert!(matches!(backend.estimate_footprint(Path::new("missing"), &layers(-1)), Err(FfiError::ModelNotFound(_))));
+    }
+
+    #[test]
+    fn test_mock_failures_and_cancellation() {
+        let backend = MockBackend::new()
+            .with_model("broken", MockModelSpec { fail_load: true, ..MockModelSpec::new(MIB) })
+            .with_model("mute", MockModelSpec { fail_generate: true, ..MockModelSpec::new(MIB) })
+            .with_model("rigid", MockModelSpec { fail_lora: true, ..MockModelSpec::new(MIB) });
+
+        assert!(matches!(backend.load(Path::new("missing"), LoadParams::default()), Err(FfiError::ModelNotFound(_))));
+        assert!(matches!(backend.load(Path::new("broken"), LoadParams::default()), Err(FfiError::Internal(_))));
+        assert_eq!(backend.load_count(Path::new("broken")), 0);
+        assert!(matches!(generate(&backend, "mute", "你好", 512), Err(FfiError::Internal(_))));
+
+        let rigid = backend.load(Path::new("rigid"), LoadParams::default()).unwrap();
+        assert!(rigid.apply_lora(Path::new("style.gguf")).is_err());
+        assert_eq!(rigid.current_lora(), None);
+
+        let cancelled = rigid.generate("你好", GenerateParams::default(), &AtomicBool::new(true), &mut |_| {});
+        assert!(matches!(cancelled, Err(FfiError::Cancelled)));
+    }
+}
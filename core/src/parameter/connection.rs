@@ -22,4 +22,8 @@ impl ConnectionGraph {
     pub fn get_source(&self, node: &NodeId, port: &PortId) -> Option<&(NodeId, PortId)> {
         self.incoming.get(&(node.clone(), port.clone()))
     }
+    /// 获取节点所有已连接的输入端口
+    pub fn input_ports(&self, node: &NodeId) -> Vec<&PortId> {
+        self.incoming.keys().filter(|(n, _)| n == node).map(|(_, p)| p).collect()
+    }
 }
\ No newline at end of file
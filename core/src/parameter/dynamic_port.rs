@@ -8,6 +8,7 @@ pub struct Port {
     pub multiple: bool,
 }
 
+#[derive(Clone)]
 pub struct DynamicPorts {
     inputs: HashMap<String, Port>,
     outputs: HashMap<String, Port>,
@@ -19,4 +20,6 @@ impl DynamicPorts {
     pub fn add_output(&mut self, port: Port) { self.outputs.insert(port.id.clone(), port); }
     pub fn get_input(&self, id: &str) -> Option<&Port> { self.inputs.get(id) }
     pub fn get_output(&self, id: &str) -> Option<&Port> { self.outputs.get(id) }
+    pub fn inputs(&self) -> impl Iterator<Item = &Port> { self.inputs.values() }
+    pub fn outputs(&self) -> impl Iterator<Item = &Port> { self.outputs.values() }
 }
\ No newline at end of file
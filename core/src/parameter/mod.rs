@@ -1,4 +1,4 @@
 pub mod dynamic_port;
 pub mod connection;
 pub use dynamic_port::{DynamicPorts, Port};
-pub use connection::{Connection, ConnectionGraph};
\ No newline at end of file
+pub use connection::{Connection, ConnectionGraph, NodeId, PortId};
\ No newline at end of file
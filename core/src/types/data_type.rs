@@ -29,3 +29,18 @@ impl fmt::Display for DataType {
         }
     }
 }
+
+impl DataType {
+    /// 能否通过`DataValue::convert_to`转换为目标类型（与其转换表保持一致）；相同类型视为可转换
+    pub fn can_convert_to(&self, target: &DataType) -> bool {
+        use DataType::*;
+        match (self, target) {
+            (a, b) if a == b => true,
+            (Number, Text) | (Number, Boolean) => true,
+            (Text, Number) | (Text, Boolean) | (Text, Binary) => true,
+            (Boolean, Number) | (Boolean, Text) => true,
+            (Path, Text) | (Binary, Text) => true,
+            _ => false,
+        }
+    }
+}
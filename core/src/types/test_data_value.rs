@@ -87,4 +87,15 @@ mod tests {
         assert_eq!(path_value.convert_to(DataType::Path).unwrap(), DataValue::Path(PathBuf::from("/test")));
         assert_eq!(path_value.convert_to(DataType::Text).unwrap(), DataValue::Text("/test".to_string()));
     }
+
+    #[test]
+    fn test_data_type_can_convert_to() {
+        assert!(DataType::Number.can_convert_to(&DataType::Text));
+        assert!(DataType::Text.can_convert_to(&DataType::Binary));
+        assert!(DataType::Path.can_convert_to(&DataType::Text));
+        assert!(!DataType::Text.can_convert_to(&DataType::Path));
+        assert!(!DataType::Model.can_convert_to(&DataType::Text));
+        assert!(!DataType::Stream(Box::new(DataType::Text)).can_convert_to(&DataType::Text));
+        assert!(DataType::List(Box::new(DataType::Text)).can_convert_to(&DataType::List(Box::new(DataType::Text))));
+    }
 }
@@ -0,0 +1,241 @@
+//! 驻留模型的访问统计、固定与可替换的淘汰策略
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+/// 驻留模型的统计信息，供淘汰策略选择
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotStats {
+    pub model_id: String,
+    /// 估算的显存占用（字节）
+    pub footprint: usize,
+    /// 最近一次访问时的逻辑时钟
+    pub last_used: u64,
+    /// 加载以来的访问次数（含加载本身）
+    pub access_count: u64,
+    /// 实测的加载耗时
+    pub load_time: Duration,
+    /// 固定该模型的持有者（运行ID），非空时不会被淘汰
+    pub pinned_by: BTreeSet<String>,
+}
+
+impl SlotStats {
+    pub fn is_pinned(&self) -> bool {
+        !self.pinned_by.is_empty()
+    }
+}
+
+/// 淘汰策略：从未固定的驻留模型中选出一个淘汰
+pub trait EvictionPolicy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// candidates非空且均未固定；clock为当前逻辑时钟
+    fn select_victim<'a>(&self, candidates: &[&'a SlotStats], clock: u64) -> Option<&'a SlotStats>;
+}
+
+/// 最久未使用
+pub struct LruPolicy;
+
+impl EvictionPolicy for LruPolicy {
+    fn name(&self) -> &'static str {
+        "lru"
+    }
+
+    fn select_victim<'a>(&self, candidates: &[&'a SlotStats], _clock: u64) -> Option<&'a SlotStats> {
+        candidates.iter().copied().min_by_key(|stats| stats.last_used)
+    }
+}
+
+/// 访问次数最少，次数相同时最久未使用
+pub struct LfuPolicy;
+
+impl EvictionPolicy for LfuPolicy {
+    fn name(&self) -> &'static str {
+        "lfu"
+    }
+
+    fn select_victim<'a>(&self, candidates: &[&'a SlotStats], _clock: u64) -> Option<&'a SlotStats> {
+        candidates.iter().copied().min_by_key(|stats| (stats.access_count, stats.last_used))
+    }
+}
+
+/// 空闲时间乘以占用最大者：优先淘汰又大又久未用的模型，一次释放更多显存
+pub struct SizeWeightedPolicy;
+
+impl EvictionPolicy for SizeWeightedPolicy {
+    fn name(&self) -> &'static str {
+        "size"
+    }
+
+    fn select_victim<'a>(&self, candidates: &[&'a SlotStats], clock: u64) -> Option<&'a SlotStats> {
+        candidates.iter().copied().max_by_key(|stats| {
+            let idle = clock.saturating_sub(stats.last_used) + 1;
+            (idle as u128 * stats.footprint as u128, std::cmp::Reverse(stats.last_used))
+        })
+    }
+}
+
+/// 重新加载代价最小者：按实测加载耗时，耗时相同时最久未使用
+pub struct ReloadCostPolicy;
+
+impl EvictionPolicy for ReloadCostPolicy {
+    fn name(&self) -> &'static str {
+        "reload_cost"
+    }
+
+    fn select_victim<'a>(&self, candidates: &[&'a SlotStats], _clock: u64) -> Option<&'a SlotStats> {
+        candidates.iter().copied().min_by_key(|stats| (stats.load_time, stats.last_used))
+    }
+}
+
+/// 按名称创建策略：lru / lfu / size / reload_cost
+pub fn policy_by_name(name: &str) -> Option<Box<dyn EvictionPolicy>> {
+    match name {
+        "lru" => Some(Box::new(LruPolicy)),
+        "lfu" => Some(Box::new(LfuPolicy)),
+        "size" => Some(Box::new(SizeWeightedPolicy)),
+        "reload_cost" => Some(Box::new(ReloadCostPolicy)),
+        _ => None,
+    }
+}
+
+/// 驻留模型的统计与固定记录，不持有模型本身
+pub struct ResidencyTracker {
+    stats: HashMap<String, SlotStats>,
+    /// 每次加载或访问加一，保证同一时刻的访问也有先后
+    clock: u64,
+    policy: Box<dyn EvictionPolicy>,
+    /// 执行计划中各模型的下次使用位置（越小越早），为空表示没有计划
+    next_uses: HashMap<String, usize>,
+}
+
+impl ResidencyTracker {
+    pub fn new(policy: Box<dyn EvictionPolicy>) -> Self {
+        Self { stats: HashMap::new(), clock: 0, policy, next_uses: HashMap::new() }
+    }
+
+    /// 设置执行计划中的下次使用位置，淘汰时按Belady优先选择不再使用或最晚才用到的模型
+    pub fn set_next_uses(&mut self, next_uses: HashMap<String, usize>) {
+        self.next_uses = next_uses;
+    }
+
+    pub fn next_use(&self, model_id: &str) -> Option<usize> {
+        self.next_uses.get(model_id).copied()
+    }
+
+    pub fn set_policy(&mut self, policy: Box<dyn EvictionPolicy>) {
+        self.policy = policy;
+    }
+
+    pub fn policy_name(&self) -> &'static str {
+        self.policy.name()
+    }
+
+    pub fn insert(&mut self, model_id: &str, footprint: usize, load_time: Duration) {
+        self.clock += 1;
+        self.stats.insert(model_id.to_string(), SlotStats {
+            model_id: model_id.to_string(),
+            footprint,
+            last_used: self.clock,
+            access_count: 1,
+            load_time,
+            pinned_by: BTreeSet::new(),
+        });
+    }
+
+    /// 记录一次访问，模型不在时返回false
+    pub fn touch(&mut self, model_id: &str) -> bool {
+        let Some(stats) = self.stats.get_mut(model_id) else { return false };
+        self.clock += 1;
+        stats.last_used = self.clock;
+        stats.access_count += 1;
+        true
+    }
+
+    pub fn remove(&mut self, model_id: &str) -> Option<SlotStats> {
+        self.stats.remove(model_id)
+    }
+
+    pub fn get(&self, model_id: &str) -> Option<&SlotStats> {
+        self.stats.get(model_id)
+    }
+
+    /// 由owner固定模型，模型不在时返回false
+    pub fn pin(&mut self, model_id: &str, owner: &str) -> bool {
+        match self.stats.get_mut(model_id) {
+            Some(stats) => {
+                stats.pinned_by.insert(owner.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn unpin(&mut self, model_id: &str, owner: &str) {
+        if let Some(stats) = self.stats.get_mut(model_id) {
+            stats.pinned_by.remove(owner);
+        }
+    }
+
+    /// 释放owner的全部固定
+    pub fn release(&mut self, owner: &str) {
+        for stats in self.stats.values_mut() {
+            stats.pinned_by.remove(owner);
+        }
+    }
+
+    /// 被固定模型的总占用（字节），不能通过淘汰释放
+    pub fn pinned_bytes(&self) -> usize {
+        self.stats.values().filter(|stats| stats.is_pinned()).map(|stats| stats.footprint).sum()
+    }
+
+    /// 选出下一个淘汰的模型；全部被固定时为None
+    ///
+    /// 有执行计划时，计划中不再使用的模型由策略从中选择，都还要使用时选最晚才用到的；
+    /// 没有计划时完全由策略选择
+    pub fn victim(&self) -> Option<String> {
+        self.victim_excluding(&HashSet::new())
+    }
+
+    /// 按淘汰顺序选出释放bytes所需的模型（不实际淘汰），返回选中的模型和它们的总占用；
+    /// 未固定的模型全部淘汰也不够时，总占用小于bytes
+    pub fn victims(&self, bytes: usize) -> (Vec<String>, usize) {
+        let mut chosen = HashSet::new();
+        let mut victims = Vec::new();
+        let mut freed = 0;
+        while freed < bytes {
+            let Some(victim) = self.victim_excluding(&chosen) else { break };
+            freed += self.stats.get(&victim).map_or(0, |stats| stats.footprint);
+            chosen.insert(victim.clone());
+            victims.push(victim);
+        }
+        (victims, freed)
+    }
+
+    fn victim_excluding(&self, excluded: &HashSet<String>) -> Option<String> {
+        let candidates: Vec<&SlotStats> = self.stats.values()
+            .filter(|stats| !stats.is_pinned() && !excluded.contains(&stats.model_id))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        if !self.next_uses.is_empty() {
+            let unused: Vec<&SlotStats> = candidates.iter().copied()
+                .filter(|stats| !self.next_uses.contains_key(&stats.model_id))
+                .collect();
+            if unused.is_empty() {
+                return candidates.iter()
+                    .max_by_key(|stats| self.next_uses.get(&stats.model_id))
+                    .map(|stats| stats.model_id.clone());
+            }
+            return self.policy.select_victim(&unused, self.clock).map(|stats| stats.model_id.clone());
+        }
+        self.policy.select_victim(&candidates, self.clock).map(|stats| stats.model_id.clone())
+    }
+}
+
+impl Default for ResidencyTracker {
+    fn default() -> Self {
+        Self::new(Box::new(LruPolicy))
+    }
+}
@@ -0,0 +1,164 @@
+//! 从GGUF元数据估算模型加载后的显存占用
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::ffi::{FfiError, LoadParams};
+
+/// GGUF魔数"GGUF"（小端）
+const GGUF_MAGIC: u32 = 0x4655_4747;
+
+// GGUF元数据值类型
+const TYPE_UINT8: u32 = 0;
+const TYPE_INT8: u32 = 1;
+const TYPE_UINT16: u32 = 2;
+const TYPE_INT16: u32 = 3;
+const TYPE_UINT32: u32 = 4;
+const TYPE_INT32: u32 = 5;
+const TYPE_FLOAT32: u32 = 6;
+const TYPE_BOOL: u32 = 7;
+const TYPE_STRING: u32 = 8;
+const TYPE_ARRAY: u32 = 9;
+const TYPE_UINT64: u32 = 10;
+const TYPE_INT64: u32 = 11;
+const TYPE_FLOAT64: u32 = 12;
+
+/// 估算所需的GGUF信息
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GgufInfo {
+    pub file_size: u64,
+    /// `<架构>.block_count`，元数据中没有时为None
+    pub block_count: Option<u64>,
+}
+
+impl GgufInfo {
+    /// 只读取文件头和元数据，读到block_count即停止
+    pub fn read(path: &Path) -> Result<Self, FfiError> {
+        let file = File::open(path).map_err(|_| FfiError::ModelNotFound(path.to_path_buf()))?;
+        let file_size = file.metadata()
+            .map_err(|e| FfiError::InvalidGguf(format!("{}: {}", path.display(), e)))?
+            .len();
+        let mut reader = GgufReader(BufReader::new(file));
+        let invalid = |reason: String| FfiError::InvalidGguf(format!("{}: {}", path.display(), reason));
+
+        if reader.u32().map_err(invalid)? != GGUF_MAGIC {
+            return Err(invalid("不是GGUF文件".into()));
+        }
+        // v1的计数和字符串长度为u32，已不再使用
+        let version = reader.u32().map_err(invalid)?;
+        if version < 2 {
+            return Err(invalid(format!("不支持的GGUF版本{}", version)));
+        }
+        let _tensor_count = reader.u64().map_err(invalid)?;
+        let kv_count = reader.u64().map_err(invalid)?;
+
+        let mut block_count = None;
+        for _ in 0..kv_count {
+            let key = reader.string().map_err(invalid)?;
+            let value_type = reader.u32().map_err(invalid)?;
+            if key.ends_with(".block_count") {
+                block_count = reader.integer(value_type).map_err(invalid)?;
+                break;
+            }
+            reader.skip_value(value_type).map_err(invalid)?;
+        }
+        Ok(Self { file_size, block_count })
+    }
+
+    /// 按卸载到GPU的层数估算显存：n_gpu_layers为0时完全在CPU上，为负数时全部卸载。
+    /// 输出层算作额外一层；不含KV缓存，由池的预留部分承担
+    pub fn footprint(&self, params: &LoadParams) -> usize {
+        if params.n_gpu_layers == 0 {
+            return 0;
+        }
+        let file_size = self.file_size as usize;
+        let Some(blocks) = self.block_count else { return file_size };
+        let layers = blocks as usize + 1;
+        let offloaded = if params.n_gpu_layers < 0 { layers } else { (params.n_gpu_layers as usize).min(layers) };
+        (file_size as u128 * offloaded as u128 / layers as u128) as usize
+    }
+}
+
+/// 估算以params加载path处模型的显存占用（字节）
+pub fn estimate_footprint(path: &Path, params: &LoadParams) -> Result<usize, FfiError> {
+    Ok(GgufInfo::read(path)?.footprint(params))
+}
+
+struct GgufReader<R: Read>(R);
+
+impl<R: Read> GgufReader<R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut buf = [0u8; N];
+        self.0.read_exact(&mut buf).map_err(|e| format!("元数据不完整: {}", e))?;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u64()?;
+        let mut buf = Vec::new();
+        (&mut self.0).take(len).read_to_end(&mut buf).map_err(|e| e.to_string())?;
+        if buf.len() as u64 != len {
+            return Err("字符串被截断".into());
+        }
+        String::from_utf8(buf).map_err(|_| "键不是有效的UTF-8".into())
+    }
+
+    // 整数类型的值，其他类型返回None
+    fn integer(&mut self, value_type: u32) -> Result<Option<u64>, String> {
+        Ok(match value_type {
+            TYPE_UINT8 | TYPE_INT8 => Some(self.bytes::<1>()?[0] as u64),
+            TYPE_UINT16 | TYPE_INT16 => Some(u16::from_le_bytes(self.bytes()?) as u64),
+            TYPE_UINT32 | TYPE_INT32 => Some(self.u32()? as u64),
+            TYPE_UINT64 | TYPE_INT64 => Some(self.u64()?),
+            _ => None,
+        })
+    }
+
+    fn skip(&mut self, len: u64) -> Result<(), String> {
+        let skipped = std::io::copy(&mut (&mut self.0).take(len), &mut std::io::sink()).map_err(|e| e.to_string())?;
+        if skipped != len {
+            return Err("元数据不完整".into());
+        }
+        Ok(())
+    }
+
+    fn skip_value(&mut self, value_type: u32) -> Result<(), String> {
+        if let Some(size) = fixed_size(value_type) {
+            return self.skip(size);
+        }
+        match value_type {
+            TYPE_STRING => {
+                let len = self.u64()?;
+                self.skip(len)
+            }
+            TYPE_ARRAY => {
+                let element_type = self.u32()?;
+                let count = self.u64()?;
+                match fixed_size(element_type) {
+                    Some(size) => self.skip(size.saturating_mul(count)),
+                    None => (0..count).try_for_each(|_| self.skip_value(element_type)),
+                }
+            }
+            other => Err(format!("未知的元数据类型{}", other)),
+        }
+    }
+}
+
+fn fixed_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        TYPE_UINT8 | TYPE_INT8 | TYPE_BOOL => Some(1),
+        TYPE_UINT16 | TYPE_INT16 => Some(2),
+        TYPE_UINT32 | TYPE_INT32 | TYPE_FLOAT32 => Some(4),
+        TYPE_UINT64 | TYPE_INT64 | TYPE_FLOAT64 => Some(8),
+        _ => None,
+    }
+}
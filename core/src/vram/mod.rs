@@ -1,3 +1,14 @@
 pub mod pool;
+pub mod footprint;
+pub mod eviction;
+
+#[cfg(test)]
+mod test_footprint;
+#[cfg(test)]
+mod test_eviction;
+#[cfg(test)]
+mod test_tiers;
 
 pub use pool::*;
+pub use footprint::{estimate_footprint, GgufInfo};
+pub use eviction::{policy_by_name, EvictionPolicy, LfuPolicy, LruPolicy, ReloadCostPolicy, ResidencyTracker, SizeWeightedPolicy, SlotStats};
@@ -1,126 +1,797 @@
-use std::sync::Arc;
-use std::time::{Instant};
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
-use crate::ffi::{LlamaModel, FfiError, LoadParams};
-use crate::ffi::lora::estimate_lora_vram;
-use crate::model::{LoraLoader, ModelError};
+use serde::Serialize;
+
+use crate::ffi::{FfiError, LoadParams};
+use crate::model::{default_backend, InferenceBackend, LoraLoader, ModelProvider};
+use crate::vram::eviction::{EvictionPolicy, ResidencyTracker, SlotStats};
+
+const MIB: usize = 1024 * 1024;
+
+/// 默认显存预算：6 GiB
+pub const DEFAULT_VRAM_BUDGET: usize = 6 * 1024 * MIB;
+
+/// 默认预留：1 GiB，留给KV缓存和LoRA
+pub const DEFAULT_VRAM_RESERVE: usize = 1024 * MIB;
+
+/// 默认CPU内存层预算：8 GiB
+pub const DEFAULT_RAM_BUDGET: usize = 8 * 1024 * MIB;
+
+// 同一模型正在由其它线程加载时，load_shared等待的轮询间隔
+const LOAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// 升级期间固定CPU层的副本，避免为腾出显存而降级的模型把它挤出内存
+const PROMOTING: &str = "promoting";
+
+/// 模型所在的层：显存、CPU内存（n_gpu_layers = 0，mmap页保持在页缓存中）或仅在磁盘上
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    Gpu,
+    Cpu,
+    Disk,
+}
+
+/// 一次层间迁移及其耗时
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TierTransition {
+    pub from: Tier,
+    pub to: Tier,
+    pub duration_ms: u64,
+}
 
 // 槽位结构
 pub struct Slot {
     pub model_id: String,
-    pub model: Arc<LlamaModel>,
-    pub last_access: Instant,
+    pub model: Arc<dyn ModelProvider>,
+    pub tier: Tier,
+    /// 降级/升级时按原路径和参数重新加载
+    pub path: PathBuf,
+    pub params: LoadParams,
     pub current_lora: Option<String>,
     pub current_lora_size: usize,
+    /// 以params加载时估算的显存占用（字节）
+    pub footprint: usize,
+    /// 位于CPU层时占用的内存（模型文件大小）
+    pub ram_bytes: usize,
+}
+
+/// get_slot_status中一个模型的驻留情况
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlotStatus {
+    pub model_id: String,
+    pub tier: Tier,
+    /// 位于GPU层时的显存占用（含LoRA）
+    pub vram_bytes: usize,
+    /// 位于CPU层时的内存占用
+    pub ram_bytes: usize,
+    pub pinned: bool,
+    pub lora: Option<String>,
+    /// 最近一次层间迁移，用于解释某一步为何变慢
+    pub last_transition: Option<TierTransition>,
+}
+
+/// 模型驻留变化，由执行器取走后作为执行事件发布
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolEvent {
+    /// 从磁盘冷加载到显存
+    Loaded(String),
+    /// 完全释放，只留在磁盘上
+    Evicted(String),
+    /// GPU层与CPU层之间的降级/升级
+    TierChanged { model_id: String, transition: TierTransition },
+    /// 从显存淘汰，显存已释放；随后降级到CPU层（TierChanged）或只留在磁盘上（Evicted）
+    Released(String),
+    /// 降级时以n_gpu_layers = 0重新加载失败，模型只留在磁盘上（随后为Evicted）
+    DemotionFailed { model_id: String, error: String },
+}
+
+/// 从显存淘汰后等待降级到CPU层的模型，池已为它预留内存
+///
+/// 重新加载是阻塞操作：由VramPool::complete_demotions在池的锁外调用load，再交回finish_demotion
+pub struct Demotion {
+    model_id: String,
+    path: PathBuf,
+    params: LoadParams,
+    ram_bytes: usize,
+    backend: Arc<dyn InferenceBackend>,
+}
+
+impl Demotion {
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// 以n_gpu_layers = 0重新加载（文件页仍在页缓存中）
+    pub fn load(self) -> DemotedModel {
+        let cpu_params = LoadParams { n_gpu_layers: 0, use_mmap: true, ..self.params };
+        let started = Instant::now();
+        let model = self.backend.load(&self.path, cpu_params);
+        DemotedModel { duration: started.elapsed(), model, demotion: self }
+    }
 }
 
-// VRAM池结构
+/// 降级重新加载的结果
+pub struct DemotedModel {
+    demotion: Demotion,
+    model: Result<Box<dyn ModelProvider>, FfiError>,
+    duration: Duration,
+}
+
+/// begin_load的结果
+pub enum LoadStep {
+    /// 模型已在所需层
+    Ready(Arc<dyn ModelProvider>),
+    /// 已腾出空间并预留占用，调用方在池的锁外加载后交回finish_load
+    Pending(PendingLoad),
+    /// 同一模型正在加载
+    InProgress,
+}
+
+/// 已预留占用、等待加载的模型
+pub struct PendingLoad {
+    model_id: String,
+    path: PathBuf,
+    params: LoadParams,
+    target: Tier,
+    footprint: usize,
+    ram_bytes: usize,
+    /// 从CPU层升级：CPU层的副本在加载期间被固定，成功后替换
+    promoting: bool,
+    backend: Arc<dyn InferenceBackend>,
+}
+
+impl PendingLoad {
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// 加载模型（阻塞操作），记录耗时供按重新加载代价淘汰
+    pub fn load(self) -> LoadedModel {
+        let started = Instant::now();
+        let model = self.backend.load(&self.path, self.params);
+        LoadedModel { load_time: started.elapsed(), model, pending: self }
+    }
+}
+
+/// PendingLoad的加载结果
+pub struct LoadedModel {
+    pending: PendingLoad,
+    model: Result<Box<dyn ModelProvider>, FfiError>,
+    load_time: Duration,
+}
+
+// VRAM池结构：模型占用不超过预算减去预留，LoRA可使用预留部分；
+// 从显存淘汰的模型先降级到CPU内存层，内存层再按LRU淘汰到磁盘
 pub struct VramPool {
-    capacity: usize, // = 2 (MVP)
+    /// 加载模型和估算占用的推理后端
+    backend: Arc<dyn InferenceBackend>,
+    budget: usize,
+    reserve: usize,
+    ram_budget: usize,
     slots: HashMap<String, Slot>,
-    lru: Vec<String>,
+    /// GPU层的访问统计、固定记录和淘汰策略（默认LRU）
+    residency: ResidencyTracker,
+    /// CPU层的访问统计，按LRU淘汰到磁盘
+    cpu_residency: ResidencyTracker,
+    /// 各模型最近一次迁移，完全淘汰后仍保留
+    transitions: BTreeMap<String, TierTransition>,
+    /// 尚未取走的降级
+    demotions: Vec<Demotion>,
+    /// 降级中的模型及为其预留的内存（字节），完成前计入CPU层占用
+    demoting: HashMap<String, usize>,
+    /// 加载中的模型：目标层及为其预留的显存（GPU层）或内存（CPU层）
+    loading: HashMap<String, (Tier, usize)>,
+    /// 模型 -> 固定它的持有者；模型尚未驻留时先记下，加载、升级或降级后重新应用
+    pins: HashMap<String, BTreeSet<String>>,
+    /// 运行ID -> 该运行计划中各模型的下次使用位置，各运行的计划合并后交给淘汰策略
+    plans: HashMap<String, HashMap<String, usize>>,
+    events: Vec<PoolEvent>,
 }
 
 impl VramPool {
-    /// 创建新的VRAM池
-    pub fn new(capacity: usize) -> Self {
+    /// 创建新的VRAM池：budget为可用显存总量，reserve为其中留给KV缓存和LoRA的部分（字节）
+    pub fn new(budget: usize, reserve: usize) -> Self {
         Self {
-            capacity,
+            backend: default_backend(),
+            budget,
+            reserve,
+            ram_budget: DEFAULT_RAM_BUDGET,
             slots: HashMap::new(),
-            lru: Vec::new(),
+            residency: ResidencyTracker::default(),
+            cpu_residency: ResidencyTracker::default(),
+            transitions: BTreeMap::new(),
+            demotions: Vec::new(),
+            demoting: HashMap::new(),
+            loading: HashMap::new(),
+            pins: HashMap::new(),
+            plans: HashMap::new(),
+            events: Vec::new(),
         }
     }
 
+    /// 设置CPU内存层预算（字节），0表示淘汰时直接释放而不降级
+    pub fn with_ram_budget(mut self, ram_budget: usize) -> Self {
+        self.ram_budget = ram_budget;
+        self
+    }
+
+    /// 替换推理后端（默认见default_backend），应在加载模型前设置
+    pub fn with_backend(mut self, backend: Arc<dyn InferenceBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// 按当前后端估算以params加载path处模型的显存占用（字节）
+    pub fn estimate_footprint(&self, path: &Path, params: &LoadParams) -> Result<usize, FfiError> {
+        self.backend.estimate_footprint(path, params)
+    }
+
     /// 加载模型
     pub fn load_model(&mut self, id: String, path: PathBuf, params: LoadParams)
-        -> Result<Arc<LlamaModel>, FfiError>
+        -> Result<Arc<dyn ModelProvider>, FfiError>
     {
-        // 如果缓存已满，淘汰最久未使用的模型
-        if self.slots.len() >= self.capacity {
-            self.evict_lru()?;
+        self.load_model_with_cancel(id, path, params, &AtomicBool::new(false))
+    }
+
+    /// 可取消的模型加载：加载完成时若已取消，立即释放模型而不占用槽位。
+    /// 已在所需层的ID直接返回并记一次访问；位于CPU层而需要显存时升级，比冷加载快。
+    /// 加载和随后的降级期间独占池，共享的池应使用load_shared
+    pub fn load_model_with_cancel(&mut self, id: String, path: PathBuf, params: LoadParams, cancel: &AtomicBool)
+        -> Result<Arc<dyn ModelProvider>, FfiError>
+    {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(FfiError::Cancelled);
+        }
+        match self.begin_load(id.clone(), path, params)? {
+            LoadStep::Ready(model) => Ok(model),
+            LoadStep::Pending(pending) => {
+                let loaded = pending.load();
+                let model = self.finish_load(loaded, cancel);
+                self.drain_demotions();
+                model
+            }
+            LoadStep::InProgress => Err(FfiError::Internal(format!("模型{}正在加载", id))),
+        }
+    }
+
+    /// 在共享的池中加载：预留占用和放入模型时持有锁，加载本身和随后的降级不持有。
+    /// 同一模型正在加载时等待其完成
+    pub fn load_shared(pool: &Mutex<VramPool>, id: &str, path: &Path, params: LoadParams, cancel: &AtomicBool)
+        -> Result<Arc<dyn ModelProvider>, FfiError>
+    {
+        let lock = || pool.lock().map_err(|_| FfiError::Internal("VRAM池锁中毒".into()));
+        let pending = loop {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(FfiError::Cancelled);
+            }
+            let step = lock()?.begin_load(id.to_string(), path.to_path_buf(), params)?;
+            match step {
+                LoadStep::Ready(model) => return Ok(model),
+                LoadStep::Pending(pending) => break pending,
+                LoadStep::InProgress => std::thread::sleep(LOAD_POLL_INTERVAL),
+            }
+        };
+        let loaded = pending.load();
+        let model = lock()?.finish_load(loaded, cancel);
+        Self::complete_demotions(pool);
+        model
+    }
+
+    /// 加载的第一步：已在所需层时直接返回；否则按淘汰策略腾出空间并预留占用。
+    /// 加载前估算占用，除去被固定的模型后仍放不下时直接失败
+    pub fn begin_load(&mut self, id: String, path: PathBuf, params: LoadParams) -> Result<LoadStep, FfiError> {
+        if self.loading.contains_key(&id) {
+            return Ok(LoadStep::InProgress);
+        }
+        let footprint = self.estimate_footprint(&path, &params)?;
+        let target = if footprint > 0 { Tier::Gpu } else { Tier::Cpu };
+        let current = self.slots.get(&id).map(|slot| slot.tier);
+        if current == Some(target) || current == Some(Tier::Gpu) {
+            if let Some(model) = self.get_model(&id) {
+                return Ok(LoadStep::Ready(model));
+            }
+        }
+        let available = self.model_budget().saturating_sub(self.residency.pinned_bytes());
+        if footprint > available {
+            return Err(FfiError::OutOfMemory { requested: footprint / MIB, available: available / MIB });
         }
 
-        // 加载新模型
-        let model = Arc::new(LlamaModel::from_file(&path, params)?);
+        // 先按淘汰策略选出放得下所需的模型并确认放得下，再实际淘汰；直接加载到CPU层时按LRU腾出内存
+        let ram_bytes = self.backend.model_size(&path);
+        let promoting = current.is_some();
+        if promoting {
+            self.cpu_residency.pin(&id, PROMOTING);
+        }
+        // 其余空间已预留给加载中的模型
+        let shortfall = (self.model_bytes() + footprint).saturating_sub(self.model_budget());
+        let (victims, freed) = self.residency.victims(shortfall);
+        if freed < shortfall {
+            self.cpu_residency.unpin(&id, PROMOTING);
+            let free = self.model_budget().saturating_sub(self.model_bytes()) + freed;
+            return Err(FfiError::OutOfMemory { requested: footprint / MIB, available: free / MIB });
+        }
+        if target == Tier::Cpu && !self.make_ram_room(ram_bytes) {
+            self.cpu_residency.unpin(&id, PROMOTING);
+            let free = self.ram_budget.saturating_sub(self.cpu_residency.pinned_bytes() + self.ram_reserved());
+            return Err(FfiError::OutOfMemory { requested: ram_bytes / MIB, available: free / MIB });
+        }
+        for victim in victims {
+            self.evict_model(&victim)?;
+        }
+        let reserved = match target {
+            Tier::Gpu => footprint,
+            _ => ram_bytes,
+        };
+        self.loading.insert(id.clone(), (target, reserved));
+        Ok(LoadStep::Pending(PendingLoad {
+            model_id: id,
+            path,
+            params,
+            target,
+            footprint,
+            ram_bytes,
+            promoting,
+            backend: Arc::clone(&self.backend),
+        }))
+    }
+
+    /// 加载的最后一步：释放预留并放入模型（从CPU层升级时替换其副本）。
+    /// 加载期间被取消时丢弃模型，CPU层的副本保留
+    pub fn finish_load(&mut self, loaded: LoadedModel, cancel: &AtomicBool) -> Result<Arc<dyn ModelProvider>, FfiError> {
+        let LoadedModel { pending, model, load_time } = loaded;
+        let PendingLoad { model_id: id, path, params, target, footprint, ram_bytes, promoting, .. } = pending;
+        self.loading.remove(&id);
+        self.cpu_residency.unpin(&id, PROMOTING);
+        let model: Arc<dyn ModelProvider> = Arc::from(model?);
+        if cancel.load(Ordering::SeqCst) {
+            drop(model);
+            return Err(FfiError::Cancelled);
+        }
 
-        // 插入到槽位中
+        // 替换CPU层的副本；降级尚未完成的副本作废
+        let promoted = promoting && self.slots.get(&id).map(|slot| slot.tier) == Some(Tier::Cpu);
+        if promoted {
+            self.remove_slot(&id);
+        }
+        self.cancel_demotion(&id);
         self.slots.insert(id.clone(), Slot {
             model_id: id.clone(),
             model: Arc::clone(&model),
-            last_access: Instant::now(),
+            tier: target,
+            path,
+            params,
             current_lora: None,
             current_lora_size: 0,
+            footprint,
+            ram_bytes,
         });
-
-        // 更新LRU列表
-        self.lru.push(id);
+        match target {
+            Tier::Gpu => self.residency.insert(&id, footprint, load_time),
+            _ => self.cpu_residency.insert(&id, ram_bytes, load_time),
+        }
+        self.apply_pins(&id);
+        if promoted {
+            self.record_transition(&id, Tier::Cpu, target, load_time);
+        } else {
+            self.transitions.remove(&id);
+            self.events.push(PoolEvent::Loaded(id));
+        }
 
         Ok(model)
     }
 
-    /// 获取模型
-    pub fn get_model(&mut self, id: &str) -> Option<Arc<LlamaModel>> {
-        self.slots.get_mut(id).map(|s| {
-            // 更新最后访问时间
-            s.last_access = Instant::now();
-            Arc::clone(&s.model)
-        })
-    }
-
-    /// 淘汰最久未使用的模型
-    pub fn evict_lru(&mut self) -> Result<(), FfiError> {
-        if let Some(oldest_id) = self.lru.first().cloned() {
-            // FIX: 先尝试卸载LoRA（如果有）
-            if let Some(slot) = self.slots.get(&oldest_id) {
-                if slot.current_lora.is_some() {
-                    if let Err(e) = slot.model.unload_lora() {
-                        eprintln!("警告: 淘汰模型{}时卸载LoRA失败: {:?}", oldest_id, e);
-                    }
+    /// 获取模型（GPU层或CPU层），并记一次访问
+    pub fn get_model(&mut self, id: &str) -> Option<Arc<dyn ModelProvider>> {
+        let slot = self.slots.get(id)?;
+        let model = Arc::clone(&slot.model);
+        match slot.tier {
+            Tier::Gpu => self.residency.touch(id),
+            _ => self.cpu_residency.touch(id),
+        };
+        Some(model)
+    }
+
+    /// 模型所在的层；从未加载过的模型为None
+    pub fn tier(&self, id: &str) -> Option<Tier> {
+        match self.slots.get(id) {
+            Some(slot) => Some(slot.tier),
+            None => self.transitions.contains_key(id).then_some(Tier::Disk),
+        }
+    }
+
+    /// 按淘汰策略从显存淘汰一个未固定的模型，返回其ID；全部被固定或显存中没有模型时为None
+    ///
+    /// CPU内存层放得下时预留内存并排入降级，由complete_demotions在池的锁外重新加载；否则完全释放
+    pub fn evict(&mut self) -> Result<Option<String>, FfiError> {
+        let Some(victim) = self.residency.victim() else { return Ok(None) };
+        self.evict_model(&victim)?;
+        Ok(Some(victim))
+    }
+
+    // 从显存淘汰指定模型，同evict
+    fn evict_model(&mut self, victim: &str) -> Result<(), FfiError> {
+        let victim = victim.to_string();
+        let Some(slot) = self.remove_slot(&victim) else { return Ok(()) };
+        
+        // 显式drop模型，确保GPU内存在降级重新加载前释放
+        let Slot { model, path, params, ram_bytes, .. } = slot;
+        drop(model);
+        self.events.push(PoolEvent::Released(victim.clone()));
+        
+        if !self.make_ram_room(ram_bytes) {
+            self.events.push(PoolEvent::Evicted(victim.clone()));
+            self.record_disk(&victim, Tier::Gpu);
+            return Ok(());
+        }
+        
+        self.demoting.insert(victim.clone(), ram_bytes);
+        self.demotions.push(Demotion {
+            model_id: victim,
+            path,
+            params,
+            ram_bytes,
+            backend: Arc::clone(&self.backend),
+        });
+        Ok(())
+    }
+
+    /// 取走排队中的降级，调用方在池的锁外加载后交回finish_demotion
+    pub fn take_demotions(&mut self) -> Vec<Demotion> {
+        std::mem::take(&mut self.demotions)
+    }
+
+    /// 将降级重新加载的模型放入CPU层；期间模型已被重新加载到显存时丢弃
+    pub fn finish_demotion(&mut self, demoted: DemotedModel) {
+        let DemotedModel { demotion, model, duration } = demoted;
+        let Demotion { model_id, path, params, ram_bytes, .. } = demotion;
+        if self.demoting.remove(&model_id).is_none() || self.slots.contains_key(&model_id) {
+            return;
+        }
+        match model {
+            Ok(model) => {
+                self.slots.insert(model_id.clone(), Slot {
+                    model_id: model_id.clone(),
+                    model: Arc::from(model),
+                    tier: Tier::Cpu,
+                    path,
+                    params,
+                    current_lora: None,
+                    current_lora_size: 0,
+                    footprint: 0,
+                    ram_bytes,
+                });
+                self.cpu_residency.insert(&model_id, ram_bytes, duration);
+                self.apply_pins(&model_id);
+                self.record_transition(&model_id, Tier::Gpu, Tier::Cpu, duration);
+            }
+            Err(e) => {
+                self.events.push(PoolEvent::DemotionFailed { model_id: model_id.clone(), error: e.to_string() });
+                self.events.push(PoolEvent::Evicted(model_id.clone()));
+                self.record_disk(&model_id, Tier::Gpu);
+            }
+        }
+    }
+
+    // 独占池时就地完成排队的降级，释放为其预留的内存
+    fn drain_demotions(&mut self) {
+        for demotion in self.take_demotions() {
+            let demoted = demotion.load();
+            self.finish_demotion(demoted);
+        }
+    }
+
+    /// 完成共享池中排队的全部降级：取出时持有锁，重新加载期间不持有
+    pub fn complete_demotions(pool: &Mutex<VramPool>) {
+        loop {
+            let demotions = match pool.lock() {
+                Ok(mut pool) => pool.take_demotions(),
+                Err(_) => return,
+            };
+            if demotions.is_empty() {
+                return;
+            }
+            for demotion in demotions {
+                let demoted = demotion.load();
+                if let Ok(mut pool) = pool.lock() {
+                    pool.finish_demotion(demoted);
                 }
             }
-            
-            // FIX: 从LRU列表中移除
-            self.lru.remove(0);
-            
-            // FIX: 从槽位中移除（触发Drop，自动释放VRAM）
-            if let Some(slot) = self.slots.remove(&oldest_id) {
-                // 显式drop模型，确保GPU内存立即释放
-                drop(slot.model);
-                println!("已淘汰模型，释放VRAM: {}", oldest_id);
+        }
+    }
+
+    /// 设置run_id的执行计划：in_use为正在使用的模型（下次使用为0），upcoming为接下来要用到的模型（按首次使用的先后）。
+    /// 淘汰时优先保留更早用到的模型；多个运行的计划同时生效，同一模型取最早的使用位置
+    pub fn plan_upcoming(&mut self, run_id: &str, in_use: &[String], upcoming: &[String]) {
+        let mut next_uses = HashMap::new();
+        for model_id in in_use {
+            next_uses.insert(model_id.clone(), 0);
+        }
+        for (position, model_id) in upcoming.iter().enumerate() {
+            next_uses.entry(model_id.clone()).or_insert(position + 1);
+        }
+        self.plans.insert(run_id.to_string(), next_uses);
+        self.apply_plans();
+    }
+
+    /// 清除run_id的执行计划，其他运行的计划保留
+    pub fn clear_plan(&mut self, run_id: &str) {
+        if self.plans.remove(run_id).is_some() {
+            self.apply_plans();
+        }
+    }
+
+    fn apply_plans(&mut self) {
+        let mut next_uses: HashMap<String, usize> = HashMap::new();
+        for (model_id, position) in self.plans.values().flatten() {
+            next_uses.entry(model_id.clone())
+                .and_modify(|earliest| *earliest = (*earliest).min(*position))
+                .or_insert(*position);
+        }
+        self.residency.set_next_uses(next_uses.clone());
+        self.cpu_residency.set_next_uses(next_uses);
+    }
+
+    /// 预取计划中即将用到的模型；已在所需层时返回false
+    ///
+    /// 腾出显存时只淘汰比它更晚才用到（或不再使用）的模型，否则放弃预取并返回false
+    pub fn prefetch(&mut self, id: String, path: PathBuf, params: LoadParams) -> Result<bool, FfiError> {
+        let Some(pending) = self.begin_prefetch(id, path, params)? else { return Ok(false) };
+        let loaded = pending.load();
+        let result = self.finish_load(loaded, &AtomicBool::new(false)).map(|_| true);
+        self.drain_demotions();
+        result
+    }
+
+    /// 在共享的池中预取，加载期间不持有锁（同load_shared）；加载完成时已取消则丢弃模型
+    pub fn prefetch_shared(pool: &Mutex<VramPool>, id: &str, path: &Path, params: LoadParams, cancel: &AtomicBool) -> Result<bool, FfiError> {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(FfiError::Cancelled);
+        }
+        let lock = || pool.lock().map_err(|_| FfiError::Internal("VRAM池锁中毒".into()));
+        let Some(pending) = lock()?.begin_prefetch(id.to_string(), path.to_path_buf(), params)? else { return Ok(false) };
+        let loaded = pending.load();
+        let result = lock()?.finish_load(loaded, cancel).map(|_| true);
+        Self::complete_demotions(pool);
+        result
+    }
+
+    // 预取的第一步：不需要或不值得预取时为None，否则同begin_load
+    fn begin_prefetch(&mut self, id: String, path: PathBuf, params: LoadParams) -> Result<Option<PendingLoad>, FfiError> {
+        if self.loading.contains_key(&id) {
+            return Ok(None);
+        }
+        let footprint = self.estimate_footprint(&path, &params)?;
+        let current = self.slots.get(&id).map(|slot| slot.tier);
+        if current == Some(Tier::Gpu) || (footprint == 0 && current.is_some()) {
+            return Ok(None);
+        }
+        // begin_load按同样的顺序淘汰，这里只确认要淘汰的模型都比它更晚才用到
+        let needed_at = self.residency.next_use(&id).unwrap_or(usize::MAX);
+        let shortfall = (self.model_bytes() + footprint).saturating_sub(self.model_budget());
+        let (victims, freed) = self.residency.victims(shortfall);
+        if freed < shortfall || victims.iter().any(|victim| self.residency.next_use(victim).map_or(false, |at| at <= needed_at)) {
+            return Ok(None);
+        }
+        match self.begin_load(id, path, params)? {
+            LoadStep::Pending(pending) => Ok(Some(pending)),
+            _ => Ok(None),
+        }
+    }
+
+    /// 替换淘汰策略，已有的访问统计保留
+    pub fn set_eviction_policy(&mut self, policy: Box<dyn EvictionPolicy>) {
+        self.residency.set_policy(policy);
+    }
+
+    pub fn eviction_policy(&self) -> &'static str {
+        self.residency.policy_name()
+    }
+
+    /// 由owner（通常为运行ID）固定模型，固定期间不会被淘汰。模型尚未驻留时记下，加载完成即固定；
+    /// 返回模型当前是否已驻留
+    pub fn pin(&mut self, model_id: &str, owner: &str) -> bool {
+        self.pins.entry(model_id.to_string()).or_default().insert(owner.to_string());
+        self.residency.pin(model_id, owner) || self.cpu_residency.pin(model_id, owner)
+    }
+
+    pub fn unpin(&mut self, model_id: &str, owner: &str) {
+        if let Some(owners) = self.pins.get_mut(model_id) {
+            owners.remove(owner);
+            if owners.is_empty() {
+                self.pins.remove(model_id);
             }
-            
-            Ok(())
-        } else {
-            Ok(())
         }
+        self.residency.unpin(model_id, owner);
+        self.cpu_residency.unpin(model_id, owner);
+    }
+
+    /// 释放owner固定的全部模型，运行结束时调用
+    pub fn release_pins(&mut self, owner: &str) {
+        self.pins.retain(|_, owners| {
+            owners.remove(owner);
+            !owners.is_empty()
+        });
+        self.residency.release(owner);
+        self.cpu_residency.release(owner);
+    }
+
+    // 模型进入GPU层或CPU层后恢复记下的固定
+    fn apply_pins(&mut self, model_id: &str) {
+        let Some(owners) = self.pins.get(model_id) else { return };
+        for owner in owners {
+            self.residency.pin(model_id, owner);
+            self.cpu_residency.pin(model_id, owner);
+        }
+    }
+
+    /// 驻留模型的访问统计与固定情况
+    pub fn slot_stats(&self, model_id: &str) -> Option<&SlotStats> {
+        self.residency.get(model_id).or_else(|| self.cpu_residency.get(model_id))
+    }
+
+    /// 取走上次调用以来的加载/淘汰/层间迁移记录
+    pub fn take_events(&mut self) -> Vec<PoolEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// 各模型所在的层、占用和最近一次迁移；已淘汰到磁盘的模型也列出。按模型ID排序
+    pub fn get_slot_status(&self) -> Vec<SlotStatus> {
+        let mut status: Vec<SlotStatus> = self.slots.values()
+            .map(|slot| SlotStatus {
+                model_id: slot.model_id.clone(),
+                tier: slot.tier,
+                vram_bytes: if slot.tier == Tier::Gpu { slot.footprint + slot.current_lora_size } else { 0 },
+                ram_bytes: if slot.tier == Tier::Cpu { slot.ram_bytes } else { 0 },
+                pinned: self.slot_stats(&slot.model_id).map_or(false, SlotStats::is_pinned),
+                lora: slot.current_lora.clone(),
+                last_transition: self.transitions.get(&slot.model_id).copied(),
+            })
+            .collect();
+        status.extend(self.transitions.iter()
+            .filter(|(model_id, _)| !self.slots.contains_key(*model_id))
+            .map(|(model_id, transition)| SlotStatus {
+                model_id: model_id.clone(),
+                tier: Tier::Disk,
+                vram_bytes: 0,
+                ram_bytes: 0,
+                pinned: false,
+                lora: None,
+                last_transition: Some(*transition),
+            }));
+        status.sort_by(|a, b| a.model_id.cmp(&b.model_id));
+        status
+    }
+
+    /// 显存预算（字节）
+    pub fn budget(&self) -> usize {
+        self.budget
     }
 
-    /// 获取当前槽位使用情况
-    pub fn get_slot_status(&self) -> Vec<String> {
-        self.slots.keys().cloned().collect()
+    /// 预算中留给KV缓存和LoRA的部分（字节）
+    pub fn reserve(&self) -> usize {
+        self.reserve
     }
 
-    /// 获取当前容量
-    pub fn capacity(&self) -> usize {
-        self.capacity
+    /// CPU内存层预算（字节）
+    pub fn ram_budget(&self) -> usize {
+        self.ram_budget
     }
 
-    /// 获取当前使用量
+    /// GPU层模型及其LoRA的估算占用（字节）
+    pub fn used_bytes(&self) -> usize {
+        self.gpu_slots().map(|s| s.footprint + s.current_lora_size).sum()
+    }
+
+    /// CPU层模型占用的内存（字节）
+    pub fn ram_used_bytes(&self) -> usize {
+        self.slots.values().filter(|s| s.tier == Tier::Cpu).map(|s| s.ram_bytes).sum()
+    }
+
+    /// 已驻留（GPU层或CPU层）的模型数
     pub fn usage(&self) -> usize {
         self.slots.len()
     }
 
+    /// 给定各模型的估算占用，最多能同时驻留几个（从小到大装入，至少为1）
+    pub fn model_slots(&self, footprints: &[usize]) -> usize {
+        let mut sorted = footprints.to_vec();
+        sorted.sort_unstable();
+        let mut total = 0usize;
+        let fitting = sorted.iter()
+            .take_while(|&&footprint| {
+                total = total.saturating_add(footprint);
+                total <= self.model_budget()
+            })
+            .count();
+        fitting.max(1)
+    }
+
+    fn model_budget(&self) -> usize {
+        self.budget.saturating_sub(self.reserve)
+    }
+
+    // GPU层模型的占用，加上为加载中的模型预留的显存
+    fn model_bytes(&self) -> usize {
+        let reserved: usize = self.loading.values().filter(|(tier, _)| *tier == Tier::Gpu).map(|(_, bytes)| bytes).sum();
+        self.gpu_slots().map(|s| s.footprint).sum::<usize>() + reserved
+    }
+
+    fn gpu_slots(&self) -> impl Iterator<Item = &Slot> {
+        self.slots.values().filter(|s| s.tier == Tier::Gpu)
+    }
+
+    // 为降级和加载到CPU层的模型预留的内存
+    fn ram_reserved(&self) -> usize {
+        self.demoting.values().sum::<usize>()
+            + self.loading.values().filter(|(tier, _)| *tier == Tier::Cpu).map(|(_, bytes)| bytes).sum::<usize>()
+    }
+
+    // 按LRU把CPU层的模型淘汰到磁盘，直到放得下ram_bytes；放不下时返回false。降级和加载中预留的内存不可淘汰
+    fn make_ram_room(&mut self, ram_bytes: usize) -> bool {
+        let reserved = self.ram_reserved();
+        if ram_bytes + reserved > self.ram_budget.saturating_sub(self.cpu_residency.pinned_bytes()) {
+            return false;
+        }
+        while self.ram_used_bytes() + reserved + ram_bytes > self.ram_budget {
+            let Some(victim) = self.cpu_residency.victim() else { return false };
+            self.remove_slot(&victim);
+            self.events.push(PoolEvent::Evicted(victim.clone()));
+            self.record_disk(&victim, Tier::Cpu);
+        }
+        true
+    }
+
+    // 模型已被重新加载：丢弃排队中的降级并释放预留，已取走的降级在finish_demotion时丢弃
+    fn cancel_demotion(&mut self, model_id: &str) {
+        self.demoting.remove(model_id);
+        self.demotions.retain(|demotion| demotion.model_id != model_id);
+    }
+
+    // 从所在层移除，先卸载LoRA
+    fn remove_slot(&mut self, model_id: &str) -> Option<Slot> {
+        let slot = self.slots.remove(model_id)?;
+        if slot.current_lora.is_some() {
+            if let Err(e) = slot.model.remove_lora() {
+                eprintln!("警告: 淘汰模型{}时卸载LoRA失败: {:?}", model_id, e);
+            }
+        }
+        match slot.tier {
+            Tier::Gpu => self.residency.remove(model_id),
+            _ => self.cpu_residency.remove(model_id),
+        };
+        Some(slot)
+    }
+
+    fn record_disk(&mut self, model_id: &str, from: Tier) {
+        self.transitions.insert(model_id.to_string(), TierTransition { from, to: Tier::Disk, duration_ms: 0 });
+    }
+
+    fn record_transition(&mut self, model_id: &str, from: Tier, to: Tier, duration: Duration) {
+        let transition = TierTransition { from, to, duration_ms: duration.as_millis() as u64 };
+        self.transitions.insert(model_id.to_string(), transition);
+        self.events.push(PoolEvent::TierChanged { model_id: model_id.to_string(), transition });
+    }
+
     pub fn load_lora(&mut self, model_id: &str, lora_path: &Path) -> Result<(), FfiError> {
-        let slot = self.slots.get_mut(model_id).ok_or_else(|| FfiError::ModelNotFound(PathBuf::from(model_id)))?;
+        // 检查空闲显存是否足够（替换的LoRA占用会被释放）
+        let replaced = self.slots.get(model_id)
+            .ok_or_else(|| FfiError::ModelNotFound(PathBuf::from(model_id)))?
+            .current_lora_size;
+        let available = self.available_vram() + replaced;
         
         // 校验 LoRA 文件
         let metadata = LoraLoader::validate(lora_path)
             .map_err(|e| FfiError::Internal(format!("LoRA 验证失败: {:?}", e)))?;
         
-        // 检查空闲显存是否足够
-        let available = self.available_vram()?;
         if metadata.estimated_vram > available {
             return Err(FfiError::OutOfMemory { 
                 requested: metadata.estimated_vram/1024/1024, 
@@ -129,6 +800,7 @@ impl VramPool {
         }
         
         // 加载 LoRA
+        let slot = self.slots.get_mut(model_id).ok_or_else(|| FfiError::ModelNotFound(PathBuf::from(model_id)))?;
         slot.model.apply_lora(lora_path)
             .map_err(|e| FfiError::Internal(format!("LoRA 加载失败: {:?}", e)))?;
         
@@ -142,7 +814,7 @@ impl VramPool {
         let slot = self.slots.get_mut(model_id).ok_or_else(|| FfiError::ModelNotFound(PathBuf::from(model_id)))?;
         
         // 卸载 LoRA
-        slot.model.unload_lora()
+        slot.model.remove_lora()
             .map_err(|e| FfiError::Internal(format!("LoRA 卸载失败: {:?}", e)))?;
         
         slot.current_lora = None;
@@ -162,9 +834,14 @@ impl VramPool {
         self.load_lora(model_id, &lora_path)
     }
     
-    fn available_vram(&self) -> Result<usize, FfiError> {
-        let total: usize = 6 * 1024 * 1024 * 1024;
-        let used: usize = self.slots.values().map(|s| s.model.size_bytes() + s.current_lora_size).sum::<Result<usize, FfiError>>()?;
-        Ok(total.saturating_sub(used))
+    // LoRA可使用包括预留在内的全部剩余预算
+    fn available_vram(&self) -> usize {
+        self.budget.saturating_sub(self.used_bytes())
+    }
+}
+
+impl Default for VramPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_VRAM_BUDGET, DEFAULT_VRAM_RESERVE)
     }
 }
@@ -0,0 +1,103 @@
+use super::{policy_by_name, LfuPolicy, ReloadCostPolicy, ResidencyTracker, SizeWeightedPolicy};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+
+    // 依次加载a、b、c，占用与加载耗时各不相同
+    fn tracker() -> ResidencyTracker {
+        let mut tracker = ResidencyTracker::default();
+        tracker.insert("a", 4 * MIB, Duration::from_millis(300));
+        tracker.insert("b", MIB, Duration::from_millis(900));
+        tracker.insert("c", 2 * MIB, Duration::from_millis(100));
+        tracker
+    }
+
+    #[test]
+    fn test_lru_follows_access_order() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.victim().as_deref(), Some("a"));
+
+        // 访问a后最久未使用的是b，而不是按加载顺序
+        assert!(tracker.touch("a"));
+        assert_eq!(tracker.victim().as_deref(), Some("b"));
+        tracker.remove("b");
+        assert_eq!(tracker.victim().as_deref(), Some("c"));
+        assert!(!tracker.touch("b"));
+    }
+
+    #[test]
+    fn test_pinned_models_not_evicted_until_released() {
+        let mut tracker = tracker();
+        assert!(tracker.pin("a", "run-1"));
+        assert!(tracker.pin("b", "run-2"));
+        assert!(!tracker.pin("missing", "run-1"));
+        assert_eq!(tracker.pinned_bytes(), 5 * MIB);
+        assert_eq!(tracker.victim().as_deref(), Some("c"));
+
+        assert!(tracker.pin("c", "run-1"));
+        assert_eq!(tracker.victim(), None);
+
+        // 释放run-1的全部固定，b仍被run-2固定
+        tracker.release("run-1");
+        assert_eq!(tracker.pinned_bytes(), MIB);
+        assert_eq!(tracker.victim().as_deref(), Some("a"));
+        tracker.unpin("b", "run-2");
+        assert_eq!(tracker.pinned_bytes(), 0);
+    }
+
+    #[test]
+    fn test_alternative_policies() {
+        let mut tracker = tracker();
+        tracker.touch("a");
+        tracker.touch("a");
+        tracker.touch("c");
+
+        tracker.set_policy(Box::new(LfuPolicy));
+        assert_eq!(tracker.victim().as_deref(), Some("b"));
+
+        // 空闲时间乘以占用：a刚被访问但占用大，仍大于空闲最久的b
+        tracker.set_policy(Box::new(SizeWeightedPolicy));
+        assert_eq!(tracker.victim().as_deref(), Some("a"));
+
+        tracker.set_policy(Box::new(ReloadCostPolicy));
+        assert_eq!(tracker.victim().as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn test_policy_by_name() {
+        for name in ["lru", "lfu", "size", "reload_cost"] {
+            assert_eq!(policy_by_name(name).map(|policy| policy.name()), Some(name));
+        }
+        assert!(policy_by_name("fifo").is_none());
+    }
+
+    fn next_uses(models: &[&str]) -> HashMap<String, usize> {
+        models.iter().enumerate().map(|(position, model)| (model.to_string(), position)).collect()
+    }
+
+    #[test]
+    fn test_plan_keeps_models_needed_sooner() {
+        let mut tracker = tracker();
+
+        // 计划中不再使用的c优先淘汰，即使a最久未使用
+        tracker.set_next_uses(next_uses(&["b", "a"]));
+        assert_eq!(tracker.victim().as_deref(), Some("c"));
+        assert_eq!(tracker.next_use("a"), Some(1));
+
+        // 都还要使用时淘汰最晚才用到的
+        tracker.set_next_uses(next_uses(&["c", "a", "b"]));
+        assert_eq!(tracker.victim().as_deref(), Some("b"));
+        tracker.pin("b", "run-1");
+        assert_eq!(tracker.victim().as_deref(), Some("a"));
+
+        // 清除计划后回到策略本身
+        tracker.set_next_uses(HashMap::new());
+        assert_eq!(tracker.victim().as_deref(), Some("a"));
+        assert_eq!(tracker.next_use("a"), None);
+    }
+}
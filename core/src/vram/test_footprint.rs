@@ -0,0 +1,100 @@
+use super::{estimate_footprint, GgufInfo, VramPool};
+use crate::ffi::{FfiError, LoadParams};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+
+    fn gguf_string(buf: &mut Vec<u8>, value: &str) {
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        buf.extend_from_slice(value.as_bytes());
+    }
+
+    // 含架构、数组和block_count元数据的GGUF文件，总大小为size字节
+    fn write_gguf(name: &str, block_count: u32, size: usize) -> std::path::PathBuf {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"GGUF");
+        buf.extend_from_slice(&3u32.to_le_bytes());
+        buf.extend_from_slice(&0u64.to_le_bytes());
+        buf.extend_from_slice(&3u64.to_le_bytes());
+
+        gguf_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        gguf_string(&mut buf, "llama");
+
+        gguf_string(&mut buf, "tokenizer.ggml.tokens");
+        buf.extend_from_slice(&9u32.to_le_bytes());
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        buf.extend_from_slice(&2u64.to_le_bytes());
+        gguf_string(&mut buf, "<s>");
+        gguf_string(&mut buf, "</s>");
+
+        gguf_string(&mut buf, "llama.block_count");
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        buf.extend_from_slice(&block_count.to_le_bytes());
+        buf.resize(size, 0);
+
+        let path = std::env::temp_dir().join(format!("microflow-{}-{}.gguf", name, std::process::id()));
+        std::fs::write(&path, buf).unwrap();
+        path
+    }
+
+    fn gpu_layers(n_gpu_layers: i32) -> LoadParams {
+        LoadParams { n_gpu_layers, ..LoadParams::default() }
+    }
+
+    #[test]
+    fn test_footprint_scales_with_gpu_layers() {
+        let path = write_gguf("layers", 31, 32 * MIB);
+        let info = GgufInfo::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(info.block_count, Some(31));
+        assert_eq!(info.footprint(&gpu_layers(0)), 0);
+        assert_eq!(info.footprint(&gpu_layers(16)), 16 * MIB);
+        assert_eq!(info.footprint(&gpu_layers(-1)), 32 * MIB);
+        assert_eq!(info.footprint(&gpu_layers(999)), 32 * MIB);
+    }
+
+    #[test]
+    fn test_invalid_or_missing_file() {
+        let path = std::env::temp_dir().join(format!("microflow-not-gguf-{}.bin", std::process::id()));
+        std::fs::write(&path, b"not a gguf file at all").unwrap();
+        let invalid = estimate_footprint(&path, &gpu_layers(-1));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(matches!(invalid, Err(FfiError::InvalidGguf(_))));
+        assert!(matches!(
+            estimate_footprint(std::path::Path::new("/nonexistent/model.gguf"), &gpu_layers(-1)),
+            Err(FfiError::ModelNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_model_larger_than_budget_rejected_before_loading() {
+        let path = write_gguf("too-large", 31, 8 * MIB);
+        // 预算6MiB，预留2MiB，模型最多可用4MiB
+        let mut pool = VramPool::new(6 * MIB, 2 * MIB);
+        let result = pool.load_model("large".to_string(), path.clone(), gpu_layers(-1));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(matches!(result, Err(FfiError::OutOfMemory { requested: 8, available: 4 })));
+        assert_eq!(pool.usage(), 0);
+        assert!(pool.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_model_slots_follow_budget() {
+        let pool = VramPool::new(8 * MIB, 2 * MIB);
+
+        // 按字节判断能否同时驻留，而不是按个数
+        assert_eq!(pool.model_slots(&[MIB / 2, 5 * MIB]), 2);
+        assert_eq!(pool.model_slots(&[4 * MIB, 4 * MIB]), 1);
+        assert_eq!(pool.model_slots(&[MIB, MIB, 3 * MIB, 4 * MIB]), 3);
+        // 单个模型放不下时也至少为1，加载时再报告内存不足
+        assert_eq!(pool.model_slots(&[16 * MIB]), 1);
+        assert_eq!(pool.model_slots(&[]), 1);
+    }
+}
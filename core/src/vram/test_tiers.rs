@@ -0,0 +1,310 @@
+use super::{LoadStep, PoolEvent, Tier, TierTransition, VramPool};
+use crate::ffi::{FfiError, LoadParams};
+use crate::model::{MockBackend, MockModelSpec};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+
+    // 预算10MiB，预留2MiB：两个6MiB的模型不能同时在显存中
+    fn mock_pool(backend: MockBackend) -> (VramPool, Arc<MockBackend>) {
+        let backend = Arc::new(backend);
+        let pool = VramPool::new(10 * MIB, 2 * MIB).with_backend(backend.clone());
+        (pool, backend)
+    }
+
+    fn two_models() -> MockBackend {
+        MockBackend::new()
+            .with_model("a", MockModelSpec::new(6 * MIB))
+            .with_model("b", MockModelSpec::new(6 * MIB))
+    }
+
+    fn full_offload() -> LoadParams {
+        LoadParams { n_gpu_layers: -1, ..LoadParams::default() }
+    }
+
+    fn load(pool: &mut VramPool, id: &str) -> Result<(), FfiError> {
+        pool.load_model(id.to_string(), PathBuf::from(id), full_offload()).map(|_| ())
+    }
+
+    // 两阶段加载，被淘汰的模型留在降级队列中，同VramPool::load_shared完成降级之前
+    fn load_deferred(pool: &mut VramPool, id: &str) {
+        if let LoadStep::Pending(pending) = pool.begin_load(id.to_string(), PathBuf::from(id), full_offload()).unwrap() {
+            let loaded = pending.load();
+            pool.finish_load(loaded, &AtomicBool::new(false)).unwrap();
+        }
+    }
+
+    // 在池外完成排队的降级，同VramPool::complete_demotions
+    fn demote(pool: &mut VramPool) {
+        for demotion in pool.take_demotions() {
+            let demoted = demotion.load();
+            pool.finish_demotion(demoted);
+        }
+    }
+
+    fn transition(event: &PoolEvent) -> Option<(Tier, Tier)> {
+        match event {
+            PoolEvent::TierChanged { transition: TierTransition { from, to, .. }, .. } => Some((*from, *to)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_evicted_model_demoted_then_promoted() {
+        let (mut pool, backend) = mock_pool(two_models());
+        assert_eq!(pool.backend_name(), "mock");
+
+        load(&mut pool, "a").unwrap();
+        load_deferred(&mut pool, "b");
+        // a释放显存后排队降级，加载b时不重新加载a
+        assert_eq!(backend.load_count(Path::new("a")), 1);
+        assert_eq!(pool.get_model("a").map(|_| ()), None);
+        demote(&mut pool);
+        // a降级到CPU层（重新加载一次），为b腾出显存
+        assert_eq!(pool.tier("a"), Some(Tier::Cpu));
+        assert_eq!(pool.tier("b"), Some(Tier::Gpu));
+        assert_eq!(backend.load_count(Path::new("a")), 2);
+        let events = pool.take_events();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], PoolEvent::Loaded("a".to_string()));
+        assert_eq!(events[1], PoolEvent::Released("a".to_string()));
+        assert_eq!(events[2], PoolEvent::Loaded("b".to_string()));
+        assert_eq!(transition(&events[3]), Some((Tier::Gpu, Tier::Cpu)));
+
+        // 再次需要a时从CPU层升级，b降级（独占池的加载就地完成降级）
+        load(&mut pool, "a").unwrap();
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+        assert_eq!(pool.tier("b"), Some(Tier::Cpu));
+        let transitions: Vec<_> = pool.take_events().iter().filter_map(transition).collect();
+        assert_eq!(transitions, vec![(Tier::Cpu, Tier::Gpu), (Tier::Gpu, Tier::Cpu)]);
+
+        let status = pool.get_slot_status();
+        assert_eq!((status[0].tier, status[0].vram_bytes, status[0].ram_bytes), (Tier::Gpu, 6 * MIB, 0));
+        assert_eq!((status[1].tier, status[1].vram_bytes, status[1].ram_bytes), (Tier::Cpu, 0, 6 * MIB));
+        assert_eq!(pool.used_bytes(), 6 * MIB);
+    }
+
+    #[test]
+    fn test_without_ram_budget_evicts_to_disk() {
+        let (pool, _backend) = mock_pool(two_models());
+        let mut pool = pool.with_ram_budget(0);
+
+        load(&mut pool, "a").unwrap();
+        load(&mut pool, "b").unwrap();
+        assert_eq!(pool.tier("a"), Some(Tier::Disk));
+        assert_eq!(pool.usage(), 1);
+        assert!(pool.take_demotions().is_empty());
+        let events = pool.take_events();
+        assert_eq!(events[1..3], [PoolEvent::Released("a".to_string()), PoolEvent::Evicted("a".to_string())]);
+
+        let status = pool.get_slot_status();
+        assert_eq!(status[0].model_id, "a");
+        assert_eq!(status[0].last_transition.map(|t| (t.from, t.to)), Some((Tier::Gpu, Tier::Disk)));
+    }
+
+    #[test]
+    fn test_failed_or_blocked_loads_leave_pool_unchanged() {
+        let backend = two_models().with_model("broken", MockModelSpec { fail_load: true, ..MockModelSpec::new(MIB) });
+        let (mut pool, _backend) = mock_pool(backend);
+
+        assert!(matches!(load(&mut pool, "broken"), Err(FfiError::Internal(_))));
+        assert!(matches!(load(&mut pool, "missing"), Err(FfiError::ModelNotFound(_))));
+        assert_eq!(pool.usage(), 0);
+        assert!(pool.take_events().is_empty());
+
+        // a被固定时b放不下，不淘汰a
+        load(&mut pool, "a").unwrap();
+        assert!(pool.pin("a", "run-1"));
+        assert!(matches!(load(&mut pool, "b"), Err(FfiError::OutOfMemory { requested: 6, available: 2 })));
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+        assert_eq!(pool.tier("b"), None);
+    }
+
+    #[test]
+    fn test_load_that_cannot_fit_evicts_nothing() {
+        // c加载中预留6MiB，a占2MiB：淘汰a也放不下4MiB的b，a保留在显存
+        let backend = MockBackend::new()
+            .with_model("a", MockModelSpec::new(2 * MIB))
+            .with_model("b", MockModelSpec::new(4 * MIB))
+            .with_model("c", MockModelSpec::new(6 * MIB));
+        let (mut pool, _backend) = mock_pool(backend);
+        load(&mut pool, "a").unwrap();
+        let step = pool.begin_load("c".to_string(), PathBuf::from("c"), full_offload()).unwrap();
+        assert!(matches!(step, LoadStep::Pending(_)));
+        pool.take_events();
+
+        assert!(matches!(load(&mut pool, "b"), Err(FfiError::OutOfMemory { requested: 4, available: 2 })));
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+        assert!(pool.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_clearing_one_run_plan_keeps_others() {
+        // 显存放得下两个3MiB模型：run-1还要用a，run-2结束后加载c时淘汰b而不是更早加载的a
+        let backend = MockBackend::new()
+            .with_model("a", MockModelSpec::new(3 * MIB))
+            .with_model("b", MockModelSpec::new(3 * MIB))
+            .with_model("c", MockModelSpec::new(3 * MIB));
+        let (mut pool, _backend) = mock_pool(backend);
+        load(&mut pool, "a").unwrap();
+        load(&mut pool, "b").unwrap();
+        pool.plan_upcoming("run-1", &[], &["a".to_string()]);
+        pool.plan_upcoming("run-2", &[], &["b".to_string()]);
+        pool.clear_plan("run-2");
+
+        load(&mut pool, "c").unwrap();
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+        assert_eq!(pool.tier("b"), Some(Tier::Cpu));
+    }
+
+    #[test]
+    fn test_cpu_load_without_ram_room_fails() {
+        let (pool, _backend) = mock_pool(two_models());
+        let mut pool = pool.with_ram_budget(4 * MIB);
+        let cpu_only = LoadParams { n_gpu_layers: 0, ..LoadParams::default() };
+
+        let result = pool.load_model("a".to_string(), PathBuf::from("a"), cpu_only);
+        assert!(matches!(result, Err(FfiError::OutOfMemory { requested: 6, available: 4 })));
+        assert_eq!(pool.tier("a"), None);
+    }
+
+    #[test]
+    fn test_full_ram_tier_evicts_least_recent_to_disk() {
+        // 内存层只放得下一个模型：b降级时把更早降级的a淘汰到磁盘
+        let backend = two_models().with_model("c", MockModelSpec::new(6 * MIB));
+        let (pool, _backend) = mock_pool(backend);
+        let mut pool = pool.with_ram_budget(8 * MIB);
+
+        load(&mut pool, "a").unwrap();
+        load(&mut pool, "b").unwrap();
+        assert_eq!(pool.tier("a"), Some(Tier::Cpu));
+        pool.take_events();
+
+        load(&mut pool, "c").unwrap();
+        assert_eq!(pool.tier("a"), Some(Tier::Disk));
+        assert_eq!(pool.tier("b"), Some(Tier::Cpu));
+        assert_eq!(pool.tier("c"), Some(Tier::Gpu));
+        assert_eq!(pool.ram_used_bytes(), 6 * MIB);
+        let events = pool.take_events();
+        assert_eq!(events[..2], [PoolEvent::Released("b".to_string()), PoolEvent::Evicted("a".to_string())]);
+        assert_eq!(transition(&events[3]), Some((Tier::Gpu, Tier::Cpu)));
+
+        let status = pool.get_slot_status();
+        assert_eq!(status[0].last_transition.map(|t| (t.from, t.to)), Some((Tier::Cpu, Tier::Disk)));
+    }
+
+    #[test]
+    fn test_promoting_model_kept_in_ram_tier() {
+        // 升级a时b被淘汰出显存；内存层只放得下一个模型，但正在升级的a不会被挤到磁盘，b直接释放
+        let (pool, backend) = mock_pool(two_models());
+        let mut pool = pool.with_ram_budget(8 * MIB);
+
+        load(&mut pool, "a").unwrap();
+        load(&mut pool, "b").unwrap();
+        pool.take_events();
+
+        load(&mut pool, "a").unwrap();
+        assert!(pool.take_demotions().is_empty());
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+        assert_eq!(pool.tier("b"), Some(Tier::Disk));
+        assert_eq!(backend.load_count(Path::new("a")), 3);
+        assert!(!pool.slot_stats("a").unwrap().is_pinned());
+        let events = pool.take_events();
+        assert_eq!(events[..2], [PoolEvent::Released("b".to_string()), PoolEvent::Evicted("b".to_string())]);
+        assert_eq!(transition(&events[2]), Some((Tier::Cpu, Tier::Gpu)));
+    }
+
+    #[test]
+    fn test_demotion_reloads_outside_pool_lock() {
+        let slow = MockModelSpec { load_delay: Duration::from_millis(300), ..MockModelSpec::new(6 * MIB) };
+        let (pool, _backend) = mock_pool(MockBackend::new().with_model("a", slow).with_model("b", MockModelSpec::new(6 * MIB)));
+        let pool = Arc::new(Mutex::new(pool));
+        {
+            let mut locked = pool.lock().unwrap();
+            load(&mut locked, "a").unwrap();
+            load_deferred(&mut locked, "b");
+        }
+
+        let demoting = Arc::clone(&pool);
+        let worker = std::thread::spawn(move || VramPool::complete_demotions(&demoting));
+        std::thread::sleep(Duration::from_millis(50));
+        // 降级重新加载期间池的锁可用
+        let started = Instant::now();
+        let tier = pool.lock().unwrap().tier("a");
+        assert!(started.elapsed() < Duration::from_millis(150));
+        assert_ne!(tier, Some(Tier::Cpu));
+        worker.join().unwrap();
+        assert_eq!(pool.lock().unwrap().tier("a"), Some(Tier::Cpu));
+    }
+
+    #[test]
+    fn test_stale_demotion_discarded() {
+        // 降级完成前模型已被重新加载到显存：降级的副本丢弃
+        let (mut pool, _backend) = mock_pool(two_models());
+        load(&mut pool, "a").unwrap();
+        load_deferred(&mut pool, "b");
+        let demotions = pool.take_demotions();
+
+        load(&mut pool, "a").unwrap();
+        for demotion in demotions {
+            let demoted = demotion.load();
+            pool.finish_demotion(demoted);
+        }
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+        assert_eq!(pool.tier("b"), Some(Tier::Cpu));
+        assert_eq!(pool.usage(), 2);
+    }
+
+    #[test]
+    fn test_prefetch_loads_outside_pool_lock() {
+        let slow = MockModelSpec { load_delay: Duration::from_millis(300), ..MockModelSpec::new(6 * MIB) };
+        let (pool, backend) = mock_pool(MockBackend::new().with_model("a", slow));
+        let pool = Arc::new(Mutex::new(pool));
+
+        let prefetching = Arc::clone(&pool);
+        let worker = std::thread::spawn(move || VramPool::prefetch_shared(&prefetching, "a", Path::new("a"), full_offload(), &AtomicBool::new(false)));
+        std::thread::sleep(Duration::from_millis(50));
+        let started = Instant::now();
+        assert_eq!(pool.lock().unwrap().tier("a"), None);
+        assert!(started.elapsed() < Duration::from_millis(150));
+
+        // 预取进行中时需要同一模型：等待预取完成而不重复加载
+        let model = VramPool::load_shared(&pool, "a", Path::new("a"), full_offload(), &AtomicBool::new(false));
+        assert!(model.is_ok());
+        assert!(worker.join().unwrap().unwrap());
+        assert_eq!(backend.load_count(Path::new("a")), 1);
+        assert_eq!(pool.lock().unwrap().tier("a"), Some(Tier::Gpu));
+    }
+
+    #[test]
+    fn test_pending_load_reserves_vram() {
+        // a加载中时已预留显存，b不能再挤进来
+        let (mut pool, _backend) = mock_pool(two_models());
+        let Ok(LoadStep::Pending(pending)) = pool.begin_load("a".to_string(), PathBuf::from("a"), full_offload()) else {
+            panic!("a应等待加载");
+        };
+        assert!(matches!(pool.begin_load("a".to_string(), PathBuf::from("a"), full_offload()), Ok(LoadStep::InProgress)));
+        assert!(matches!(load(&mut pool, "b"), Err(FfiError::OutOfMemory { requested: 6, available: 2 })));
+
+        let loaded = pending.load();
+        pool.finish_load(loaded, &AtomicBool::new(false)).unwrap();
+        assert_eq!(pool.tier("a"), Some(Tier::Gpu));
+
+        // 加载期间被取消：丢弃模型并释放预留
+        let Ok(LoadStep::Pending(pending)) = pool.begin_load("b".to_string(), PathBuf::from("b"), full_offload()) else {
+            panic!("b应等待加载");
+        };
+        let loaded = pending.load();
+        assert!(matches!(pool.finish_load(loaded, &AtomicBool::new(true)), Err(FfiError::Cancelled)));
+        assert_eq!(pool.tier("b"), None);
+        load(&mut pool, "b").unwrap();
+        assert_eq!(pool.tier("b"), Some(Tier::Gpu));
+    }
+}
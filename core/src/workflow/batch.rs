@@ -0,0 +1,167 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::engine::CancellationToken;
+use crate::workflow::executor::{WorkflowError, WorkflowExecutor};
+use crate::workflow::outputs::{outputs_to_json, JsonOutputs};
+use crate::workflow::params::ParameterValues;
+use crate::workflow::serialization::WorkflowData;
+
+/// 批量运行中单次运行的结果，结果文件每行一条
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    /// 参数组在输入文件中的序号（从0开始）
+    pub index: usize,
+    pub parameters: ParameterValues,
+    pub run_id: Option<String>,
+    /// "Completed" / "Failed" / "Cancelled"
+    pub status: String,
+    /// 工作流输出（节点ID -> 端口 -> 值），无法表示为JSON的值（流）省略
+    pub outputs: JsonOutputs,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 批量运行汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// 被取消而未运行的参数组数
+    pub skipped: usize,
+}
+
+/// 读取参数组：.csv首行为参数名、其余每行一组（值为文本，按参数类型转换）；
+/// .jsonl每行一个对象。空行忽略
+pub fn read_parameter_sets(path: &Path) -> Result<Vec<ParameterValues>, WorkflowError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| WorkflowError::Batch(format!("读取{}失败: {}", path.display(), e)))?;
+    let is_csv = path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        parse_csv(&content)
+    } else {
+        parse_jsonl(&content)
+    }
+}
+
+fn parse_jsonl(content: &str) -> Result<Vec<ParameterValues>, WorkflowError> {
+    content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line)
+            .map_err(|e| WorkflowError::Batch(format!("第{}行不是参数对象: {}", i + 1, e))))
+        .collect()
+}
+
+fn parse_csv(content: &str) -> Result<Vec<ParameterValues>, WorkflowError> {
+    let mut rows = csv_rows(content.trim_start_matches('\u{feff}'))?.into_iter();
+    let Some(header) = rows.next() else { return Ok(Vec::new()) };
+
+    rows.enumerate()
+        .filter(|(_, row)| row.iter().any(|field| !field.is_empty()))
+        .map(|(i, row)| {
+            if row.len() != header.len() {
+                return Err(WorkflowError::Batch(format!("第{}组参数有{}列，表头有{}列", i + 1, row.len(), header.len())));
+            }
+            Ok(header.iter().cloned().zip(row.into_iter().map(Value::String)).collect())
+        })
+        .collect()
+}
+
+// RFC 4180：字段可用双引号包裹，引号内的""为一个引号，引号内可含逗号和换行
+fn csv_rows(content: &str) -> Result<Vec<Vec<String>>, WorkflowError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(WorkflowError::Batch("CSV中的引号未闭合".to_string()));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+impl WorkflowExecutor {
+    /// 对每组参数运行一次工作流，结果逐行写入output（JSONL）
+    ///
+    /// 单次运行失败不影响后续参数组；cancel被触发后剩余参数组不再运行
+    pub async fn run_batch(
+        &self,
+        workflow: &WorkflowData,
+        parameter_sets: &[ParameterValues],
+        output: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<BatchSummary, WorkflowError> {
+        let file = File::create(output)
+            .map_err(|e| WorkflowError::Batch(format!("创建{}失败: {}", output.display(), e)))?;
+        let mut writer = BufWriter::new(file);
+        let mut summary = BatchSummary { total: parameter_sets.len(), ..BatchSummary::default() };
+
+        for (index, parameters) in parameter_sets.iter().enumerate() {
+            if cancel.is_cancelled() {
+                summary.skipped = parameter_sets.len() - index;
+                break;
+            }
+
+            let started = Instant::now();
+            let result = self.execute_workflow_with_params(workflow, parameters, cancel).await;
+            let mut record = BatchRecord {
+                index,
+                parameters: parameters.clone(),
+                run_id: None,
+                status: "Completed".to_string(),
+                outputs: JsonOutputs::new(),
+                error: None,
+                duration_ms: started.elapsed().as_millis() as u64,
+            };
+            match result {
+                Ok(result) => {
+                    summary.succeeded += 1;
+                    record.run_id = Some(result.run_id);
+                    record.outputs = outputs_to_json(&result.outputs);
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    record.status = if matches!(e, WorkflowError::Cancelled) { "Cancelled" } else { "Failed" }.to_string();
+                    record.error = Some(e.to_string());
+                }
+            }
+
+            // 每条结果立即落盘，批量中途中断时已完成的结果不丢失
+            let line = serde_json::to_string(&record).map_err(|e| WorkflowError::Batch(e.to_string()))?;
+            writeln!(writer, "{}", line)
+                .and_then(|_| writer.flush())
+                .map_err(|e| WorkflowError::Batch(format!("写入{}失败: {}", output.display(), e)))?;
+        }
+        Ok(summary)
+    }
+}
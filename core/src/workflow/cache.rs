@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::types::DataValue;
+use crate::workflow::serialization::NodeData;
+
+/// 节点输出缓存：键为节点类型、data、配置、默认输入和输入值的内容哈希
+///
+/// 内存缓存始终启用；设置磁盘目录后，条目同时以`<key>.json`写入磁盘，跨进程复用。
+pub struct OutputCache {
+    entries: HashMap<String, HashMap<String, DataValue>>,
+    disk_dir: Option<PathBuf>,
+}
+
+impl OutputCache {
+    pub fn new() -> Self {
+        Self { entries: HashMap::new(), disk_dir: None }
+    }
+
+    /// 启用磁盘缓存
+    pub fn with_disk_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { entries: HashMap::new(), disk_dir: Some(dir.into()) }
+    }
+
+    /// 查找缓存：先内存，后磁盘
+    pub fn get(&mut self, key: &str) -> Option<HashMap<String, DataValue>> {
+        if let Some(outputs) = self.entries.get(key) {
+            return Some(outputs.clone());
+        }
+
+        let path = self.disk_path(key)?;
+        let json = fs::read_to_string(path).ok()?;
+        let outputs: HashMap<String, DataValue> = serde_json::from_str(&json).ok()?;
+        self.entries.insert(key.to_string(), outputs.clone());
+        Some(outputs)
+    }
+
+    /// 写入缓存；包含流式数据的输出不缓存
+    pub fn insert(&mut self, key: String, outputs: HashMap<String, DataValue>) {
+        if outputs.values().any(contains_stream) {
+            return;
+        }
+
+        if let Some(path) = self.disk_path(&key) {
+            let written = fs::create_dir_all(path.parent().unwrap_or(&path))
+                .and_then(|_| serde_json::to_string(&outputs).map_err(std::io::Error::from))
+                .and_then(|json| fs::write(&path, json));
+            if let Err(e) = written {
+                tracing::warn!("写入磁盘缓存失败 {:?}: {}", path, e);
+            }
+        }
+
+        self.entries.insert(key, outputs);
+    }
+
+    /// 清空内存缓存（磁盘文件保留）
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        self.disk_dir.as_ref().map(|dir| dir.join(format!("{}.json", key)))
+    }
+}
+
+impl Default for OutputCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 节点是否配置为每次都重新执行（非确定性节点）
+pub fn always_rerun(node: &NodeData) -> bool {
+    node.config.as_ref()
+        .and_then(|config| config.get("always_rerun"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// 计算节点的缓存键；输入含流式数据或无法序列化时返回None
+///
+/// 节点工厂也从data读取配置（如输入节点的label），因此data与代入参数后的default_inputs都参与哈希
+pub fn cache_key(node: &NodeData, inputs: &HashMap<String, DataValue>) -> Option<String> {
+    if inputs.values().any(contains_stream) {
+        return None;
+    }
+
+    // 经serde_json::Value规范化：对象键有序，HashMap的迭代顺序不影响哈希
+    let mut sorted_inputs: Vec<(&String, &DataValue)> = inputs.iter().collect();
+    sorted_inputs.sort_by(|a, b| a.0.cmp(b.0));
+    let mut canonical_inputs = Vec::with_capacity(sorted_inputs.len());
+    for (port, value) in sorted_inputs {
+        canonical_inputs.push((port, serde_json::to_value(value).ok()?));
+    }
+
+    let canonical = serde_json::json!({
+        "type": node.type,
+        "data": node.data,
+        "config": node.config,
+        "default_inputs": node.default_inputs,
+        "inputs": canonical_inputs,
+    });
+    Some(format!("{:016x}", fnv1a64(canonical.to_string().as_bytes())))
+}
+
+pub(crate) fn contains_stream(value: &DataValue) -> bool {
+    match value {
+        DataValue::Stream(_) => true,
+        DataValue::List(items) => items.iter().any(contains_stream),
+        DataValue::Dict(map) => map.values().any(contains_stream),
+        _ => false,
+    }
+}
+
+// FNV-1a：跨版本稳定，可用于磁盘缓存的键
+pub(crate) fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -0,0 +1,474 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::workflow::schema::CURRENT_VERSION;
+use crate::workflow::serialization::{EdgeData, NodeData, Position, WorkflowData};
+
+/// 无法映射的ComfyUI节点导入为该类型的占位节点，原始类型和参数保存在config中
+pub const PLACEHOLDER_TYPE: &str = "comfyui";
+
+/// API格式没有坐标，按拓扑深度排布的列宽和行高
+const LAYOUT_COLUMN_WIDTH: f64 = 300.0;
+const LAYOUT_ROW_HEIGHT: f64 = 150.0;
+
+/// 只有注释作用的节点，导入时忽略
+const ANNOTATION_TYPES: &[&str] = &["Note", "MarkdownNote"];
+
+/// 连线中转节点，导入时把经过它的连线接到真正的源节点
+const REROUTE_TYPE: &str = "Reroute";
+
+/// ComfyUI节点到MicroFlow节点的映射
+struct Mapping {
+    class_type: &'static str,
+    node_type: &'static str,
+    /// UI格式widgets_values按位置对应的参数名
+    widgets: &'static [&'static str],
+    /// 参数名 -> 节点config键
+    config: &'static [(&'static str, &'static str)],
+    /// 参数名 -> 输入端口：连线时作为端口连接，为常量时写入default_inputs
+    inputs: &'static [(&'static str, &'static str)],
+    /// 按输出槽位顺序对应的输出端口
+    outputs: &'static [&'static str],
+}
+
+const MAPPINGS: &[Mapping] = &[
+    Mapping { class_type: "CLIPTextEncode", node_type: "input", widgets: &["text"], config: &[("text", "text")], inputs: &[], outputs: &["text"] },
+    Mapping { class_type: "PrimitiveString", node_type: "input", widgets: &["value"], config: &[("value", "text")], inputs: &[], outputs: &["text"] },
+    Mapping { class_type: "PrimitiveStringMultiline", node_type: "input", widgets: &["value"], config: &[("value", "text")], inputs: &[], outputs: &["text"] },
+    Mapping {
+        class_type: "OllamaGenerate",
+        node_type: "llm",
+        widgets: &["prompt", "debug", "url", "model", "keep_alive"],
+        config: &[("model", "model_id")],
+        inputs: &[("prompt", "prompt")],
+        outputs: &["response"],
+    },
+    Mapping {
+        class_type: "LoraLoader",
+        node_type: "lora_switch",
+        widgets: &["lora_name", "strength_model", "strength_clip"],
+        config: &[("lora_name", "lora_path")],
+        // CLIP没有对应端口，clip输入和输出槽位1的连线丢弃
+        inputs: &[("model", "model_id"), ("lora_name", "lora_path")],
+        outputs: &["model_id"],
+    },
+    Mapping {
+        class_type: "LoraLoaderModelOnly",
+        node_type: "lora_switch",
+        widgets: &["lora_name", "strength_model"],
+        config: &[("lora_name", "lora_path")],
+        inputs: &[("model", "model_id"), ("lora_name", "lora_path")],
+        outputs: &["model_id"],
+    },
+    Mapping { class_type: "ShowText|pysssss", node_type: "output", widgets: &[], config: &[], inputs: &[("text", "text")], outputs: &["output"] },
+];
+
+fn mapping(class_type: &str) -> Option<&'static Mapping> {
+    MAPPINGS.iter().find(|m| m.class_type == class_type)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("ComfyUI工作流解析失败: {0}")]
+    Parse(String),
+    #[error("无法识别的ComfyUI格式: 既不是API格式也不是UI格式")]
+    UnknownFormat,
+    #[error("ComfyUI节点{node_id}无效: {reason}")]
+    InvalidNode { node_id: String, reason: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComfyFormat {
+    /// "保存(API格式)"导出：{ 节点ID: { class_type, inputs } }
+    Api,
+    /// 画布保存的格式：{ nodes: [...], links: [...] }
+    Ui,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaceholderNode {
+    pub node_id: String,
+    pub class_type: String,
+}
+
+/// 导入报告
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub format: ComfyFormat,
+    /// 映射为MicroFlow节点的数量
+    pub mapped_nodes: usize,
+    /// 未支持、导入为占位节点的节点
+    pub placeholders: Vec<PlaceholderNode>,
+    /// 映射后两端找不到对应端口而丢弃的连线
+    pub dropped_links: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "已映射{}个节点，{}个占位节点", self.mapped_nodes, self.placeholders.len())?;
+        for placeholder in &self.placeholders {
+            writeln!(f, "占位节点: {} ({})", placeholder.node_id, placeholder.class_type)?;
+        }
+        for link in &self.dropped_links {
+            writeln!(f, "丢弃连线: {}", link)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "警告: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComfyImport {
+    pub workflow: WorkflowData,
+    pub report: ImportReport,
+}
+
+// 统一两种格式后的中间表示
+struct ComfyNode {
+    id: String,
+    class_type: String,
+    title: Option<String>,
+    position: Option<Position>,
+    /// 常量参数
+    widgets: Map<String, Value>,
+}
+
+struct ComfyLink {
+    id: String,
+    source: String,
+    source_slot: usize,
+    source_name: Option<String>,
+    target: String,
+    target_input: String,
+}
+
+/// 导入ComfyUI工作流（自动识别API格式和UI格式）
+pub fn import_comfyui(json: &str) -> Result<ComfyImport, ImportError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| ImportError::Parse(e.to_string()))?;
+    let root = value.as_object().ok_or(ImportError::UnknownFormat)?;
+
+    let (format, nodes, links) = if root.get("nodes").map_or(false, Value::is_array) && root.contains_key("links") {
+        let (nodes, links) = parse_ui(root)?;
+        (ComfyFormat::Ui, nodes, links)
+    } else if !root.is_empty() && root.values().all(|node| node.get("class_type").is_some()) {
+        let (nodes, links) = parse_api(root)?;
+        (ComfyFormat::Api, nodes, links)
+    } else {
+        return Err(ImportError::UnknownFormat);
+    };
+
+    let mut report = ImportReport {
+        format,
+        mapped_nodes: 0,
+        placeholders: Vec::new(),
+        dropped_links: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let positions = match format {
+        ComfyFormat::Ui => HashMap::new(),
+        ComfyFormat::Api => layout(&nodes, &links),
+    };
+
+    let mut classes = HashMap::new();
+    let mut workflow_nodes = Vec::new();
+    for node in &nodes {
+        if ANNOTATION_TYPES.contains(&node.class_type.as_str()) {
+            report.warnings.push(format!("忽略注释节点{}", node.id));
+            continue;
+        }
+        let position = node.position.clone()
+            .or_else(|| positions.get(&node.id).cloned())
+            .unwrap_or(Position { x: 0.0, y: 0.0 });
+        let data = convert_node(node, position, &mut report);
+        classes.insert(node.id.clone(), node.class_type.clone());
+        workflow_nodes.push(data);
+    }
+
+    let mut edges = Vec::new();
+    for link in &links {
+        let (Some(source_class), Some(target_class)) = (classes.get(&link.source), classes.get(&link.target)) else {
+            report.dropped_links.push(format!("{}: 连接了被忽略的节点", link.id));
+            continue;
+        };
+        let source_port = match mapping(source_class) {
+            Some(m) => m.outputs.get(link.source_slot).map(|port| port.to_string()),
+            None => Some(link.source_name.clone().unwrap_or_else(|| format!("out{}", link.source_slot))),
+        };
+        let target_port = match mapping(target_class) {
+            Some(m) => m.inputs.iter().find(|(name, _)| *name == link.target_input).map(|(_, port)| port.to_string()),
+            None => Some(link.target_input.clone()),
+        };
+        match (source_port, target_port) {
+            (Some(source_port), Some(target_port)) => edges.push(EdgeData {
+                id: link.id.clone(),
+                source: link.source.clone(),
+                target: link.target.clone(),
+                source_handle: Some(source_port),
+                target_handle: Some(target_port),
+                animated: false,
+            }),
+            _ => report.dropped_links.push(format!(
+                "{}: {}[{}]({}) -> {}.{}({})",
+                link.id, link.source, link.source_slot, source_class, link.target, link.target_input, target_class
+            )),
+        }
+    }
+
+    let workflow = WorkflowData { version: CURRENT_VERSION.to_string(), nodes: workflow_nodes, edges, parameters: Vec::new() };
+    Ok(ComfyImport { workflow, report })
+}
+
+fn convert_node(node: &ComfyNode, position: Position, report: &mut ImportReport) -> NodeData {
+    let label = node.title.clone().unwrap_or_else(|| node.class_type.clone());
+    let Some(mapping) = mapping(&node.class_type) else {
+        report.placeholders.push(PlaceholderNode { node_id: node.id.clone(), class_type: node.class_type.clone() });
+        return NodeData {
+            id: node.id.clone(),
+            type: PLACEHOLDER_TYPE.to_string(),
+            position,
+            data: serde_json::json!({ "label": label, "class_type": node.class_type }),
+            config: Some(serde_json::json!({ "class_type": node.class_type, "widgets": node.widgets })),
+            default_inputs: None,
+            input_types: None,
+            parent_id: None,
+        };
+    };
+
+    report.mapped_nodes += 1;
+    let mut config = Map::new();
+    for (param, key) in mapping.config {
+        if let Some(value) = node.widgets.get(*param) {
+            config.insert(key.to_string(), value.clone());
+        }
+    }
+    let mut default_inputs = Map::new();
+    for (param, port) in mapping.inputs {
+        if let Some(value) = node.widgets.get(*param) {
+            default_inputs.insert(port.to_string(), value.clone());
+        }
+    }
+
+    let mut data = config.clone();
+    data.insert("label".to_string(), Value::String(label));
+    NodeData {
+        id: node.id.clone(),
+        type: mapping.node_type.to_string(),
+        position,
+        data: Value::Object(data),
+        config: Some(Value::Object(config)),
+        default_inputs: (!default_inputs.is_empty()).then(|| Value::Object(default_inputs)),
+        input_types: None,
+        parent_id: None,
+    }
+}
+
+// API格式：inputs中[源节点ID, 输出槽位]为连线，其余为常量参数
+fn parse_api(root: &Map<String, Value>) -> Result<(Vec<ComfyNode>, Vec<ComfyLink>), ImportError> {
+    let mut nodes = Vec::new();
+    let mut links = Vec::new();
+    let mut ids: Vec<&String> = root.keys().collect();
+    ids.sort_by_key(|id| (id.parse::<u64>().unwrap_or(u64::MAX), id.to_string()));
+
+    for id in ids {
+        let node = &root[id];
+        let class_type = node.get("class_type").and_then(Value::as_str)
+            .ok_or_else(|| ImportError::InvalidNode { node_id: id.clone(), reason: "class_type不是字符串".to_string() })?;
+        let mut widgets = Map::new();
+        for (name, input) in node.get("inputs").and_then(Value::as_object).into_iter().flatten() {
+            match api_link(input) {
+                Some((source, slot)) => links.push(ComfyLink {
+                    id: format!("{}-{}-{}-{}", source, slot, id, name),
+                    source,
+                    source_slot: slot,
+                    source_name: None,
+                    target: id.clone(),
+                    target_input: name.clone(),
+                }),
+                None => { widgets.insert(name.clone(), input.clone()); }
+            }
+        }
+        nodes.push(ComfyNode {
+            id: id.clone(),
+            class_type: class_type.to_string(),
+            title: node.pointer("/_meta/title").and_then(Value::as_str).map(str::to_string),
+            position: None,
+            widgets,
+        });
+    }
+    Ok((nodes, links))
+}
+
+fn api_link(input: &Value) -> Option<(String, usize)> {
+    match input.as_array()?.as_slice() {
+        [source, slot] => {
+            let source = match source {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                _ => return None,
+            };
+            Some((source, slot.as_u64()? as usize))
+        }
+        _ => None,
+    }
+}
+
+// UI格式：links为[link_id, 源节点, 源槽位, 目标节点, 目标槽位, 类型]（新版为对象），
+// 输入名由目标节点inputs[目标槽位].name给出
+fn parse_ui(root: &Map<String, Value>) -> Result<(Vec<ComfyNode>, Vec<ComfyLink>), ImportError> {
+    let raw_nodes = root["nodes"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let mut nodes = Vec::new();
+    let mut input_names: HashMap<(String, usize), String> = HashMap::new();
+    let mut output_names: HashMap<(String, usize), String> = HashMap::new();
+    let mut reroutes: HashSet<String> = HashSet::new();
+
+    for raw in raw_nodes {
+        let id = raw.get("id").map(id_string)
+            .ok_or_else(|| ImportError::InvalidNode { node_id: "?".to_string(), reason: "缺少id".to_string() })?;
+        let class_type = raw.get("type").and_then(Value::as_str)
+            .ok_or_else(|| ImportError::InvalidNode { node_id: id.clone(), reason: "缺少type".to_string() })?;
+
+        for (slot, input) in raw.get("inputs").and_then(Value::as_array).into_iter().flatten().enumerate() {
+            if let Some(name) = input.get("name").and_then(Value::as_str) {
+                input_names.insert((id.clone(), slot), name.to_string());
+            }
+        }
+        for (slot, output) in raw.get("outputs").and_then(Value::as_array).into_iter().flatten().enumerate() {
+            if let Some(name) = output.get("name").and_then(Value::as_str) {
+                output_names.insert((id.clone(), slot), name.to_string());
+            }
+        }
+        if class_type == REROUTE_TYPE {
+            reroutes.insert(id);
+            continue;
+        }
+
+        nodes.push(ComfyNode {
+            title: raw.get("title").and_then(Value::as_str).map(str::to_string),
+            position: raw.get("pos").and_then(ui_position),
+            widgets: ui_widgets(class_type, raw.get("widgets_values")),
+            class_type: class_type.to_string(),
+            id,
+        });
+    }
+
+    // (link_id, 源节点, 源槽位, 目标节点, 目标槽位)
+    let mut raw_links = Vec::new();
+    for link in root["links"].as_array().map(Vec::as_slice).unwrap_or_default() {
+        let fields = match link {
+            Value::Array(fields) if fields.len() >= 5 => [&fields[0], &fields[1], &fields[2], &fields[3], &fields[4]],
+            Value::Object(fields) => match (fields.get("id"), fields.get("origin_id"), fields.get("origin_slot"), fields.get("target_id"), fields.get("target_slot")) {
+                (Some(a), Some(b), Some(c), Some(d), Some(e)) => [a, b, c, d, e],
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let slot = |v: &Value| v.as_u64().unwrap_or(0) as usize;
+        raw_links.push((id_string(fields[0]), id_string(fields[1]), slot(fields[2]), id_string(fields[3]), slot(fields[4])));
+    }
+
+    // 中转节点只有一个输入：指向它的连线即其上游
+    let reroute_sources: HashMap<&str, (&str, usize)> = raw_links.iter()
+        .filter(|(_, _, _, target, _)| reroutes.contains(target))
+        .map(|(_, source, slot, target, _)| (target.as_str(), (source.as_str(), *slot)))
+        .collect();
+
+    let mut links = Vec::new();
+    for (id, source, slot, target, target_slot) in &raw_links {
+        if reroutes.contains(target) {
+            continue;
+        }
+        let (mut source, mut slot) = (source.as_str(), *slot);
+        let mut hops = 0;
+        while reroutes.contains(source) && hops <= reroutes.len() {
+            match reroute_sources.get(source) {
+                Some(&(upstream, upstream_slot)) => { source = upstream; slot = upstream_slot; }
+                None => break,
+            }
+            hops += 1;
+        }
+        let target_input = input_names.get(&(target.clone(), *target_slot)).cloned()
+            .unwrap_or_else(|| format!("in{}", target_slot));
+        links.push(ComfyLink {
+            id: format!("link-{}", id),
+            source: source.to_string(),
+            source_slot: slot,
+            source_name: output_names.get(&(source.to_string(), slot)).cloned(),
+            target: target.clone(),
+            target_input,
+        });
+    }
+    Ok((nodes, links))
+}
+
+fn id_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// pos为[x, y]或{"0": x, "1": y}
+fn ui_position(pos: &Value) -> Option<Position> {
+    let (x, y) = match pos {
+        Value::Array(xy) if xy.len() >= 2 => (&xy[0], &xy[1]),
+        Value::Object(xy) => (xy.get("0")?, xy.get("1")?),
+        _ => return None,
+    };
+    Some(Position { x: x.as_f64()?, y: y.as_f64()? })
+}
+
+// widgets_values为按位置排列的数组（已知节点按映射表命名，未知节点用widget序号）或按名称的对象
+fn ui_widgets(class_type: &str, values: Option<&Value>) -> Map<String, Value> {
+    match values {
+        Some(Value::Object(named)) => named.clone(),
+        Some(Value::Array(values)) => {
+            let names = mapping(class_type).map(|m| m.widgets).unwrap_or_default();
+            values.iter().enumerate()
+                .map(|(i, value)| {
+                    let name = names.get(i).map(|n| n.to_string()).unwrap_or_else(|| format!("widget{}", i));
+                    (name, value.clone())
+                })
+                .collect()
+        }
+        _ => Map::new(),
+    }
+}
+
+// API格式没有坐标：按到源节点的最长距离分列，列内按ID顺序排布
+fn layout(nodes: &[ComfyNode], links: &[ComfyLink]) -> HashMap<String, Position> {
+    let mut depth: HashMap<&str, usize> = nodes.iter().map(|node| (node.id.as_str(), 0)).collect();
+    // 最多迭代节点数轮，有环时也能结束
+    for _ in 0..nodes.len() {
+        let mut changed = false;
+        for link in links {
+            let (Some(&source), Some(&target)) = (depth.get(link.source.as_str()), depth.get(link.target.as_str())) else { continue };
+            if target < source + 1 {
+                depth.insert(link.target.as_str(), source + 1);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut rows: HashMap<usize, usize> = HashMap::new();
+    let mut positions = HashMap::new();
+    for node in nodes {
+        let column = depth[node.id.as_str()];
+        let row = rows.entry(column).or_insert(0);
+        positions.insert(node.id.clone(), Position {
+            x: column as f64 * LAYOUT_COLUMN_WIDTH,
+            y: *row as f64 * LAYOUT_ROW_HEIGHT,
+        });
+        *row += 1;
+    }
+    positions
+}
@@ -1,22 +1,49 @@
 use crate::vram::VramPool;
 use crate::types::DataValue;
+use crate::workflow::cache::OutputCache;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 
 pub struct ExecutionContext {
     pub vram_pool: Arc<Mutex<VramPool>>,
+    /// 跨运行共享的节点输出缓存
+    pub output_cache: Arc<Mutex<OutputCache>>,
     outputs: HashMap<String, HashMap<String, DataValue>>,
+    /// 节点执行时的输入（启用运行历史时记录）
+    inputs: HashMap<String, HashMap<String, DataValue>>,
 }
 
 impl ExecutionContext {
     pub fn new() -> Self {
         Self {
-            vram_pool: Arc::new(Mutex::new(VramPool::new(2))),
+            vram_pool: Arc::new(Mutex::new(VramPool::default())),
+            output_cache: Arc::new(Mutex::new(OutputCache::new())),
             outputs: HashMap::new(),
+            inputs: HashMap::new(),
         }
     }
     
-    pub fn get_model(&self, model_id: &str) -> Option<Arc<crate::ffi::LlamaModel>> {
+    /// 使用共享的VRAM池创建上下文
+    pub fn with_pool(vram_pool: Arc<Mutex<VramPool>>) -> Self {
+        Self {
+            vram_pool,
+            output_cache: Arc::new(Mutex::new(OutputCache::new())),
+            outputs: HashMap::new(),
+            inputs: HashMap::new(),
+        }
+    }
+    
+    /// 为一次新运行创建上下文：共享VRAM池和输出缓存，输出为空
+    pub fn new_run(&self) -> Self {
+        Self {
+            vram_pool: Arc::clone(&self.vram_pool),
+            output_cache: Arc::clone(&self.output_cache),
+            outputs: HashMap::new(),
+            inputs: HashMap::new(),
+        }
+    }
+    
+    pub fn get_model(&self, model_id: &str) -> Option<Arc<dyn crate::model::ModelProvider>> {
         let mut pool = self.vram_pool.lock().unwrap();
         pool.get_model(model_id)
     }
@@ -37,14 +64,16 @@ impl ExecutionContext {
         self.get_outputs(node_id)
     }
     
-    pub fn get_final_outputs(&self) -> HashMap<String, DataValue> {
-        // 简化版：返回所有输出
-        let mut final_outputs = HashMap::new();
-        for (_, outputs) in &self.outputs {
-            for (key, value) in outputs {
-                final_outputs.insert(key.clone(), value.clone());
-            }
-        }
-        final_outputs
+    pub fn set_inputs(&mut self, node_id: String, inputs: HashMap<String, DataValue>) {
+        self.inputs.insert(node_id, inputs);
+    }
+    
+    pub fn get_inputs(&self, node_id: &str) -> Option<&HashMap<String, DataValue>> {
+        self.inputs.get(node_id)
+    }
+    
+    /// 获取指定节点指定端口的输出值
+    pub fn get_output(&self, node_id: &str, port_id: &str) -> Option<&DataValue> {
+        self.outputs.get(node_id).and_then(|outputs| outputs.get(port_id))
     }
 }
\ No newline at end of file
@@ -0,0 +1,73 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::engine::RunningSubState;
+use crate::vram::Tier;
+
+/// 每个订阅者可积压的事件数，落后更多时丢失最早的事件（接收端收到Lagged）
+pub const EVENT_BUFFER: usize = 1024;
+
+/// 执行事件，序列化时以"type"字段区分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum ExecutionEvent {
+    RunStarted { run_id: String, node_ids: Vec<String> },
+    /// 节点开始执行（重试时再次发出）
+    NodeStarted { run_id: String, node_id: String },
+    /// 运行中的子状态变化：循环迭代、分支选择等
+    NodeProgress { run_id: String, node_id: String, substate: RunningSubState },
+    /// 流式端口产出（输出端口）或读取（输入端口）的文本块
+    TokenGenerated { run_id: String, node_id: String, port: String, token: String },
+    NodeCompleted { run_id: String, node_id: String, duration_ms: Option<u64> },
+    /// 工作流输出端口（输出节点或expose_outputs）的值，节点完成时发出；流式值不发出
+    OutputProduced { run_id: String, node_id: String, port: String, value: serde_json::Value },
+    /// will_retry为true时节点将按错误策略重试
+    NodeFailed { run_id: String, node_id: String, error: String, will_retry: bool },
+    /// 节点不会执行（上游失败、分支未选中或运行被取消前未开始）
+    NodeSkipped { run_id: String, node_id: String },
+    NodeCancelled { run_id: String, node_id: String },
+    ModelLoaded { model_id: String },
+    ModelEvicted { model_id: String },
+    /// 模型从显存淘汰，显存已释放；随后为ModelTierChanged（降级到CPU层）或ModelEvicted
+    ModelReleased { model_id: String },
+    /// 降级到CPU层失败，模型只留在磁盘上
+    ModelDemotionFailed { model_id: String, error: String },
+    /// 预取失败，节点执行时再加载
+    ModelPrefetchFailed { model_id: String, error: String },
+    /// 模型在显存与CPU内存之间降级/升级，duration_ms为重新加载耗时
+    ModelTierChanged { model_id: String, from: Tier, to: Tier, duration_ms: u64 },
+    /// status为"Completed"/"Error"/"Cancelled"
+    RunFinished { run_id: String, status: String, duration_ms: u64, error: Option<String> },
+}
+
+/// 执行事件通道：每个订阅者接收订阅之后发布的全部事件
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ExecutionEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ExecutionEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 发布事件；没有订阅者时直接丢弃
+    pub fn publish(&self, event: ExecutionEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,12 +1,51 @@
-use crate::workflow::nodes::{TextInputNode, LLMNode, TextOutputNode};
+use crate::workflow::nodes::{Node, TextInputNode, LLMNode, TextOutputNode, cancelled_error};
 use crate::workflow::context::ExecutionContext;
+use crate::workflow::registry::NodeRegistry;
+use crate::workflow::scheduler::{Scheduler, SchedulerConfig};
+use crate::workflow::tracker::{NodeStateTracker, NodeStateSnapshot};
+use crate::workflow::recovery::{error_policy, retry_delay};
+use crate::workflow::cache::{cache_key, always_rerun};
+use crate::workflow::journal::{JournalStore, RunJournal, new_run_id, workflow_hash};
+use crate::workflow::history::{FileVersion, HistoryStore, ModelUsage, NodeRecord, RunRecord};
+use crate::workflow::loops::{self, IterationCallback, LoopBody, LoopKind, LoopNode};
+use crate::workflow::subgraph;
+use crate::workflow::stream::{NodeStreams, STREAM_BUFFER, is_stream_type};
+use crate::workflow::events::{EventBus, ExecutionEvent};
+use crate::workflow::outputs::{self, outputs_to_json, NodeRunInfo, WorkflowOutputs};
+use crate::workflow::params::{self, ParameterValues, json_to_value, value_to_json};
+use crate::workflow::validator::{self, EdgeError, EdgeErrorKind, EdgeTypeReport, IssueCode, ValidationReport};
 use crate::workflow::serialization::{WorkflowData, NodeData};
+use crate::engine::{CancellationToken, NodeError, ErrorInfo, MainState, RecoveryAction, RunningSubState, StateMachineContext};
 use crate::types::{DataValue};
-use std::collections::{HashMap, HashSet, VecDeque};
+use crate::parameter::{Connection, ConnectionGraph, DynamicPorts, Port};
+use crate::ffi::LoadParams;
+use crate::vram::{PoolEvent, SlotStatus, Tier, VramPool};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use serde::Serialize;
+use tokio::task::JoinSet;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ExecutionResult {
-    pub final_outputs: HashMap<String, DataValue>,
+    pub run_id: String,
+    /// 工作流输出，按节点ID和端口区分（见outputs::designated_outputs）
+    pub outputs: WorkflowOutputs,
+    /// 各节点的最终状态与耗时，按声明顺序
+    pub nodes: Vec<NodeRunInfo>,
+    /// 命中缓存、未重新执行的节点
+    pub cached_nodes: Vec<String>,
+    /// 从运行日志恢复、未重新执行的节点
+    pub resumed_nodes: Vec<String>,
+}
+
+impl ExecutionResult {
+    /// 指定节点指定端口的输出
+    pub fn output(&self, node_id: &str, port: &str) -> Option<&DataValue> {
+        self.outputs.get(node_id).and_then(|outputs| outputs.get(port))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -15,61 +54,1007 @@ pub enum WorkflowError {
     UnknownNodeType(String),
     #[error("缺少配置")]
     MissingConfig,
-    #[error("缺少输入")]
-    MissingInput,
+    #[error("缺少输入: 节点{node_id}的端口{port_id}")]
+    MissingInput { node_id: String, port_id: String },
     #[error("循环依赖")]
     CycleDetected,
     #[error("节点未找到: {0}")]
     NodeNotFound(String),
+    #[error("无法确定端口: 节点{0}需要在边上显式指定端口")]
+    PortNotSpecified(String),
+    #[error("节点{node_id}执行失败: {}", .error.message)]
+    NodeFailed { node_id: String, error: NodeError },
+    #[error("任务执行异常: {0}")]
+    TaskFailed(String),
+    #[error("节点{node_id}配置无效: {reason}")]
+    InvalidConfig { node_id: String, reason: String },
+    #[error("工作流已取消")]
+    Cancelled,
+    #[error("运行日志错误: {0}")]
+    Journal(String),
+    #[error("运行历史错误: {0}")]
+    History(String),
+    #[error("循环体结构无效: {0}")]
+    InvalidScope(String),
+    #[error("子图递归包含: {0}")]
+    RecursiveSubgraph(String),
+    #[error("参数无效: {0}")]
+    InvalidParameter(String),
+    #[error("批量运行错误: {0}")]
+    Batch(String),
+    #[error("{0}")]
+    InvalidEdge(EdgeError),
+}
+
+// 执行中的节点任务，完成时返回(节点ID, 执行结果)
+type NodeTasks = JoinSet<(String, Result<HashMap<String, DataValue>, NodeError>)>;
+
+// 一次运行中不变的部分：节点实例、错误策略和端口连接
+struct RunPlan<'a> {
+    workflow: &'a WorkflowData,
+    instances: HashMap<String, Arc<dyn Node>>,
+    policies: HashMap<String, RecoveryAction>,
+    graph: ConnectionGraph,
+    /// 边ID与解析后的端口连接
+    edges: Vec<(String, Connection)>,
+    /// 源端口为流式类型的连接
+    stream_edges: Vec<Connection>,
+    /// 重放时不使用输出缓存，所有节点重新执行
+    use_cache: bool,
+    /// 顶层节点的拓扑顺序，用于规划模型的预取与淘汰
+    order: Vec<String>,
+    run_id: String,
+    /// 工作流输出：节点ID -> 端口（None为全部端口），完成时发布OutputProduced
+    outputs: HashMap<String, Option<Vec<String>>>,
+    tracking: RunTracking,
+}
+
+// 一次运行的整体状态与节点状态：每次运行单独创建，重叠的运行互不覆盖
+#[derive(Clone)]
+struct RunTracking {
+    state: Arc<Mutex<StateMachineContext>>,
+    nodes: Arc<Mutex<NodeStateTracker>>,
+}
+
+impl RunTracking {
+    fn new(events: &EventBus) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(StateMachineContext::new(MainState::Idle))),
+            nodes: Arc::new(Mutex::new(NodeStateTracker::with_events(events.clone()))),
+        }
+    }
+    
+    fn with_tracker<F: FnOnce(&mut NodeStateTracker)>(&self, f: F) {
+        if let Ok(mut tracker) = self.nodes.lock() {
+            f(&mut tracker);
+        }
+    }
+    
+    fn snapshot(&self) -> Vec<NodeStateSnapshot> {
+        self.nodes.lock().map(|tracker| tracker.snapshot()).unwrap_or_default()
+    }
+}
+
+// 一次运行中的模型预取：同一时间最多一个预取任务，每个模型只尝试一次
+#[derive(Default)]
+struct Prefetcher {
+    task: Option<tokio::task::JoinHandle<()>>,
+    attempted: HashSet<String>,
+}
+
+// 运行的来源，记录到运行历史
+struct RunRequest {
+    /// 代入参数前的工作流哈希
+    workflow_hash: String,
+    parameters: ParameterValues,
+    replay_of: Option<String>,
+}
+
+impl RunPlan<'_> {
+    // 参与流式连接的节点：块无法缓存或重放，每次都重新执行
+    fn is_streaming(&self, node_id: &str) -> bool {
+        self.stream_edges.iter().any(|c| c.from_node == node_id || c.to_node == node_id)
+    }
+    
+    // 流式输出的生产者：消费者已处理了部分块，失败后不能重试
+    fn produces_stream(&self, node_id: &str) -> bool {
+        self.stream_edges.iter().any(|c| c.from_node == node_id)
+    }
 }
 
 pub struct WorkflowExecutor {
     ctx: ExecutionContext,
+    registry: NodeRegistry,
+    config: SchedulerConfig,
+    /// 最近一次开始的运行
+    current: Mutex<RunTracking>,
+    events: EventBus,
+    journal_store: Option<JournalStore>,
+    history: Option<HistoryStore>,
+    subgraph_dir: PathBuf,
+    last_run_id: Mutex<Option<String>>,
 }
 
 impl WorkflowExecutor {
-    pub fn new(ctx: ExecutionContext) -> Self { Self { ctx } }
+    pub fn new(ctx: ExecutionContext) -> Self {
+        Self::with_config(ctx, SchedulerConfig::default())
+    }
+    
+    pub fn with_config(ctx: ExecutionContext, config: SchedulerConfig) -> Self {
+        let registry = NodeRegistry::with_builtin_nodes(Arc::clone(&ctx.vram_pool));
+        let events = EventBus::new();
+        Self {
+            ctx,
+            registry,
+            config,
+            current: Mutex::new(RunTracking::new(&events)),
+            events,
+            journal_store: None,
+            history: None,
+            subgraph_dir: PathBuf::from("."),
+            last_run_id: Mutex::new(None),
+        }
+    }
+    
+    /// 当前（或最近一次）运行的整体状态
+    pub fn run_state(&self) -> MainState {
+        self.current_tracking()
+            .and_then(|tracking| tracking.state.lock().ok().map(|state| state.current_state().clone()))
+            .unwrap_or(MainState::Idle)
+    }
+    
+    /// 订阅执行事件：节点状态变化、流式token、模型加载/淘汰及运行结束
+    pub fn subscribe(&self) -> broadcast::Receiver<ExecutionEvent> {
+        self.events.subscribe()
+    }
+    
+    /// 当前（或最近一次）运行中每个节点的状态快照
+    pub fn node_states(&self) -> Vec<NodeStateSnapshot> {
+        self.current_tracking().map(|tracking| tracking.snapshot()).unwrap_or_default()
+    }
+    
+    /// 启用运行日志：每个节点完成后写入`<dir>/<run_id>.json`，可通过resume_workflow恢复
+    pub fn set_journal_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.journal_store = Some(JournalStore::new(dir));
+    }
+    
+    /// 节点在当前（或最近一次）运行中经历过的状态
+    pub fn node_history(&self, node_id: &str) -> Vec<MainState> {
+        self.current_tracking()
+            .and_then(|tracking| tracking.nodes.lock().ok()?.history(node_id).cloned())
+            .unwrap_or_default()
+    }
+    
+    fn current_tracking(&self) -> Option<RunTracking> {
+        self.current.lock().ok().map(|tracking| tracking.clone())
+    }
+    
+    /// 设置子图文件引用（config.path）的基准目录，通常为工作流文件所在目录
+    pub fn set_subgraph_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.subgraph_dir = dir.into();
+    }
+    
+    /// 展开工作流中的子图节点，内部节点ID变为"<子图节点ID>/<内部节点ID>"
+    pub fn expand_subgraphs(&self, workflow: &WorkflowData) -> Result<WorkflowData, WorkflowError> {
+        subgraph::expand_subgraphs(workflow, &self.subgraph_dir, &|node| self.node_ports(node))
+    }
+    
+    /// 子图节点暴露的端口（内部未连接的端口，ID为"<内部节点ID>.<端口ID>"）
+    pub fn subgraph_ports(&self, node: &NodeData) -> Result<DynamicPorts, WorkflowError> {
+        subgraph::subgraph_ports(node, &self.subgraph_dir, &|node| self.node_ports(node))
+    }
+    
+    /// 展开子图后按端口检查每条边的类型兼容性和扇入
+    pub fn validate_edges(&self, workflow: &WorkflowData) -> Result<EdgeTypeReport, WorkflowError> {
+        let workflow = self.expand_subgraphs(workflow)?;
+        Ok(validator::validate_edge_types(&workflow, &|node| self.node_ports(node)))
+    }
+    
+    /// 完整验证工作流（子图展开后），返回所有问题；模型文件相对于子图基准目录查找
+    pub fn validate_workflow(&self, workflow: &WorkflowData) -> ValidationReport {
+        match self.expand_subgraphs(workflow) {
+            Ok(expanded) => validator::validate_workflow(&expanded, &|node| self.node_stub(node), &self.subgraph_dir),
+            Err(e) => {
+                let mut report = ValidationReport::default();
+                report.error(IssueCode::InvalidSubgraph, e.to_string(), Vec::new(), Vec::new());
+                report
+            }
+        }
+    }
+    
+    fn node_ports(&self, node: &NodeData) -> Result<DynamicPorts, WorkflowError> {
+        Ok(self.node_stub(node)?.ports())
+    }
+    
+    // 仅用于查询端口等静态信息的实例；循环节点不实例化循环体
+    fn node_stub(&self, node: &NodeData) -> Result<Box<dyn Node>, WorkflowError> {
+        match LoopKind::from_node(node)? {
+            Some(kind) => Ok(Box::new(LoopNode::new(&node.id, kind, LoopBody::new(Vec::new(), HashMap::new(), Vec::new())))),
+            None => self.registry.create(node),
+        }
+    }
+    
+    /// 最近一次运行的ID（运行失败时用于resume_workflow）
+    pub fn last_run_id(&self) -> Option<String> {
+        self.last_run_id.lock().ok().and_then(|id| id.clone())
+    }
+    
+    /// 获取节点注册表，用于注册外部节点类型
+    pub fn registry_mut(&mut self) -> &mut NodeRegistry {
+        &mut self.registry
+    }
     
     pub fn run_simple_workflow(&self, input_text: &str, model_id: &str) {
         println!("开始执行工作流...");
+        let result = self.run_simple_chain(input_text, model_id);
+        match result {
+            Ok(()) => println!("工作流完成!"),
+            Err(e) => println!("工作流失败: {}", e.message),
+        }
+    }
+    
+    fn run_simple_chain(&self, input_text: &str, model_id: &str) -> Result<(), NodeError> {
         let input_node = TextInputNode::new(input_text);
-        let prompt = input_node.execute();
-        println!("输入: {}", prompt);
-        
-        let llm_node = LLMNode::new(model_id);
-        let response = llm_node.execute(&prompt, &self.ctx);
-        println!("推理: {}", response);
+        let prompt = input_node.execute(HashMap::new())?.remove("text").unwrap_or(DataValue::Text(String::new()));
+        println!("输入: {:?}", prompt);
         
-        let mut output_node = TextOutputNode::new();
-        output_node.execute(&response);
+        let llm_node = LLMNode::new(model_id, Arc::clone(&self.ctx.vram_pool));
+        let mut llm_inputs = HashMap::new();
+        llm_inputs.insert("prompt".to_string(), prompt);
+        let response = llm_node.execute(llm_inputs)?.remove("response").unwrap_or(DataValue::Text(String::new()));
+        println!("推理: {:?}", response);
         
-        println!("工作流完成!");
+        let output_node = TextOutputNode::new();
+        let mut output_inputs = HashMap::new();
+        output_inputs.insert("text".to_string(), response);
+        output_node.execute(output_inputs)?;
+        Ok(())
     }
     
     pub async fn execute_workflow(&self, workflow: &WorkflowData) -> Result<ExecutionResult, WorkflowError> {
-        // 1. 拓扑排序获取执行顺序
-        let execution_order = self.topological_sort(workflow)?;
+        self.execute_workflow_with_cancel(workflow, &CancellationToken::new()).await
+    }
+    
+    /// 可取消的执行：cancel被触发后不再派发新节点，等待运行中的节点响应取消后返回WorkflowError::Cancelled
+    pub async fn execute_workflow_with_cancel(&self, workflow: &WorkflowData, cancel: &CancellationToken) -> Result<ExecutionResult, WorkflowError> {
+        self.execute_workflow_with_params(workflow, &ParameterValues::new(), cancel).await
+    }
+    
+    /// 以覆盖的参数值执行：未覆盖的参数使用声明的默认值。运行日志记录代入参数后的工作流
+    ///
+    /// 先展开子图：子图声明的参数并入外层，可以覆盖，子图内部节点中的引用一同替换
+    pub async fn execute_workflow_with_params(&self, workflow: &WorkflowData, overrides: &ParameterValues, cancel: &CancellationToken) -> Result<ExecutionResult, WorkflowError> {
+        let source = workflow;
+        let expanded = self.expand_subgraphs(workflow)?;
+        let request = RunRequest {
+            workflow_hash: workflow_hash(source),
+            parameters: params::resolve_values(&expanded.parameters, overrides)?,
+            replay_of: None,
+        };
+        let workflow = params::apply_parameters(&expanded, overrides)?;
+        let journal = self.journal_store.as_ref().map(|_| RunJournal::new(new_run_id(), source, &workflow));
+        self.run_workflow(&workflow, request, true, journal, cancel).await
+    }
+    
+    /// 启用运行历史：每次运行结束后追加到`<dir>/runs.jsonl`
+    pub fn set_history_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.history = Some(HistoryStore::new(dir));
+    }
+    
+    pub fn history(&self) -> Option<&HistoryStore> {
+        self.history.as_ref()
+    }
+    
+    /// 以历史记录中的工作流和参数重新运行，不使用输出缓存；新运行记录的replay_of为原运行ID
+    pub async fn replay_run(&self, run_id: &str, cancel: &CancellationToken) -> Result<ExecutionResult, WorkflowError> {
+        let store = self.history.as_ref()
+            .ok_or_else(|| WorkflowError::History("未配置运行历史目录".into()))?;
+        let record = store.get(run_id)?;
+        let request = RunRequest {
+            workflow_hash: record.workflow_hash,
+            parameters: record.parameters,
+            replay_of: Some(record.run_id),
+        };
+        let journal = self.journal_store.as_ref().map(|_| RunJournal::new(new_run_id(), &record.workflow, &record.workflow));
+        self.run_workflow(&record.workflow, request, false, journal, cancel).await
+    }
+    
+    /// 从运行日志恢复中断的运行：已完成节点直接使用记录的输出，其余节点重新执行
+    ///
+    /// workflow为调用方当前的工作流，与运行时提交的不一致（已被编辑）时拒绝恢复
+    pub async fn resume_workflow(&self, workflow: &WorkflowData, run_id: &str) -> Result<ExecutionResult, WorkflowError> {
+        self.resume_workflow_with_cancel(workflow, run_id, &CancellationToken::new()).await
+    }
+    
+    pub async fn resume_workflow_with_cancel(&self, workflow: &WorkflowData, run_id: &str, cancel: &CancellationToken) -> Result<ExecutionResult, WorkflowError> {
+        let store = self.journal_store.as_ref()
+            .ok_or_else(|| WorkflowError::Journal("未配置运行日志目录".into()))?;
+        let journal = store.load(run_id)?;
+        if !journal.matches_workflow(workflow) {
+            return Err(WorkflowError::Journal(format!("运行{}的工作流哈希不匹配", run_id)));
+        }
+        let workflow = journal.workflow.clone();
+        let request = RunRequest { workflow_hash: journal.workflow_hash.clone(), parameters: ParameterValues::new(), replay_of: None };
+        self.run_workflow(&workflow, request, true, Some(journal), cancel).await
+    }
+    
+    async fn run_workflow(
+        &self,
+        workflow: &WorkflowData,
+        request: RunRequest,
+        use_cache: bool,
+        mut journal: Option<RunJournal>,
+        cancel: &CancellationToken,
+    ) -> Result<ExecutionResult, WorkflowError> {
+        let source = workflow;
+        // 子图展开后按普通节点执行，状态中的节点ID即嵌套路径
+        let expanded = self.expand_subgraphs(workflow)?;
+        let workflow = &expanded;
+        
+        // 1. 拓扑排序检查循环依赖；循环体作为独立作用域，在实例化循环节点时检查
+        loops::check_scopes(workflow).map_err(WorkflowError::InvalidScope)?;
+        let top_level = loops::scope_view(workflow, None);
+        let order = self.topological_sort(&top_level)?;
+        
+        // 2. 实例化所有顶层节点（循环体随循环节点一起构建）
+        let tracking = RunTracking::new(&self.events);
+        let mut instances: HashMap<String, Arc<dyn Node>> = HashMap::new();
+        for node in &top_level.nodes {
+            instances.insert(node.id.clone(), self.instantiate(workflow, node, &tracking)?);
+        }
+        let edges = self.resolve_edges(&top_level, &instances)?;
+        let mut graph = ConnectionGraph::new();
+        for (_, connection) in &edges {
+            graph.connect(connection.clone());
+        }
+        let policies = top_level.nodes.iter()
+            .map(|node| error_policy(node).map(|policy| (node.id.clone(), policy)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
         
-        let mut context = ExecutionContext::new();
+        let is_stream = |c: &Connection| instances.get(&c.from_node)
+            .and_then(|node| node.ports().get_output(&c.from_port).map(|port| is_stream_type(&port.data_type)))
+            .unwrap_or(false);
+        let stream_edges: Vec<Connection> = edges.iter()
+            .filter(|(_, c)| is_stream(c))
+            .map(|(_, c)| c.clone())
+            .collect();
+        // 同一对节点间有流式边时不能再有普通边，否则消费者等待生产者完成而生产者阻塞在流上
+        let conflict = edges.iter()
+            .find(|(_, c)| !is_stream(c) && stream_edges.iter().any(|s| s.from_node == c.from_node && s.to_node == c.to_node));
+        if let Some((edge_id, c)) = conflict {
+            return Err(WorkflowError::InvalidEdge(EdgeError {
+                edge_id: edge_id.clone(),
+                kind: EdgeErrorKind::StreamWithValueEdge { producer: c.from_node.clone(), consumer: c.to_node.clone() },
+            }));
+        }
+        
+        let run_id = journal.as_ref().map(|journal| journal.run_id.clone()).unwrap_or_else(new_run_id);
+        let designated = outputs::designated_outputs(&top_level).into_iter().collect();
+        let plan = RunPlan { workflow: &top_level, instances, policies, graph, edges, stream_edges, use_cache, order, run_id: run_id.clone(), outputs: designated, tracking };
+        
+        let mut context = self.ctx.new_run();
+        let mut summary = ExecutionResult {
+            run_id,
+            outputs: WorkflowOutputs::new(),
+            nodes: Vec::new(),
+            cached_nodes: Vec::new(),
+            resumed_nodes: Vec::new(),
+        };
+        if let Ok(mut last_run_id) = self.last_run_id.lock() {
+            *last_run_id = Some(summary.run_id.clone());
+        }
+        
+        let started = Instant::now();
+        self.begin_run(&plan.tracking, &top_level, &summary.run_id);
+        let result = self.run_scheduled(&plan, &mut context, &mut summary, &mut journal, cancel).await;
+        summary.outputs = outputs::collect_outputs(&top_level, &context);
+        summary.nodes = plan.tracking.snapshot().iter().map(NodeRunInfo::from).collect();
+        let recorded = self.record_history(source, request, &plan, &context, &summary, started, &result);
+        self.finish_run(&plan.tracking, &summary.run_id, started, &result);
+        self.finish_journal(&plan.tracking, &mut journal);
+        result?;
+        recorded?;
+        Ok(summary)
+    }
+    
+    // 运行结束（无论成败）后追加到运行历史；运行本身失败时返回运行的错误，否则返回写入错误
+    #[allow(clippy::too_many_arguments)]
+    fn record_history(
+        &self,
+        source: &WorkflowData,
+        request: RunRequest,
+        plan: &RunPlan<'_>,
+        context: &ExecutionContext,
+        summary: &ExecutionResult,
+        started: Instant,
+        result: &Result<(), WorkflowError>,
+    ) -> Result<(), WorkflowError> {
+        let Some(store) = &self.history else { return Ok(()) };
+        let elapsed = started.elapsed();
+        let started_at = SystemTime::now().checked_sub(elapsed)
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let to_json = |values: Option<&HashMap<String, DataValue>>| -> BTreeMap<String, serde_json::Value> { values
+            .into_iter()
+            .flatten()
+            .filter_map(|(port, value)| Some((port.clone(), value_to_json(value)?)))
+            .collect() };
+        
+        let nodes = summary.nodes.iter()
+            .map(|info| NodeRecord {
+                node_id: info.node_id.clone(),
+                node_type: plan.workflow.get_node(&info.node_id).map(|node| node.type.clone()).unwrap_or_default(),
+                status: info.status.clone(),
+                duration_ms: info.duration_ms,
+                error: info.error.clone(),
+                inputs: to_json(context.get_inputs(&info.node_id)),
+                outputs: to_json(context.get_outputs(&info.node_id)),
+            })
+            .collect();
+        let models = plan.workflow.nodes.iter()
+            .filter_map(|node| {
+                let model = plan.instances.get(&node.id)?.required_model()?;
+                Some(ModelUsage {
+                    node_id: node.id.clone(),
+                    model: FileVersion::of(&model),
+                    lora: node.config_str("lora_path").map(FileVersion::of),
+                })
+            })
+            .collect();
         
-        // 2. 按顺序执行节点
-        for node_id in execution_order {
-            let node = workflow.get_node(&node_id).ok_or(WorkflowError::NodeNotFound(node_id.clone()))?;
-            let inputs = self.collect_inputs(node, &context)?;
+        let record = RunRecord {
+            run_id: summary.run_id.clone(),
+            started_at,
+            workflow_hash: request.workflow_hash,
+            parameters: request.parameters,
+            status: match result {
+                Ok(_) => "Completed",
+                Err(WorkflowError::Cancelled) => "Cancelled",
+                Err(_) => "Error",
+            }.to_string(),
+            duration_ms: elapsed.as_millis() as u64,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            nodes,
+            outputs: outputs_to_json(&summary.outputs),
+            models,
+            replay_of: request.replay_of,
+            workflow: source.clone(),
+        };
+        store.append(&record)
+    }
+    
+    /// 并发调度：依赖满足的节点立即派发到阻塞线程池，受并发上限和VRAM槽位约束
+    async fn run_scheduled(
+        &self,
+        plan: &RunPlan<'_>,
+        context: &mut ExecutionContext,
+        summary: &mut ExecutionResult,
+        journal: &mut Option<RunJournal>,
+        cancel: &CancellationToken,
+    ) -> Result<(), WorkflowError> {
+        let RunPlan { workflow, instances, policies, graph, .. } = plan;
+        let required_models: HashMap<String, String> = instances.iter()
+            .filter_map(|(id, node)| node.required_model().map(|model| (id.clone(), model)))
+            .collect();
+        // 同时驻留的模型数由显存预算决定；按全部卸载到GPU估算，后端无法加载的模型ID不占显存
+        let model_slots = self.ctx.vram_pool.lock()
+            .map(|pool| {
+                let footprints: Vec<usize> = required_models.values()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .map(|model| pool.estimate_footprint(Path::new(model), &full_offload()).unwrap_or(0))
+                    .collect();
+                pool.model_slots(&footprints)
+            })
+            .map_err(|_| WorkflowError::TaskFailed("VRAM池锁中毒".into()))?;
+        let partial_inputs = instances.iter()
+            .filter(|(_, node)| node.accepts_partial_inputs())
+            .map(|(id, _)| id.clone())
+            .collect();
+        let stream_pairs = plan.stream_edges.iter()
+            .map(|c| (c.from_node.clone(), c.to_node.clone()))
+            .collect();
+        let mut scheduler = Scheduler::new(workflow, required_models, model_slots, self.config)
+            .with_partial_inputs(partial_inputs)
+            .with_stream_edges(stream_pairs);
+        
+        // 每条流式边一个通道，节点派发时取走自己的发送端/接收端
+        let mut streams: HashMap<String, NodeStreams> = HashMap::new();
+        for connection in &plan.stream_edges {
+            let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+            streams.entry(connection.from_node.clone()).or_default().add_output(connection.from_port.clone(), sender);
+            streams.entry(connection.to_node.clone()).or_default().add_input(connection.to_port.clone(), receiver);
+        }
+        let mut running = NodeTasks::new();
+        let mut retry_counts: HashMap<String, u32> = HashMap::new();
+        let mut cache_keys: HashMap<String, String> = HashMap::new();
+        let mut failure: Option<WorkflowError> = None;
+        // 已派发（含缓存命中、恢复）的节点与正在执行的节点，用于规划模型
+        let mut dispatched: HashSet<String> = HashSet::new();
+        let mut in_flight: HashSet<String> = HashSet::new();
+        let mut prefetcher = Prefetcher::default();
+        
+        loop {
+            // 出错或取消后不会再派发节点：丢弃未取走的通道，避免生产者阻塞在无人读取的流上
+            if failure.is_some() || cancel.is_cancelled() {
+                streams.clear();
+            }
+            streams.retain(|node_id, _| !scheduler.is_skipped(node_id));
+            
+            // 派发所有可立即执行的节点（出错或取消后不再派发）
+            while failure.is_none() && !cancel.is_cancelled() {
+                let node_id = match scheduler.next_ready() {
+                    Some(node_id) => node_id,
+                    None => break,
+                };
+                dispatched.insert(node_id.clone());
+                let (Some(node), Some(instance)) = (workflow.get_node(&node_id), instances.get(&node_id)) else {
+                    failure = Some(WorkflowError::NodeNotFound(node_id));
+                    break;
+                };
+                
+                // 恢复运行时，日志中已完成的节点直接使用记录的输出
+                if let Some(outputs) = journal.as_ref().and_then(|journal| journal.completed_outputs(&node_id)).cloned() {
+                    plan.tracking.with_tracker(|tracker| { let _ = tracker.mark_running(&node_id, RunningSubState::Normal); });
+                    self.complete_node(plan, &mut scheduler, &node_id, &outputs);
+                    context.set_outputs(node_id.clone(), outputs);
+                    summary.resumed_nodes.push(node_id);
+                    continue;
+                }
+                
+                let inputs = match self.collect_inputs(node, instance.as_ref(), graph, context) {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        plan.tracking.with_tracker(|tracker| {
+                            let _ = tracker.mark_running(&node_id, RunningSubState::Normal);
+                            let _ = tracker.mark_failed(&node_id, fatal_error_info(NodeError::new(e.to_string(), 0, None)));
+                        });
+                        failure = Some(e);
+                        break;
+                    }
+                };
+                
+                if self.history.is_some() {
+                    context.set_inputs(node_id.clone(), inputs.clone());
+                }
+                plan.tracking.with_tracker(|tracker| { let _ = tracker.mark_running(&node_id, RunningSubState::Normal); });
+                
+                // 类型、配置和输入均未变化时复用缓存的输出；循环体的变化不体现在循环节点的缓存键中，
+                // 流式块不参与缓存键，循环节点、流式节点和有副作用的节点不缓存
+                let uncacheable = !plan.use_cache || always_rerun(node) || !instance.cacheable()
+                    || loops::is_loop_type(&node.type) || plan.is_streaming(&node_id);
+                let key = if uncacheable { None } else { cache_key(node, &inputs) };
+                if let Some(key) = key {
+                    if let Some(outputs) = self.cached_outputs(context, &key) {
+                        self.journal_completed(plan, journal, &node_id, &outputs);
+                        self.complete_node(plan, &mut scheduler, &node_id, &outputs);
+                        context.set_outputs(node_id.clone(), outputs);
+                        summary.cached_nodes.push(node_id);
+                        continue;
+                    }
+                    cache_keys.insert(node_id.clone(), key);
+                }
+                
+                let node_streams = self.observe_tokens(streams.remove(&node_id).unwrap_or_default(), &summary.run_id, &node_id);
+                self.pin_model(instance.as_ref(), &summary.run_id);
+                in_flight.insert(node_id.clone());
+                spawn_node(&mut running, node_id, Arc::clone(instance), inputs, node_streams, Duration::ZERO, cancel.clone());
+            }
+            self.update_running_state(&plan.tracking, scheduler.active_tasks());
+            if failure.is_none() && !cancel.is_cancelled() {
+                self.plan_models(plan, &scheduler, &dispatched, &in_flight, &mut prefetcher, cancel);
+            }
+            if failure.is_none() && !cancel.is_cancelled() && scheduler.is_idle() {
+                break;
+            }
+            
+            // 等待任一节点完成；取消时立即回到循环顶部停止派发
+            let joined = if cancel.is_cancelled() {
+                running.join_next().await
+            } else {
+                tokio::select! {
+                    joined = running.join_next() => joined,
+                    _ = cancel.cancelled() => continue,
+                }
+            };
+            let joined = match joined {
+                Some(joined) => joined,
+                None => break,
+            };
+            self.publish_pool_events();
+            let (node_id, outputs) = match joined {
+                Ok(result) => result,
+                Err(e) => {
+                    failure.get_or_insert(WorkflowError::TaskFailed(e.to_string()));
+                    continue;
+                }
+            };
+            in_flight.remove(&node_id);
+            self.unpin_model(plan, &in_flight, &node_id);
             
-            // 3. 根据节点类型执行
-            let outputs = match node.type.as_str() {
-                "input" => self.execute_input_node(node, inputs)?,
-                "llm" => self.execute_llm_node(node, inputs).await?,
-                "output" => self.execute_output_node(node, inputs)?,
-                _ => return Err(WorkflowError::UnknownNodeType(node.type.clone())),
+            let error = match outputs {
+                Ok(outputs) => {
+                    if let Some(key) = cache_keys.remove(&node_id) {
+                        if let Ok(mut cache) = context.output_cache.lock() {
+                            cache.insert(key, outputs.clone());
+                        }
+                    }
+                    self.journal_completed(plan, journal, &node_id, &outputs);
+                    self.complete_node(plan, &mut scheduler, &node_id, &outputs);
+                    // 存储结果到上下文
+                    context.set_outputs(node_id, outputs);
+                    continue;
+                }
+                Err(error) => error,
             };
             
-            // 4. 存储结果到上下文
-            context.set_outputs(node_id, outputs);
+            // 取消后的失败不再走恢复策略
+            if cancel.is_cancelled() {
+                plan.tracking.with_tracker(|tracker| { let _ = tracker.mark_cancelled(&node_id); });
+                continue;
+            }
+            
+            // 按节点的错误策略恢复；流式输出的生产者不重试，直接失败
+            let retry_count = retry_counts.get(&node_id).copied().unwrap_or(0);
+            let action = policies.get(&node_id).cloned().unwrap_or(RecoveryAction::ImmediateFail);
+            match action {
+                RecoveryAction::Retry { max_attempts, backoff }
+                    if failure.is_none() && retry_count + 1 < max_attempts && !plan.produces_stream(&node_id) => {
+                    let retry_count = retry_count + 1;
+                    retry_counts.insert(node_id.clone(), retry_count);
+                    
+                    let info = ErrorInfo {
+                        error,
+                        recoverable: true,
+                        retry_count,
+                        suggested_action: RecoveryAction::Retry { max_attempts, backoff },
+                    };
+                    plan.tracking.with_tracker(|tracker| {
+                        let _ = tracker.mark_failed(&node_id, info);
+                        let _ = tracker.mark_running(&node_id, RunningSubState::Normal);
+                    });
+                    
+                    // 重试前的错误不直接返回：其它节点仍在执行，由循环等待它们结束
+                    let retried = match (workflow.get_node(&node_id), instances.get(&node_id)) {
+                        (Some(node), Some(instance)) => self.collect_inputs(node, instance.as_ref(), graph, context)
+                            .map(|inputs| (instance, inputs)),
+                        _ => Err(WorkflowError::NodeNotFound(node_id.clone())),
+                    };
+                    match retried {
+                        Ok((instance, inputs)) => {
+                            // 流在首次执行时已被消耗，重试不再连接流
+                            let node_streams = self.observe_tokens(NodeStreams::new(), &summary.run_id, &node_id);
+                            self.pin_model(instance.as_ref(), &summary.run_id);
+                            in_flight.insert(node_id.clone());
+                            spawn_node(&mut running, node_id, Arc::clone(instance), inputs, node_streams, retry_delay(backoff, retry_count), cancel.clone());
+                        }
+                        Err(e) => {
+                            plan.tracking.with_tracker(|tracker| {
+                                let _ = tracker.mark_failed(&node_id, fatal_error_info(NodeError::new(e.to_string(), 0, None)));
+                            });
+                            failure.get_or_insert(e);
+                        }
+                    }
+                }
+                RecoveryAction::Fallback { value } => {
+                    // 所有输出端口使用回退值
+                    let outputs = instances.get(&node_id)
+                        .map(|instance| instance.ports().outputs().map(|port| (port.id.clone(), value.clone())).collect())
+                        .unwrap_or_default();
+                    self.journal_completed(plan, journal, &node_id, &outputs);
+                    self.complete_node(plan, &mut scheduler, &node_id, &outputs);
+                    context.set_outputs(node_id, outputs);
+                }
+                RecoveryAction::Skip => {
+                    // 跳过节点及其下游
+                    let dependents = scheduler.skip(&node_id);
+                    plan.tracking.with_tracker(|tracker| {
+                        let _ = tracker.mark_skipped(&node_id);
+                        for dependent in &dependents {
+                            let _ = tracker.mark_skipped(dependent);
+                        }
+                    });
+                }
+                action => {
+                    let info = ErrorInfo {
+                        error: error.clone(),
+                        recoverable: false,
+                        retry_count,
+                        suggested_action: action,
+                    };
+                    plan.tracking.with_tracker(|tracker| { let _ = tracker.mark_failed(&node_id, info); });
+                    failure.get_or_insert(WorkflowError::NodeFailed { node_id, error });
+                }
+            }
+        }
+        
+        // 运行结束前等待预取完成，不让它在释放固定之后才放入模型
+        if let Some(task) = prefetcher.task.take() {
+            let _ = task.await;
+        }
+        
+        match failure {
+            Some(e) => {
+                // 未执行的节点标记为跳过
+                plan.tracking.with_tracker(|tracker| tracker.skip_pending());
+                Err(e)
+            }
+            None if cancel.is_cancelled() => {
+                plan.tracking.with_tracker(|tracker| tracker.cancel_unfinished());
+                Err(WorkflowError::Cancelled)
+            }
+            None => Ok(()),
+        }
+    }
+    
+    // 标记节点完成并释放下游；分支节点未产出的端口上的边为死边，只经死边可达的节点被跳过
+    fn complete_node(&self, plan: &RunPlan<'_>, scheduler: &mut Scheduler, node_id: &str, outputs: &HashMap<String, DataValue>) {
+        let mut dead_targets = Vec::new();
+        let branch = plan.instances.get(node_id).and_then(|instance| instance.branch_condition(outputs));
+        if let Some(condition) = branch {
+            let mut selected_branch = String::new();
+            for (edge_id, connection) in plan.edges.iter().filter(|(_, c)| c.from_node == node_id) {
+                if outputs.contains_key(&connection.from_port) {
+                    if selected_branch.is_empty() {
+                        selected_branch = edge_id.clone();
+                    }
+                } else {
+                    dead_targets.push(connection.to_node.clone());
+                }
+            }
+            plan.tracking.with_tracker(|tracker| {
+                let _ = tracker.mark_running(node_id, RunningSubState::Branching { condition, selected_branch });
+            });
         }
         
-        Ok(ExecutionResult { final_outputs: context.get_final_outputs() })
+        plan.tracking.with_tracker(|tracker| { let _ = tracker.mark_completed(node_id); });
+        self.publish_outputs(plan, node_id, outputs);
+        scheduler.complete_with_dead_edges(node_id, &dead_targets);
+        let pruned = scheduler.take_pruned();
+        plan.tracking.with_tracker(|tracker| {
+            for node_id in &pruned {
+                let _ = tracker.mark_skipped(node_id);
+            }
+        });
+    }
+    
+    // 工作流输出节点的值作为OutputProduced事件发布，按端口名排序
+    fn publish_outputs(&self, plan: &RunPlan<'_>, node_id: &str, outputs: &HashMap<String, DataValue>) {
+        let Some(ports) = plan.outputs.get(node_id) else { return };
+        let mut produced: Vec<(&String, serde_json::Value)> = outputs.iter()
+            .filter(|(port, _)| ports.as_ref().map_or(true, |ports| ports.contains(port)))
+            .filter_map(|(port, value)| Some((port, value_to_json(value)?)))
+            .collect();
+        produced.sort_by(|a, b| a.0.cmp(b.0));
+        for (port, value) in produced {
+            self.events.publish(ExecutionEvent::OutputProduced {
+                run_id: plan.run_id.clone(),
+                node_id: node_id.to_string(),
+                port: port.clone(),
+                value,
+            });
+        }
+    }
+    
+    // 记录节点完成并立即落盘；写入失败只记录警告，不影响本次运行
+    fn journal_completed(&self, plan: &RunPlan<'_>, journal: &mut Option<RunJournal>, node_id: &str, outputs: &HashMap<String, DataValue>) {
+        if let (Some(journal), Some(store)) = (journal.as_mut(), self.journal_store.as_ref()) {
+            if plan.is_streaming(node_id) {
+                journal.record_rerun(node_id);
+            } else {
+                journal.record_completed(node_id, outputs);
+            }
+            if let Err(e) = store.save(journal) {
+                tracing::warn!(run_id = %journal.run_id, "{}", e);
+            }
+        }
+    }
+    
+    // 运行结束时记录所有节点的最终状态
+    fn finish_journal(&self, tracking: &RunTracking, journal: &mut Option<RunJournal>) {
+        if let (Some(journal), Some(store)) = (journal.as_mut(), self.journal_store.as_ref()) {
+            for snapshot in tracking.snapshot() {
+                journal.record_status(&snapshot.node_id, snapshot.status());
+            }
+            if let Err(e) = store.save(journal) {
+                tracing::warn!(run_id = %journal.run_id, "{}", e);
+            }
+        }
+    }
+    
+    fn cached_outputs(&self, context: &ExecutionContext, key: &str) -> Option<HashMap<String, DataValue>> {
+        context.output_cache.lock().ok()?.get(key)
+    }
+    
+    /// 清空内存中的输出缓存，下次运行所有节点重新执行
+    pub fn clear_cache(&self) {
+        if let Ok(mut cache) = self.ctx.output_cache.lock() {
+            cache.clear();
+        }
+    }
+    
+    /// VRAM池中各模型所在的层、占用和最近一次迁移
+    pub fn slot_status(&self) -> Vec<SlotStatus> {
+        self.ctx.vram_pool.lock().map(|pool| pool.get_slot_status()).unwrap_or_default()
+    }
+    
+    // 本次运行成为当前运行，run_state/node_states此后读取它
+    fn begin_run(&self, tracking: &RunTracking, workflow: &WorkflowData, run_id: &str) {
+        if let Ok(mut state) = tracking.state.lock() {
+            let _ = state.transition(MainState::Pending);
+        }
+        if let Ok(mut current) = self.current.lock() {
+            *current = tracking.clone();
+        }
+        let node_ids: Vec<String> = workflow.nodes.iter().map(|node| node.id.clone()).collect();
+        self.events.publish(ExecutionEvent::RunStarted { run_id: run_id.to_string(), node_ids: node_ids.clone() });
+        tracking.with_tracker(|tracker| {
+            tracker.set_run_id(run_id);
+            tracker.reset(node_ids);
+            for node in &workflow.nodes {
+                let _ = tracker.mark_pending(&node.id);
+            }
+        });
+    }
+    
+    fn update_running_state(&self, tracking: &RunTracking, active_tasks: usize) {
+        let substate = if active_tasks > 1 {
+            RunningSubState::Concurrent { active_tasks }
+        } else {
+            RunningSubState::Normal
+        };
+        if let Ok(mut state) = tracking.state.lock() {
+            if *state.current_state() != MainState::Running(substate.clone()) {
+                let _ = state.transition(MainState::Running(substate));
+            }
+        }
+    }
+    
+    // 流式块作为TokenGenerated事件发布；没有订阅者时不挂观察者
+    fn observe_tokens(&self, mut streams: NodeStreams, run_id: &str, node_id: &str) -> NodeStreams {
+        if self.events.has_subscribers() {
+            let events = self.events.clone();
+            let (run_id, node_id) = (run_id.to_string(), node_id.to_string());
+            streams.set_observer(Arc::new(move |port, chunk| {
+                if let Some(token) = chunk.as_text() {
+                    events.publish(ExecutionEvent::TokenGenerated {
+                        run_id: run_id.clone(),
+                        node_id: node_id.clone(),
+                        port: port.to_string(),
+                        token: token.to_string(),
+                    });
+                }
+            }));
+        }
+        streams
+    }
+    
+    // 执行中的节点所需的模型（下次使用为0）和按拓扑顺序列出的尚未派发的节点所需的模型，交给VRAM池做Belady式淘汰。
+    // 有节点在执行时预取下一个不在显存中的模型，隐藏加载延迟；加载期间不持有池的锁
+    fn plan_models(
+        &self,
+        plan: &RunPlan<'_>,
+        scheduler: &Scheduler,
+        dispatched: &HashSet<String>,
+        in_flight: &HashSet<String>,
+        prefetcher: &mut Prefetcher,
+        cancel: &CancellationToken,
+    ) {
+        let model_of = |node_id: &String| plan.instances.get(node_id).and_then(|instance| instance.required_model());
+        let upcoming: Vec<String> = plan.order.iter()
+            .filter(|node_id| !dispatched.contains(*node_id) && !scheduler.is_skipped(node_id))
+            .filter_map(model_of)
+            .collect();
+        let in_use: HashSet<String> = in_flight.iter().filter_map(model_of).collect();
+        let in_use_models: Vec<String> = in_use.iter().cloned().collect();
+        
+        let Ok(mut pool) = self.ctx.vram_pool.lock() else { return };
+        pool.plan_upcoming(&plan.run_id, &in_use_models, &upcoming);
+        
+        if in_flight.is_empty() || prefetcher.task.as_ref().map_or(false, |task| !task.is_finished()) {
+            return;
+        }
+        let next = upcoming.iter().find(|model| {
+            !in_use.contains(*model)
+                && !prefetcher.attempted.contains(*model)
+                && pool.tier(model) != Some(Tier::Gpu)
+                && pool.estimate_footprint(Path::new(model), &full_offload()).is_ok()
+        });
+        let Some(model) = next.cloned() else { return };
+        drop(pool);
+        
+        prefetcher.attempted.insert(model.clone());
+        let pool = Arc::clone(&self.ctx.vram_pool);
+        let events = self.events.clone();
+        let cancel = cancel.clone();
+        prefetcher.task = Some(tokio::task::spawn_blocking(move || {
+            // 取消导致的失败不报告
+            if let Err(e) = VramPool::prefetch_shared(&pool, &model, Path::new(&model), full_offload(), cancel.as_flag()) {
+                if !cancel.is_cancelled() {
+                    events.publish(ExecutionEvent::ModelPrefetchFailed { model_id: model, error: e.to_string() });
+                }
+            }
+        }));
+    }
+    
+    // 派发时由本次运行固定节点所需的模型，尚未驻留的模型在加载完成时固定，生成期间不会被淘汰；
+    // 节点结束后由unpin_model解除，运行结束时释放剩余的固定
+    fn pin_model(&self, instance: &dyn Node, run_id: &str) {
+        let Some(model) = instance.required_model() else { return };
+        if let Ok(mut pool) = self.ctx.vram_pool.lock() {
+            pool.pin(&model, run_id);
+        }
+    }
+    
+    // 节点结束后立即解除其模型的固定（仍有执行中的节点在用时保留），之后派发的节点加载模型时可以淘汰它
+    fn unpin_model(&self, plan: &RunPlan<'_>, in_flight: &HashSet<String>, node_id: &str) {
+        let model_of = |id: &str| plan.instances.get(id).and_then(|instance| instance.required_model());
+        let Some(model) = model_of(node_id) else { return };
+        if in_flight.iter().any(|other| model_of(other).as_ref() == Some(&model)) {
+            return;
+        }
+        if let Ok(mut pool) = self.ctx.vram_pool.lock() {
+            pool.unpin(&model, &plan.run_id);
+        }
+    }
+    
+    // 将VRAM池记录的模型加载/淘汰发布为事件
+    fn publish_pool_events(&self) {
+        let pool_events = match self.ctx.vram_pool.lock() {
+            Ok(mut pool) => pool.take_events(),
+            Err(_) => return,
+        };
+        for event in pool_events {
+            self.events.publish(match event {
+                PoolEvent::Loaded(model_id) => ExecutionEvent::ModelLoaded { model_id },
+                PoolEvent::Evicted(model_id) => ExecutionEvent::ModelEvicted { model_id },
+                PoolEvent::Released(model_id) => ExecutionEvent::ModelReleased { model_id },
+                PoolEvent::DemotionFailed { model_id, error } => ExecutionEvent::ModelDemotionFailed { model_id, error },
+                PoolEvent::TierChanged { model_id, transition } => ExecutionEvent::ModelTierChanged {
+                    model_id,
+                    from: transition.from,
+                    to: transition.to,
+                    duration_ms: transition.duration_ms,
+                },
+            });
+        }
+    }
+    
+    fn finish_run<T>(&self, tracking: &RunTracking, run_id: &str, started: Instant, result: &Result<T, WorkflowError>) {
+        if let Ok(mut pool) = self.ctx.vram_pool.lock() {
+            pool.release_pins(run_id);
+            pool.clear_plan(run_id);
+        }
+        self.publish_pool_events();
+        self.events.publish(ExecutionEvent::RunFinished {
+            run_id: run_id.to_string(),
+            status: match result {
+                Ok(_) => "Completed",
+                Err(WorkflowError::Cancelled) => "Cancelled",
+                Err(_) => "Error",
+            }.to_string(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+
+        let final_state = match result {
+            Ok(_) => MainState::Completed,
+            Err(WorkflowError::Cancelled) => MainState::Cancelled,
+            Err(e) => {
+                let error = match e {
+                    WorkflowError::NodeFailed { error, .. } => error.clone(),
+                    other => NodeError::new(other.to_string(), 0, None),
+                };
+                MainState::Error(fatal_error_info(error))
+            }
+        };
+        if let Ok(mut state) = tracking.state.lock() {
+            if let MainState::Pending = state.current_state() {
+                let _ = state.transition(MainState::Running(RunningSubState::Normal));
+            }
+            let _ = state.transition(final_state);
+        }
     }
     
     fn topological_sort(&self, workflow: &WorkflowData) -> Result<Vec<String>, WorkflowError> {
@@ -121,36 +1106,177 @@ impl WorkflowExecutor {
         Ok(result)
     }
     
-    fn collect_inputs(&self, node: &NodeData, context: &ExecutionContext) -> Result<HashMap<String, DataValue>, WorkflowError> {
-        // 简化版：从上下文收集输入
-        Ok(HashMap::new())
+    /// 实例化节点：循环节点连同其循环体一起构建，其余节点通过注册表创建
+    fn instantiate(&self, workflow: &WorkflowData, node: &NodeData, tracking: &RunTracking) -> Result<Arc<dyn Node>, WorkflowError> {
+        let kind = match LoopKind::from_node(node)? {
+            Some(kind) => kind,
+            None => return Ok(Arc::from(self.registry.create(node)?)),
+        };
+        
+        let body_view = loops::scope_view(workflow, Some(&node.id));
+        let order = self.topological_sort(&body_view)?;
+        let mut instances: HashMap<String, Arc<dyn Node>> = HashMap::new();
+        for child in &body_view.nodes {
+            instances.insert(child.id.clone(), self.instantiate(workflow, child, tracking)?);
+        }
+        
+        // 循环体内部的边，加上循环节点与子节点之间的端口连接
+        let mut connections: Vec<Connection> = self.resolve_edges(&body_view, &instances)?
+            .into_iter()
+            .map(|(_, connection)| connection)
+            .collect();
+        for edge in &workflow.edges {
+            if edge.source == node.id && instances.contains_key(&edge.target) {
+                let to_port = match &edge.target_handle {
+                    Some(handle) => handle.clone(),
+                    None => sole_port(instances[&edge.target].ports().inputs())
+                        .ok_or(WorkflowError::PortNotSpecified(edge.target.clone()))?,
+                };
+                connections.push(Connection {
+                    from_node: node.id.clone(),
+                    from_port: edge.source_handle.clone().unwrap_or_else(|| kind.default_body_input().to_string()),
+                    to_node: edge.target.clone(),
+                    to_port,
+                });
+            } else if edge.target == node.id && instances.contains_key(&edge.source) {
+                let from_port = match &edge.source_handle {
+                    Some(handle) => handle.clone(),
+                    None => sole_port(instances[&edge.source].ports().outputs())
+                        .ok_or(WorkflowError::PortNotSpecified(edge.source.clone()))?,
+                };
+                connections.push(Connection {
+                    from_node: edge.source.clone(),
+                    from_port,
+                    to_node: node.id.clone(),
+                    to_port: edge.target_handle.clone().unwrap_or_else(|| kind.default_body_output().to_string()),
+                });
+            }
+        }
+        
+        // 每次迭代开始时将循环节点切换到Iterating子状态
+        let tracker = Arc::clone(&tracking.nodes);
+        let loop_id = node.id.clone();
+        let on_iteration: IterationCallback = Arc::new(move |current, total| {
+            if let Ok(mut tracker) = tracker.lock() {
+                let _ = tracker.mark_running(&loop_id, RunningSubState::Iterating { current, total });
+            }
+        });
+        let body = LoopBody::new(order, instances, connections);
+        Ok(Arc::new(LoopNode::new(&node.id, kind, body).with_iteration_callback(on_iteration)))
     }
     
-    fn execute_input_node(&self, node: &NodeData, inputs: HashMap<String, DataValue>) -> Result<HashMap<String, DataValue>, WorkflowError> {
-        // 从节点数据中获取输入文本
-        let text = node.data.get("label").and_then(|v| v.as_str()).unwrap_or("").to_string();
-        let mut outputs = HashMap::new();
-        outputs.insert("text".to_string(), DataValue::Text(text));
-        Ok(outputs)
+    /// 将边解析为端口级连接，未指定端口句柄的边使用节点唯一的输入/输出端口
+    fn resolve_edges(&self, workflow: &WorkflowData, instances: &HashMap<String, Arc<dyn Node>>) -> Result<Vec<(String, Connection)>, WorkflowError> {
+        let mut edges = Vec::new();
+        for edge in &workflow.edges {
+            let source = instances.get(&edge.source).ok_or(WorkflowError::NodeNotFound(edge.source.clone()))?;
+            let target = instances.get(&edge.target).ok_or(WorkflowError::NodeNotFound(edge.target.clone()))?;
+            
+            let from_port = match &edge.source_handle {
+                Some(handle) => handle.clone(),
+                None => sole_port(source.ports().outputs())
+                    .ok_or(WorkflowError::PortNotSpecified(edge.source.clone()))?,
+            };
+            let to_port = match &edge.target_handle {
+                Some(handle) => handle.clone(),
+                None => sole_port(target.ports().inputs())
+                    .ok_or(WorkflowError::PortNotSpecified(edge.target.clone()))?,
+            };
+            
+            edges.push((edge.id.clone(), Connection {
+                from_node: edge.source.clone(),
+                from_port,
+                to_node: edge.target.clone(),
+                to_port,
+            }));
+        }
+        Ok(edges)
     }
     
-    async fn execute_llm_node(&self, node: &NodeData, inputs: HashMap<String, DataValue>) -> Result<HashMap<String, DataValue>, WorkflowError> {
-        // 从输入中获取文本
-        let prompt = inputs.get("text").map(|v| v.to_string()).unwrap_or("").to_string();
-        
-        // 简化版：生成回复
-        let result = format!("AI回复: 收到 '{}'", prompt);
+    /// 通过连接图将每个输入端口解析为上游节点已存储的输出；
+    /// 汇合节点（accepts_partial_inputs）缺少上游输出的端口不传入，其余节点报告缺少输入
+    fn collect_inputs(&self, node: &NodeData, instance: &dyn Node, graph: &ConnectionGraph, context: &ExecutionContext) -> Result<HashMap<String, DataValue>, WorkflowError> {
+        let mut inputs = HashMap::new();
+        let ports = instance.ports();
+        for port_id in graph.input_ports(&node.id) {
+            // 流式输入在执行时通过NodeStreams读取
+            if ports.get_input(port_id).map_or(false, |port| is_stream_type(&port.data_type)) {
+                continue;
+            }
+            let (source_node, source_port) = graph.get_source(&node.id, port_id)
+                .ok_or_else(|| WorkflowError::MissingInput { node_id: node.id.clone(), port_id: port_id.clone() })?;
+            let value = match context.get_output(source_node, source_port) {
+                Some(value) => value,
+                None if instance.accepts_partial_inputs() => continue,
+                None => return Err(WorkflowError::MissingInput { node_id: node.id.clone(), port_id: port_id.clone() }),
+            };
+            inputs.insert(port_id.clone(), value.clone());
+        }
         
-        let mut outputs = HashMap::new();
-        outputs.insert("result".to_string(), DataValue::Text(result));
-        Ok(outputs)
+        // 未连接的端口使用节点的default_inputs：导入的ComfyUI节点把常量参数（如OllamaGenerate的prompt）
+        // 写在这里，缺少它们时导入的工作流无法运行；缓存键和参数替换同样覆盖default_inputs
+        let defaults = node.default_inputs.as_ref().and_then(|d| d.as_object()).into_iter().flatten();
+        for (port_id, value) in defaults {
+            if ports.get_input(port_id).is_some() && !graph.input_ports(&node.id).contains(&port_id) {
+                if let Some(value) = json_to_value(value) {
+                    inputs.insert(port_id.clone(), value);
+                }
+            }
+        }
+        Ok(inputs)
     }
-    
-    fn execute_output_node(&self, node: &NodeData, inputs: HashMap<String, DataValue>) -> Result<HashMap<String, DataValue>, WorkflowError> {
-        // 从输入中获取结果
-        let result = inputs.get("result").map(|v| v.to_string()).unwrap_or("").to_string();
-        let mut outputs = HashMap::new();
-        outputs.insert("output".to_string(), DataValue::Text(result));
-        Ok(outputs)
+}
+
+/// 节点仅有一个非流式端口时返回其ID（边未指定端口句柄时使用，流式端口需显式连接）
+pub(crate) fn sole_port<'a>(ports: impl Iterator<Item = &'a Port>) -> Option<String> {
+    let mut ports = ports.filter(|port| !is_stream_type(&port.data_type));
+    match (ports.next(), ports.next()) {
+        (Some(port), None) => Some(port.id.clone()),
+        _ => None,
     }
-}
\ No newline at end of file
+}
+
+/// 不可恢复的错误信息
+fn fatal_error_info(error: NodeError) -> ErrorInfo {
+    ErrorInfo {
+        error,
+        recoverable: false,
+        retry_count: 0,
+        suggested_action: RecoveryAction::ImmediateFail,
+    }
+}
+
+// 模型ID即推理后端的模型路径，按全部层卸载到GPU加载
+fn full_offload() -> LoadParams {
+    LoadParams { n_gpu_layers: -1, ..LoadParams::default() }
+}
+
+/// 派发节点到阻塞线程池，delay用于重试退避
+fn spawn_node(
+    running: &mut NodeTasks,
+    node_id: String,
+    instance: Arc<dyn Node>,
+    inputs: HashMap<String, DataValue>,
+    mut streams: NodeStreams,
+    delay: Duration,
+    cancel: CancellationToken,
+) {
+    // 重试前的退避在异步侧等待，期间可被取消，不占用阻塞线程
+    running.spawn(async move {
+        if !delay.is_zero() {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancel.cancelled() => {}
+            }
+        }
+        if cancel.is_cancelled() {
+            return (node_id, Err(cancelled_error()));
+        }
+        let task = tokio::task::spawn_blocking(move || instance.execute_streaming(inputs, &mut streams, &cancel));
+        match task.await {
+            Ok(outputs) => (node_id, outputs),
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => (node_id, Err(cancelled_error())),
+        }
+    });
+}
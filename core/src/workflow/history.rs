@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::workflow::executor::WorkflowError;
+use crate::workflow::outputs::JsonOutputs;
+use crate::workflow::params::ParameterValues;
+use crate::workflow::serialization::WorkflowData;
+
+const HISTORY_FILE: &str = "runs.jsonl";
+
+/// 模型或LoRA文件的版本：按大小和修改时间判断文件是否被替换
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileVersion {
+    pub path: String,
+    /// 文件不存在（模型ID不是路径）时为None
+    pub size: Option<u64>,
+    /// 修改时间（Unix秒）
+    pub modified: Option<u64>,
+}
+
+impl FileVersion {
+    /// 相对路径基于当前工作目录解析，与模型加载一致
+    pub fn of(path: &str) -> Self {
+        let metadata = fs::metadata(path).ok();
+        Self {
+            path: path.to_string(),
+            size: metadata.as_ref().map(|m| m.len()),
+            modified: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+        }
+    }
+}
+
+/// 节点使用的模型及LoRA
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub node_id: String,
+    pub model: FileVersion,
+    pub lora: Option<FileVersion>,
+}
+
+/// 单个节点在一次运行中的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeRecord {
+    pub node_id: String,
+    pub node_type: String,
+    pub status: String,
+    pub duration_ms: Option<u64>,
+    pub error: Option<String>,
+    /// 无法表示为JSON的值（流）省略
+    pub inputs: BTreeMap<String, Value>,
+    pub outputs: BTreeMap<String, Value>,
+}
+
+/// 运行历史中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    /// 开始时间（Unix毫秒）
+    pub started_at: u64,
+    /// 代入参数前的工作流哈希，同一工作流不同参数的运行哈希相同
+    pub workflow_hash: String,
+    /// 解析后的参数值（含默认值）
+    pub parameters: ParameterValues,
+    /// "Completed" / "Error" / "Cancelled"
+    pub status: String,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+    pub nodes: Vec<NodeRecord>,
+    pub outputs: JsonOutputs,
+    pub models: Vec<ModelUsage>,
+    /// 重放产生的运行记录原运行ID
+    pub replay_of: Option<String>,
+    /// 实际运行的工作流（参数已代入），重放时使用
+    pub workflow: WorkflowData,
+}
+
+/// 运行列表中的摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub started_at: u64,
+    pub workflow_hash: String,
+    pub status: String,
+    pub duration_ms: u64,
+    pub replay_of: Option<String>,
+}
+
+impl From<&RunRecord> for RunSummary {
+    fn from(record: &RunRecord) -> Self {
+        Self {
+            run_id: record.run_id.clone(),
+            started_at: record.started_at,
+            workflow_hash: record.workflow_hash.clone(),
+            status: record.status.clone(),
+            duration_ms: record.duration_ms,
+            replay_of: record.replay_of.clone(),
+        }
+    }
+}
+
+/// 运行历史：`<dir>/runs.jsonl`，每次运行追加一行
+pub struct HistoryStore {
+    dir: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn append(&self, record: &RunRecord) -> Result<(), WorkflowError> {
+        let path = self.path();
+        let line = serde_json::to_string(record).map_err(|e| WorkflowError::History(e.to_string()))?;
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| WorkflowError::History(format!("写入{:?}失败: {}", path, e)))
+    }
+
+    /// 按时间顺序列出所有运行
+    pub fn list(&self) -> Result<Vec<RunSummary>, WorkflowError> {
+        Ok(self.records()?.iter().map(RunSummary::from).collect())
+    }
+
+    pub fn get(&self, run_id: &str) -> Result<RunRecord, WorkflowError> {
+        self.records()?
+            .into_iter()
+            .find(|record| record.run_id == run_id)
+            .ok_or_else(|| WorkflowError::History(format!("运行{}不存在", run_id)))
+    }
+
+    // 每行以换行结尾：没有换行的末行是写入中途崩溃留下的，忽略；其余无法解析的行返回错误
+    fn records(&self) -> Result<Vec<RunRecord>, WorkflowError> {
+        let path = self.path();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(WorkflowError::History(format!("读取{:?}失败: {}", path, e))),
+        };
+        let complete = match content.rfind('\n') {
+            Some(end) => &content[..end],
+            None => "",
+        };
+        complete.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line)
+                .map_err(|e| WorkflowError::History(format!("运行历史第{}行无法解析: {}", i + 1, e))))
+            .collect()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(HISTORY_FILE)
+    }
+}
+
+/// 两次运行中不同的值，缺失一侧为None
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    /// 输出为"节点ID.端口"，参数为参数名
+    pub key: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+/// 两次运行的差异
+#[derive(Debug, Clone, Serialize)]
+pub struct RunDiff {
+    pub left: String,
+    pub right: String,
+    /// 代入参数前的工作流不同
+    pub workflow_changed: bool,
+    pub parameters: Vec<ValueChange>,
+    /// 模型或LoRA文件不同的节点
+    pub models: Vec<String>,
+    pub outputs: Vec<ValueChange>,
+}
+
+impl RunDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.workflow_changed && self.parameters.is_empty() && self.models.is_empty() && self.outputs.is_empty()
+    }
+}
+
+/// 比较两次运行的工作流、参数、模型版本和输出
+pub fn diff_runs(left: &RunRecord, right: &RunRecord) -> RunDiff {
+    let flatten = |outputs: &JsonOutputs| -> BTreeMap<String, Value> {
+        outputs.iter()
+            .flat_map(|(node_id, ports)| ports.iter().map(move |(port, value)| (format!("{}.{}", node_id, port), value.clone())))
+            .collect()
+    };
+    let sorted = |parameters: &ParameterValues| -> BTreeMap<String, Value> {
+        parameters.iter().map(|(name, value)| (name.clone(), value.clone())).collect()
+    };
+
+    let models = |record: &RunRecord| -> BTreeMap<String, ModelUsage> {
+        record.models.iter().map(|usage| (usage.node_id.clone(), usage.clone())).collect()
+    };
+    let (left_models, right_models) = (models(left), models(right));
+    let model_nodes: BTreeSet<&String> = left_models.keys().chain(right_models.keys()).collect();
+
+    RunDiff {
+        left: left.run_id.clone(),
+        right: right.run_id.clone(),
+        workflow_changed: left.workflow_hash != right.workflow_hash,
+        parameters: changes(&sorted(&left.parameters), &sorted(&right.parameters)),
+        models: model_nodes.into_iter()
+            .filter(|node_id| left_models.get(*node_id) != right_models.get(*node_id))
+            .cloned()
+            .collect(),
+        outputs: changes(&flatten(&left.outputs), &flatten(&right.outputs)),
+    }
+}
+
+fn changes(left: &BTreeMap<String, Value>, right: &BTreeMap<String, Value>) -> Vec<ValueChange> {
+    let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+    keys.into_iter()
+        .filter(|key| left.get(*key) != right.get(*key))
+        .map(|key| ValueChange { key: key.clone(), left: left.get(key).cloned(), right: right.get(key).cloned() })
+        .collect()
+}
@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::DataValue;
+use crate::workflow::cache::{contains_stream, fnv1a64};
+use crate::workflow::executor::WorkflowError;
+use crate::workflow::serialization::WorkflowData;
+
+/// 单个节点在运行日志中的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// 节点状态（与NodeStateSnapshot::status一致）
+    pub status: String,
+    /// 节点输出；含流式数据的输出不记录，恢复时该节点重新执行
+    pub outputs: Option<HashMap<String, DataValue>>,
+}
+
+/// 运行日志：每个节点完成后落盘，进程崩溃后可据此恢复运行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunJournal {
+    pub run_id: String,
+    /// 调用方提交的工作流（代入参数、展开子图前）的内容哈希，恢复时与调用方当前的工作流比对
+    pub workflow_hash: String,
+    /// 实际运行的工作流（参数已代入），恢复时按它重新调度
+    pub workflow: WorkflowData,
+    pub nodes: BTreeMap<String, JournalEntry>,
+}
+
+impl RunJournal {
+    /// source为调用方提交的工作流，workflow为由它得到的实际运行的工作流
+    pub fn new(run_id: String, source: &WorkflowData, workflow: &WorkflowData) -> Self {
+        Self {
+            run_id,
+            workflow_hash: workflow_hash(source),
+            workflow: workflow.clone(),
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// 记录节点完成及其输出
+    pub fn record_completed(&mut self, node_id: &str, outputs: &HashMap<String, DataValue>) {
+        let outputs = if outputs.values().any(contains_stream) { None } else { Some(outputs.clone()) };
+        self.nodes.insert(node_id.to_string(), JournalEntry { status: "Completed".to_string(), outputs });
+    }
+
+    /// 记录节点完成但不保存输出（流式节点的块无法重放），恢复时该节点重新执行
+    pub fn record_rerun(&mut self, node_id: &str) {
+        self.nodes.insert(node_id.to_string(), JournalEntry { status: "Completed".to_string(), outputs: None });
+    }
+
+    /// 记录节点的最终状态（已完成节点的输出保留）
+    pub fn record_status(&mut self, node_id: &str, status: &str) {
+        if status == "Completed" && self.nodes.contains_key(node_id) {
+            return;
+        }
+        self.nodes.insert(node_id.to_string(), JournalEntry { status: status.to_string(), outputs: None });
+    }
+
+    /// 已完成且输出可恢复的节点
+    pub fn completed_outputs(&self, node_id: &str) -> Option<&HashMap<String, DataValue>> {
+        self.nodes.get(node_id)
+            .filter(|entry| entry.status == "Completed")
+            .and_then(|entry| entry.outputs.as_ref())
+    }
+
+    /// 要恢复的工作流是否与日志记录时提交的一致
+    pub fn matches_workflow(&self, workflow: &WorkflowData) -> bool {
+        workflow_hash(workflow) == self.workflow_hash
+    }
+}
+
+/// 运行日志存储：每次运行一个`<run_id>.json`文件
+pub struct JournalStore {
+    dir: PathBuf,
+}
+
+impl JournalStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// 先写临时文件再重命名，崩溃时不会留下半个日志
+    pub fn save(&self, journal: &RunJournal) -> Result<(), WorkflowError> {
+        let path = self.path(&journal.run_id);
+        let tmp = path.with_extension("json.tmp");
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| serde_json::to_string(journal).map_err(std::io::Error::from))
+            .and_then(|json| fs::write(&tmp, json))
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| WorkflowError::Journal(format!("写入{:?}失败: {}", path, e)))
+    }
+
+    pub fn load(&self, run_id: &str) -> Result<RunJournal, WorkflowError> {
+        let path = self.path(run_id);
+        let json = fs::read_to_string(&path)
+            .map_err(|e| WorkflowError::Journal(format!("读取{:?}失败: {}", path, e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| WorkflowError::Journal(format!("解析{:?}失败: {}", path, e)))
+    }
+
+    /// 列出所有已记录的运行ID
+    pub fn run_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = fs::read_dir(&self.dir)
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().to_str()?.strip_suffix(".json").map(str::to_string))
+                .collect())
+            .unwrap_or_default();
+        ids.sort();
+        ids
+    }
+
+    fn path(&self, run_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", run_id))
+    }
+}
+
+/// 工作流内容哈希
+pub fn workflow_hash(workflow: &WorkflowData) -> String {
+    let json = serde_json::to_string(workflow).unwrap_or_default();
+    format!("{:016x}", fnv1a64(json.as_bytes()))
+}
+
+/// 生成运行ID：时间戳加进程内计数，同一纳秒内的多次运行也不冲突
+pub fn new_run_id() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("run-{}-{}", nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::engine::{CancellationToken, NodeError};
+use crate::parameter::{Connection, DynamicPorts, Port, PortId};
+use crate::types::{DataType, DataValue};
+use crate::workflow::executor::WorkflowError;
+use crate::workflow::nodes::{Node, cancelled_error, ERR_EXECUTION_FAILED, ERR_MISSING_INPUT};
+use crate::workflow::serialization::{NodeData, WorkflowData};
+
+/// 循环节点类型
+pub const FOREACH_TYPE: &str = "foreach";
+pub const WHILE_TYPE: &str = "while";
+
+/// while循环默认的最大迭代次数
+pub const DEFAULT_MAX_ITERATIONS: usize = 100;
+
+/// 迭代进度回调：(当前迭代序号, 总次数)，总次数为None表示次数未知
+pub type IterationCallback = Arc<dyn Fn(usize, Option<usize>) + Send + Sync>;
+
+/// 循环种类
+///
+/// - ForEach：输入端口"list"，循环体从"item"/"index"取当前元素，结果连回"result"，输出"results"列表
+/// - While：输入端口"initial"，循环体从"value"/"index"取当前值，新值连回"next"，
+///   条件连回"continue"；输出最终"value"和迭代次数"iterations"
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopKind {
+    ForEach,
+    While { max_iterations: usize },
+}
+
+impl LoopKind {
+    /// 由节点类型和config解析循环种类，非循环节点返回None
+    pub fn from_node(node: &NodeData) -> Result<Option<Self>, WorkflowError> {
+        match node.type.as_str() {
+            FOREACH_TYPE => Ok(Some(LoopKind::ForEach)),
+            WHILE_TYPE => {
+                let max_iterations = node.config_value("max_iterations")
+                    .and_then(|v| v.as_u64())
+                    .map(|n| n as usize)
+                    .unwrap_or(DEFAULT_MAX_ITERATIONS);
+                if max_iterations == 0 {
+                    return Err(WorkflowError::InvalidConfig {
+                        node_id: node.id.clone(),
+                        reason: "max_iterations必须大于0".to_string(),
+                    });
+                }
+                Ok(Some(LoopKind::While { max_iterations }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 循环节点连向循环体的边未指定端口时使用的端口
+    pub fn default_body_input(&self) -> &'static str {
+        match self {
+            LoopKind::ForEach => "item",
+            LoopKind::While { .. } => "value",
+        }
+    }
+
+    /// 循环体连回循环节点的边未指定端口时使用的端口
+    pub fn default_body_output(&self) -> &'static str {
+        match self {
+            LoopKind::ForEach => "result",
+            LoopKind::While { .. } => "next",
+        }
+    }
+}
+
+/// 是否为循环节点类型
+pub fn is_loop_type(node_type: &str) -> bool {
+    node_type == FOREACH_TYPE || node_type == WHILE_TYPE
+}
+
+/// 作用域视图：parent_id为scope的节点，以及两端都在该作用域内的边
+///
+/// 循环节点与其子节点之间的边属于循环的端口连接，不出现在任何作用域视图中，
+/// 因此循环体连回循环节点不会被当作循环依赖。
+pub fn scope_view(workflow: &WorkflowData, scope: Option<&str>) -> WorkflowData {
+    let in_scope = |node_id: &str| workflow.get_node(node_id)
+        .map(|node| node.parent_id.as_deref() == scope)
+        .unwrap_or(false);
+
+    WorkflowData {
+        version: workflow.version.clone(),
+        nodes: workflow.nodes.iter()
+            .filter(|node| node.parent_id.as_deref() == scope)
+            .cloned()
+            .collect(),
+        edges: workflow.edges.iter()
+            .filter(|edge| in_scope(&edge.source) && in_scope(&edge.target))
+            .cloned()
+            .collect(),
+        parameters: workflow.parameters.clone(),
+    }
+}
+
+/// 所有作用域：顶层（None）和每个循环节点
+pub fn scopes(workflow: &WorkflowData) -> Vec<Option<&str>> {
+    let mut scopes = vec![None];
+    scopes.extend(workflow.nodes.iter()
+        .filter(|node| is_loop_type(&node.type))
+        .map(|node| Some(node.id.as_str())));
+    scopes
+}
+
+/// 检查边不跨越作用域：只允许同一作用域内的边，以及循环节点与其直接子节点之间的边
+pub fn check_scopes(workflow: &WorkflowData) -> Result<(), String> {
+    for node in &workflow.nodes {
+        if let Some(parent) = &node.parent_id {
+            match workflow.get_node(parent) {
+                Some(parent_node) if is_loop_type(&parent_node.type) => {}
+                Some(_) => return Err(format!("节点{}的父节点{}不是循环节点", node.id, parent)),
+                None => return Err(format!("节点{}的父节点{}不存在", node.id, parent)),
+            }
+        }
+    }
+
+    for edge in &workflow.edges {
+        let parent_of = |node_id: &str| workflow.get_node(node_id).map(|node| node.parent_id.as_deref());
+        let (source_scope, target_scope) = match (parent_of(&edge.source), parent_of(&edge.target)) {
+            (Some(source), Some(target)) => (source, target),
+            // 端点不存在由执行器报告NodeNotFound
+            _ => continue,
+        };
+        let loop_port_edge = target_scope == Some(edge.source.as_str()) || source_scope == Some(edge.target.as_str());
+        if source_scope != target_scope && !loop_port_edge {
+            return Err(format!("边{}跨越了循环体边界: {} -> {}", edge.id, edge.source, edge.target));
+        }
+    }
+    Ok(())
+}
+
+/// 循环体：按拓扑顺序排列的子节点及其端口连接（含与循环节点之间的连接）
+pub struct LoopBody {
+    order: Vec<String>,
+    instances: HashMap<String, Arc<dyn Node>>,
+    connections: Vec<Connection>,
+}
+
+impl LoopBody {
+    pub fn new(order: Vec<String>, instances: HashMap<String, Arc<dyn Node>>, connections: Vec<Connection>) -> Self {
+        Self { order, instances, connections }
+    }
+
+    /// 执行一次迭代：loop_outputs为循环节点提供给循环体的值，返回所有节点的输出
+    fn run(
+        &self,
+        loop_id: &str,
+        loop_outputs: HashMap<PortId, DataValue>,
+        cancel: &CancellationToken,
+    ) -> Result<HashMap<String, HashMap<PortId, DataValue>>, NodeError> {
+        let mut outputs: HashMap<String, HashMap<PortId, DataValue>> = HashMap::new();
+        outputs.insert(loop_id.to_string(), loop_outputs);
+
+        for node_id in &self.order {
+            if cancel.is_cancelled() {
+                return Err(cancelled_error());
+            }
+            let instance = match self.instances.get(node_id) {
+                Some(instance) => instance,
+                None => continue,
+            };
+
+            let inputs = self.connections.iter()
+                .filter(|c| &c.to_node == node_id)
+                .filter_map(|c| {
+                    let value = outputs.get(&c.from_node).and_then(|o| o.get(&c.from_port))?;
+                    Some((c.to_port.clone(), value.clone()))
+                })
+                .collect();
+            let result = instance.execute_with_cancel(inputs, cancel)
+                .map_err(|e| NodeError::new(format!("循环体节点{}执行失败: {}", node_id, e.message), e.code, e.details))?;
+            outputs.insert(node_id.clone(), result);
+        }
+        Ok(outputs)
+    }
+
+    /// 循环体连回循环节点指定端口的值
+    fn returned<'a>(&self, loop_id: &str, port: &str, outputs: &'a HashMap<String, HashMap<PortId, DataValue>>) -> Option<&'a DataValue> {
+        self.connections.iter()
+            .find(|c| c.to_node == loop_id && c.to_port == port)
+            .and_then(|c| outputs.get(&c.from_node)?.get(&c.from_port))
+    }
+
+    fn returns(&self, loop_id: &str, port: &str) -> bool {
+        self.connections.iter().any(|c| c.to_node == loop_id && c.to_port == port)
+    }
+}
+
+/// 循环节点：每次迭代在阻塞线程中按顺序执行循环体
+pub struct LoopNode {
+    pub id: String,
+    pub kind: LoopKind,
+    pub ports: DynamicPorts,
+    body: LoopBody,
+    on_iteration: Option<IterationCallback>,
+}
+
+impl LoopNode {
+    pub fn new(id: &str, kind: LoopKind, body: LoopBody) -> Self {
+        let mut ports = DynamicPorts::new();
+        let port = |id: &str, data_type: DataType| Port { id: id.to_string(), data_type, multiple: false };
+        match kind {
+            LoopKind::ForEach => {
+                ports.add_input(port("list", DataType::List(Box::new(DataType::Text))));
+                ports.add_output(port("results", DataType::List(Box::new(DataType::Text))));
+            }
+            LoopKind::While { .. } => {
+                ports.add_input(port("initial", DataType::Text));
+                ports.add_output(port("value", DataType::Text));
+                ports.add_output(port("iterations", DataType::Number));
+            }
+        }
+        Self { id: id.to_string(), kind, ports, body, on_iteration: None }
+    }
+
+    /// 每次迭代开始时调用回调（用于更新Iterating子状态）
+    pub fn with_iteration_callback(mut self, callback: IterationCallback) -> Self {
+        self.on_iteration = Some(callback);
+        self
+    }
+
+    fn report(&self, current: usize, total: Option<usize>) {
+        if let Some(callback) = &self.on_iteration {
+            callback(current, total);
+        }
+    }
+
+    fn run_foreach(&self, inputs: HashMap<PortId, DataValue>, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let items = match inputs.get("list") {
+            Some(DataValue::List(items)) => items.clone(),
+            Some(other) => return Err(NodeError::new(format!("list需要列表，实际为{}", other.data_type()), ERR_EXECUTION_FAILED, None)),
+            None => return Err(NodeError::new("缺少列表输入: list".to_string(), ERR_MISSING_INPUT, None)),
+        };
+
+        let total = Some(items.len());
+        let mut results = Vec::with_capacity(items.len());
+        for (index, item) in items.into_iter().enumerate() {
+            self.report(index, total);
+            let mut loop_outputs = HashMap::new();
+            loop_outputs.insert("item".to_string(), item);
+            loop_outputs.insert("index".to_string(), DataValue::Number(index as f64));
+
+            let outputs = self.body.run(&self.id, loop_outputs, cancel)?;
+            let result = self.body.returned(&self.id, "result", &outputs)
+                .ok_or_else(|| NodeError::new(format!("第{}次迭代没有产出result", index), ERR_EXECUTION_FAILED, None))?;
+            results.push(result.clone());
+        }
+
+        let mut outputs = HashMap::new();
+        outputs.insert("results".to_string(), DataValue::List(results));
+        Ok(outputs)
+    }
+
+    fn run_while(&self, max_iterations: usize, inputs: HashMap<PortId, DataValue>, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let mut value = inputs.get("initial").cloned()
+            .ok_or_else(|| NodeError::new("缺少初始值输入: initial".to_string(), ERR_MISSING_INPUT, None))?;
+        // 未连接continue时固定执行max_iterations次
+        let has_condition = self.body.returns(&self.id, "continue");
+
+        let mut iterations = 0;
+        loop {
+            if iterations == max_iterations {
+                if has_condition {
+                    return Err(NodeError::new(format!("超过最大迭代次数{}", max_iterations), ERR_EXECUTION_FAILED, None));
+                }
+                break;
+            }
+
+            self.report(iterations, None);
+            let mut loop_outputs = HashMap::new();
+            loop_outputs.insert("value".to_string(), value.clone());
+            loop_outputs.insert("index".to_string(), DataValue::Number(iterations as f64));
+
+            let outputs = self.body.run(&self.id, loop_outputs, cancel)?;
+            iterations += 1;
+            if let Some(next) = self.body.returned(&self.id, "next", &outputs) {
+                value = next.clone();
+            }
+            let proceed = match self.body.returned(&self.id, "continue", &outputs) {
+                Some(DataValue::Boolean(b)) => *b,
+                Some(other) => matches!(other.convert_to(DataType::Boolean), Ok(DataValue::Boolean(true))),
+                None => !has_condition,
+            };
+            if !proceed {
+                break;
+            }
+        }
+
+        let mut outputs = HashMap::new();
+        outputs.insert("value".to_string(), value);
+        outputs.insert("iterations".to_string(), DataValue::Number(iterations as f64));
+        Ok(outputs)
+    }
+}
+
+impl Node for LoopNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        self.execute_with_cancel(inputs, &CancellationToken::new())
+    }
+
+    fn execute_with_cancel(&self, inputs: HashMap<PortId, DataValue>, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        match self.kind {
+            LoopKind::ForEach => self.run_foreach(inputs, cancel),
+            LoopKind::While { max_iterations } => self.run_while(max_iterations, inputs, cancel),
+        }
+    }
+
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+
+    fn required_model(&self) -> Option<String> {
+        // 循环体内第一个需要模型的节点（用于调度时的VRAM槽位规划）
+        self.body.order.iter()
+            .filter_map(|id| self.body.instances.get(id))
+            .find_map(|node| node.required_model())
+    }
+}
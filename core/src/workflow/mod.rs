@@ -3,7 +3,54 @@ pub mod executor;
 pub mod nodes;
 pub mod validator;
 pub mod serialization;
+pub mod registry;
+pub mod scheduler;
+pub mod tracker;
+pub mod recovery;
+pub mod cache;
+pub mod journal;
+pub mod loops;
+pub mod subgraph;
+pub mod stream;
+pub mod schema;
+pub mod comfyui;
+pub mod params;
+pub mod batch;
+pub mod outputs;
+pub mod events;
+pub mod history;
+#[cfg(test)]
+mod test_executor;
+#[cfg(test)]
+mod test_scheduler;
+#[cfg(test)]
+mod test_schema;
+#[cfg(test)]
+mod test_comfyui;
+#[cfg(test)]
+mod test_params;
+#[cfg(test)]
+mod test_history;
+#[cfg(test)]
+mod test_support;
 pub use context::ExecutionContext;
 pub use executor::{WorkflowExecutor, ExecutionResult, WorkflowError};
-pub use validator::{detect_cycles, validate_type_match};
-pub use serialization::{WorkflowData, NodeData, EdgeData, Position};
\ No newline at end of file
+pub use nodes::Node;
+pub use registry::{NodeRegistry, NodeFactory};
+pub use scheduler::SchedulerConfig;
+pub use tracker::{NodeStateTracker, NodeStateSnapshot};
+pub use recovery::error_policy;
+pub use cache::OutputCache;
+pub use journal::{RunJournal, JournalEntry, JournalStore};
+pub use loops::{LoopKind, LoopNode};
+pub use stream::NodeStreams;
+pub use comfyui::{import_comfyui, ComfyFormat, ComfyImport, ImportError, ImportReport};
+pub use events::{EventBus, ExecutionEvent};
+pub use outputs::{NodeRunInfo, WorkflowOutputs};
+pub use params::{apply_parameters, ParameterValues};
+pub use batch::{BatchRecord, BatchSummary, read_parameter_sets};
+pub use history::{diff_runs, FileVersion, HistoryStore, ModelUsage, NodeRecord, RunDiff, RunRecord, RunSummary, ValueChange};
+pub use schema::{load_workflow, LoadOptions, LoadedWorkflow, SchemaError, CURRENT_VERSION};
+pub use validator::{detect_cycles, detect_workflow_cycles, find_cycle, validate_edge_types, validate_type_match, EdgeError, EdgeErrorKind, EdgeTypeReport, ImplicitConversion};
+pub use validator::{IssueCode, Severity, ValidationIssue, ValidationReport};
+pub use serialization::{WorkflowData, WorkflowParameter, NodeData, EdgeData, Position};
\ No newline at end of file
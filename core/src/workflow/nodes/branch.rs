@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use crate::engine::NodeError;
+use crate::parameter::{DynamicPorts, Port, PortId};
+use crate::types::{DataType, DataValue};
+use crate::workflow::nodes::{Node, ERR_EXECUTION_FAILED, ERR_MISSING_INPUT};
+
+fn text_port(id: &str) -> Port {
+    Port { id: id.to_string(), data_type: DataType::Text, multiple: false }
+}
+
+/// 条件分支：condition为真时从"true"端口输出value，否则从"false"端口输出
+///
+/// 配置了equals时，condition按文本与其比较；未连接value时输出condition本身。
+pub struct IfNode {
+    pub equals: Option<String>,
+    pub ports: DynamicPorts,
+}
+
+impl IfNode {
+    pub fn new(equals: Option<&str>) -> Self {
+        let mut ports = DynamicPorts::new();
+        ports.add_input(Port { id: "condition".to_string(), data_type: DataType::Boolean, multiple: false });
+        ports.add_input(text_port("value"));
+        ports.add_output(text_port("true"));
+        ports.add_output(text_port("false"));
+        Self { equals: equals.map(str::to_string), ports }
+    }
+
+    fn evaluate(&self, condition: &DataValue) -> Result<bool, NodeError> {
+        if let Some(expected) = &self.equals {
+            return Ok(condition.as_text().map(str::trim) == Some(expected.as_str()));
+        }
+        match condition {
+            DataValue::Boolean(b) => Ok(*b),
+            DataValue::Number(n) => Ok(*n != 0.0),
+            other => match other.convert_to(DataType::Boolean) {
+                Ok(DataValue::Boolean(b)) => Ok(b),
+                _ => Err(NodeError::new(format!("无法将条件解释为布尔值: {:?}", other), ERR_EXECUTION_FAILED, None)),
+            },
+        }
+    }
+}
+
+impl Node for IfNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let condition = inputs.get("condition")
+            .ok_or_else(|| NodeError::new("缺少条件输入: condition".to_string(), ERR_MISSING_INPUT, None))?;
+        let branch = if self.evaluate(condition)? { "true" } else { "false" };
+        let value = inputs.get("value").unwrap_or(condition).clone();
+
+        let mut outputs = HashMap::new();
+        outputs.insert(branch.to_string(), value);
+        Ok(outputs)
+    }
+
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+
+    fn branch_condition(&self, outputs: &HashMap<PortId, DataValue>) -> Option<bool> {
+        Some(outputs.contains_key("true"))
+    }
+
+    fn required_inputs(&self) -> Vec<PortId> { vec!["condition".to_string()] }
+}
+
+/// 多路分支：key与某个case相同时从该case同名端口输出value，都不匹配时从"default"端口输出
+pub struct SwitchNode {
+    pub cases: Vec<String>,
+    pub ports: DynamicPorts,
+}
+
+impl SwitchNode {
+    pub fn new(cases: Vec<String>) -> Self {
+        let mut ports = DynamicPorts::new();
+        ports.add_input(text_port("key"));
+        ports.add_input(text_port("value"));
+        for case in &cases {
+            ports.add_output(text_port(case));
+        }
+        ports.add_output(text_port("default"));
+        Self { cases, ports }
+    }
+}
+
+impl Node for SwitchNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let key = inputs.get("key")
+            .ok_or_else(|| NodeError::new("缺少分支输入: key".to_string(), ERR_MISSING_INPUT, None))?;
+        let key_text = match key {
+            DataValue::Text(s) => s.trim().to_string(),
+            other => other.convert_to(DataType::Text).ok()
+                .and_then(|v| v.as_text().map(str::to_string))
+                .unwrap_or_default(),
+        };
+        let branch = self.cases.iter()
+            .find(|case| **case == key_text)
+            .map(String::as_str)
+            .unwrap_or("default");
+        let value = inputs.get("value").unwrap_or(key).clone();
+
+        let mut outputs = HashMap::new();
+        outputs.insert(branch.to_string(), value);
+        Ok(outputs)
+    }
+
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+
+    fn branch_condition(&self, outputs: &HashMap<PortId, DataValue>) -> Option<bool> {
+        Some(!outputs.contains_key("default"))
+    }
+
+    fn required_inputs(&self) -> Vec<PortId> { vec!["key".to_string()] }
+}
+
+/// 汇合节点：输出第一个实际执行的分支的值（按in1, in2...顺序）
+pub struct MergeNode {
+    pub input_count: usize,
+    pub ports: DynamicPorts,
+}
+
+impl MergeNode {
+    pub fn new(input_count: usize) -> Self {
+        let input_count = input_count.max(1);
+        let mut ports = DynamicPorts::new();
+        for i in 1..=input_count {
+            ports.add_input(text_port(&format!("in{}", i)));
+        }
+        ports.add_output(text_port("out"));
+        Self { input_count, ports }
+    }
+}
+
+impl Node for MergeNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let value = (1..=self.input_count)
+            .find_map(|i| inputs.get(&format!("in{}", i)))
+            .cloned()
+            .ok_or_else(|| NodeError::new("汇合节点没有任何输入".to_string(), ERR_MISSING_INPUT, None))?;
+
+        let mut outputs = HashMap::new();
+        outputs.insert("out".to_string(), value);
+        Ok(outputs)
+    }
+
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+
+    fn accepts_partial_inputs(&self) -> bool { true }
+}
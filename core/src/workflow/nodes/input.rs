@@ -1,5 +1,8 @@
-use crate::parameter::{DynamicPorts, Port};
-use crate::types::DataType;
+use std::collections::HashMap;
+use crate::engine::NodeError;
+use crate::parameter::{DynamicPorts, Port, PortId};
+use crate::types::{DataType, DataValue};
+use crate::workflow::nodes::Node;
 
 pub struct TextInputNode {
     pub text: String,
@@ -16,5 +19,14 @@ impl TextInputNode {
         });
         Self { text: text.to_string(), ports }
     }
-    pub fn execute(&self) -> String { self.text.clone() }
-}
\ No newline at end of file
+}
+
+impl Node for TextInputNode {
+    fn execute(&self, _inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let mut outputs = HashMap::new();
+        outputs.insert("text".to_string(), DataValue::Text(self.text.clone()));
+        Ok(outputs)
+    }
+    
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+}
@@ -1,14 +1,22 @@
-use crate::parameter::{DynamicPorts, Port};
-use crate::types::DataType;
-use crate::workflow::context::ExecutionContext;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use crate::engine::{CancellationToken, NodeError};
+use crate::ffi::{FfiError, GenerateParams, LoadParams};
+use crate::parameter::{DynamicPorts, Port, PortId};
+use crate::types::{DataType, DataValue};
+use crate::vram::VramPool;
+use crate::workflow::nodes::{Node, cancelled_error, text_input, ERR_EXECUTION_FAILED};
+use crate::workflow::stream::NodeStreams;
 
 pub struct LLMNode {
     pub model_id: String,
     pub ports: DynamicPorts,
+    vram_pool: Arc<Mutex<VramPool>>,
 }
 
 impl LLMNode {
-    pub fn new(model_id: &str) -> Self {
+    pub fn new(model_id: &str, vram_pool: Arc<Mutex<VramPool>>) -> Self {
         let mut ports = DynamicPorts::new();
         ports.add_input(Port {
             id: "prompt".to_string(),
@@ -20,9 +28,48 @@ impl LLMNode {
             data_type: DataType::Text,
             multiple: false,
         });
-        Self { model_id: model_id.to_string(), ports }
+        // 逐token输出，"response"为聚合后的完整文本
+        ports.add_output(Port {
+            id: "stream".to_string(),
+            data_type: DataType::Stream(Box::new(DataType::Text)),
+            multiple: true,
+        });
+        Self { model_id: model_id.to_string(), ports, vram_pool }
+    }
+}
+
+impl Node for LLMNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        self.execute_streaming(inputs, &mut NodeStreams::new(), &CancellationToken::new())
     }
-    pub fn execute(&self, prompt: &str, _ctx: &ExecutionContext) -> String {
-        format!("[模型{}响应] 你输入了: {}", self.model_id, prompt)
+    
+    // 从VRAM池取得模型（未驻留时加载，全部层卸载到GPU，同执行器规划模型时的估算），加载和生成都响应取消
+    fn execute_streaming(&self, inputs: HashMap<PortId, DataValue>, streams: &mut NodeStreams, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        let prompt = text_input(&inputs, "prompt")?;
+        let params = LoadParams { n_gpu_layers: -1, ..LoadParams::default() };
+        let model = VramPool::load_shared(&self.vram_pool, &self.model_id, Path::new(&self.model_id), params, cancel.as_flag())
+            .map_err(model_error)?;
+        let response = model
+            .generate(&prompt, GenerateParams::default(), cancel.as_flag(), &mut |token| {
+                streams.send("stream", DataValue::Text(token.to_string()));
+            })
+            .map_err(model_error)?;
+        
+        let mut outputs = HashMap::new();
+        outputs.insert("response".to_string(), DataValue::Text(response));
+        Ok(outputs)
     }
-}
\ No newline at end of file
+    
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+    
+    fn required_model(&self) -> Option<String> {
+        if self.model_id.is_empty() { None } else { Some(self.model_id.clone()) }
+    }
+}
+
+fn model_error(error: FfiError) -> NodeError {
+    match error {
+        FfiError::Cancelled => cancelled_error(),
+        other => NodeError::new(other.to_string(), ERR_EXECUTION_FAILED, None),
+    }
+}
@@ -1,17 +1,22 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
-use crate::parameter::{DynamicPorts, Port};
-use crate::types::DataType;
-use crate::workflow::context::ExecutionContext;
+use std::sync::{Arc, Mutex};
+use crate::engine::NodeError;
+use crate::parameter::{DynamicPorts, Port, PortId};
+use crate::types::{DataType, DataValue};
+use crate::vram::VramPool;
+use crate::workflow::nodes::{Node, ERR_EXECUTION_FAILED};
 use crate::ffi::FfiError;
 
 pub struct LoRASwitchNode {
     pub model_id: String,
     pub lora_path: PathBuf,
     pub ports: DynamicPorts,
+    vram_pool: Arc<Mutex<VramPool>>,
 }
 
 impl LoRASwitchNode {
-    pub fn new(model_id: &str, lora_path: &str) -> Self {
+    pub fn new(model_id: &str, lora_path: &str, vram_pool: Arc<Mutex<VramPool>>) -> Self {
         let mut ports = DynamicPorts::new();
         
         // 输入端口
@@ -27,15 +32,9 @@ impl LoRASwitchNode {
             multiple: false,
         });
         
-        // 输出端口
+        // 输出端口：切换后的模型ID，供下游继续引用同一模型；切换失败时节点报错
         ports.add_output(Port {
-            id: "success".to_string(),
-            data_type: DataType::Boolean,
-            multiple: false,
-        });
-        
-        ports.add_output(Port {
-            id: "error".to_string(),
+            id: "model_id".to_string(),
             data_type: DataType::Text,
             multiple: false,
         });
@@ -44,15 +43,46 @@ impl LoRASwitchNode {
             model_id: model_id.to_string(),
             lora_path: PathBuf::from(lora_path),
             ports,
+            vram_pool,
         }
     }
     
-    pub fn execute(&self, ctx: &ExecutionContext) -> Result<bool, FfiError> {
-        let mut vram_pool = ctx.vram_pool.lock()
+    fn switch(&self, model_id: &str, lora_path: PathBuf) -> Result<(), FfiError> {
+        let mut vram_pool = self.vram_pool.lock()
             .map_err(|_| FfiError::Internal("锁中毒".into()))?;
         
-        vram_pool.switch_lora(&self.model_id, self.lora_path.clone())?;
+        vram_pool.switch_lora(model_id, lora_path)
+    }
+}
+
+impl Node for LoRASwitchNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        // 已连接的输入端口优先于节点配置
+        let model_id = inputs.get("model_id")
+            .and_then(|v| v.as_text())
+            .unwrap_or(&self.model_id);
+        let lora_path = inputs.get("lora_path")
+            .and_then(|v| v.as_text())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.lora_path.clone());
         
-        Ok(true)
+        self.switch(model_id, lora_path)
+            .map_err(|e| NodeError::new(e.to_string(), ERR_EXECUTION_FAILED, None))?;
+        
+        let mut outputs = HashMap::new();
+        outputs.insert("model_id".to_string(), DataValue::Text(model_id.to_string()));
+        Ok(outputs)
     }
-}
\ No newline at end of file
+    
+    // 切换LoRA会改变模型状态，不能用缓存跳过
+    fn cacheable(&self) -> bool { false }
+    
+    // 未连接时使用节点配置
+    fn required_inputs(&self) -> Vec<PortId> { Vec::new() }
+    
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+    
+    fn required_model(&self) -> Option<String> {
+        if self.model_id.is_empty() { None } else { Some(self.model_id.clone()) }
+    }
+}
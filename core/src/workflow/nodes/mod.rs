@@ -2,7 +2,79 @@ pub mod input;
 pub mod llm;
 pub mod output;
 pub mod lora_switch;
+pub mod branch;
 pub use input::TextInputNode;
 pub use llm::LLMNode;
 pub use output::TextOutputNode;
-pub use lora_switch::LoRASwitchNode;
\ No newline at end of file
+pub use lora_switch::LoRASwitchNode;
+pub use branch::{IfNode, SwitchNode, MergeNode};
+
+use std::collections::HashMap;
+use crate::engine::{CancellationToken, NodeError};
+use crate::parameter::{DynamicPorts, PortId};
+use crate::types::DataValue;
+use crate::workflow::stream::{NodeStreams, is_stream_type};
+
+/// 节点错误码：缺少必需输入
+pub const ERR_MISSING_INPUT: u32 = 1001;
+/// 节点错误码：配置无效
+pub const ERR_INVALID_CONFIG: u32 = 1002;
+/// 节点错误码：执行失败
+pub const ERR_EXECUTION_FAILED: u32 = 1003;
+/// 节点错误码：执行被取消
+pub const ERR_CANCELLED: u32 = 1004;
+
+/// 节点统一接口：按端口接收输入，按端口产出输出
+pub trait Node: Send + Sync {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError>;
+    fn ports(&self) -> DynamicPorts;
+    
+    /// 可取消的执行：长时间运行的节点（LLM生成、Python调用）应覆盖此方法并轮询cancel，
+    /// 取消时返回错误码ERR_CANCELLED
+    fn execute_with_cancel(&self, inputs: HashMap<PortId, DataValue>, _cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        self.execute(inputs)
+    }
+    
+    /// 带流式端口的执行：生产者通过streams.send逐块输出，消费者通过streams.recv在块到达时处理。
+    /// 流式端口不出现在inputs/outputs中；不涉及流的节点无需覆盖
+    fn execute_streaming(&self, inputs: HashMap<PortId, DataValue>, _streams: &mut NodeStreams, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        self.execute_with_cancel(inputs, cancel)
+    }
+    
+    /// 节点执行所需的模型ID（用于调度时的VRAM槽位规划），不使用模型的节点返回None
+    fn required_model(&self) -> Option<String> { None }
+    
+    /// 分支节点根据输出返回条件结果；分支节点未产出的输出端口上的边为死边，
+    /// 只经死边可达的下游节点被跳过。普通节点返回None
+    fn branch_condition(&self, _outputs: &HashMap<PortId, DataValue>) -> Option<bool> { None }
+    
+    /// 输出能否缓存复用：执行有副作用的节点（如切换LoRA）返回false，每次都重新执行
+    fn cacheable(&self) -> bool { true }
+    
+    /// 汇合节点：只要有一条输入边存活即可执行，来自死边的输入端口不传入
+    fn accepts_partial_inputs(&self) -> bool { false }
+    
+    /// 必须连接（或在default_inputs中给出）的输入端口，用于验证；默认为全部非流式输入，汇合节点没有必需输入
+    fn required_inputs(&self) -> Vec<PortId> {
+        if self.accepts_partial_inputs() {
+            return Vec::new();
+        }
+        self.ports().inputs()
+            .filter(|port| !is_stream_type(&port.data_type))
+            .map(|port| port.id.clone())
+            .collect()
+    }
+}
+
+/// 取消错误
+pub(crate) fn cancelled_error() -> NodeError {
+    NodeError::new("执行已取消".to_string(), ERR_CANCELLED, None)
+}
+
+/// 读取文本输入端口
+pub(crate) fn text_input(inputs: &HashMap<PortId, DataValue>, port: &str) -> Result<String, NodeError> {
+    inputs.get(port)
+        .and_then(|v| v.as_text())
+        .map(str::to_string)
+        .ok_or_else(|| NodeError::new(format!("缺少文本输入: {}", port), ERR_MISSING_INPUT, None))
+}
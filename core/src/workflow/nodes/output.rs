@@ -1,9 +1,12 @@
-use crate::parameter::{DynamicPorts, Port};
-use crate::types::DataType;
+use std::collections::HashMap;
+use crate::engine::{CancellationToken, NodeError};
+use crate::parameter::{DynamicPorts, Port, PortId};
+use crate::types::{DataType, DataValue};
+use crate::workflow::nodes::{Node, cancelled_error, text_input};
+use crate::workflow::stream::NodeStreams;
 
 pub struct TextOutputNode {
     pub ports: DynamicPorts,
-    pub result: Option<String>,
 }
 
 impl TextOutputNode {
@@ -14,10 +17,59 @@ impl TextOutputNode {
             data_type: DataType::Text,
             multiple: false,
         });
-        Self { ports, result: None }
+        // 连接流时边读边发布TokenGenerated事件，输出为拼接后的文本
+        ports.add_input(Port {
+            id: "stream".to_string(),
+            data_type: DataType::Stream(Box::new(DataType::Text)),
+            multiple: false,
+        });
+        ports.add_output(Port {
+            id: "output".to_string(),
+            data_type: DataType::Text,
+            multiple: false,
+        });
+        Self { ports }
     }
-    pub fn execute(&mut self, input: &str) {
-        self.result = Some(input.to_string());
-        println!("输出结果: {}", input);
+}
+
+impl Default for TextOutputNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Node for TextOutputNode {
+    fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        // 输出值由执行器作为OutputProduced事件发布
+        let text = text_input(&inputs, "text")?;
+        
+        let mut outputs = HashMap::new();
+        outputs.insert("output".to_string(), DataValue::Text(text));
+        Ok(outputs)
+    }
+    
+    fn execute_streaming(&self, inputs: HashMap<PortId, DataValue>, streams: &mut NodeStreams, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+        if !streams.has_input("stream") {
+            return self.execute_with_cancel(inputs, cancel);
+        }
+        
+        let mut text = String::new();
+        while let Some(chunk) = streams.recv("stream") {
+            if cancel.is_cancelled() {
+                return Err(cancelled_error());
+            }
+            if let Some(chunk) = chunk.as_text() {
+                text.push_str(chunk);
+            }
+        }
+        
+        let mut outputs = HashMap::new();
+        outputs.insert("output".to_string(), DataValue::Text(text));
+        Ok(outputs)
     }
-}
\ No newline at end of file
+    
+    fn ports(&self) -> DynamicPorts { self.ports.clone() }
+    
+    // text和stream连接其一即可
+    fn required_inputs(&self) -> Vec<PortId> { Vec::new() }
+}
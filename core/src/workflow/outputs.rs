@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::engine::MainState;
+use crate::types::DataValue;
+use crate::workflow::context::ExecutionContext;
+use crate::workflow::params::value_to_json;
+use crate::workflow::serialization::{NodeData, WorkflowData};
+use crate::workflow::tracker::NodeStateSnapshot;
+
+/// 工作流输出：节点ID -> 端口 -> 值。按ID排序，同名端口不会互相覆盖
+pub type WorkflowOutputs = BTreeMap<String, BTreeMap<String, DataValue>>;
+
+/// 可序列化的端口值：节点ID -> 端口 -> JSON值，无法表示为JSON的值（流）省略
+pub type JsonOutputs = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// 标记输出端口的配置键：true表示节点的全部输出，或端口名数组
+pub const EXPOSE_KEY: &str = "expose_outputs";
+
+/// 输出节点的类型，其全部输出都是工作流输出
+const OUTPUT_NODE_TYPE: &str = "output";
+
+/// 节点在一次运行中的最终状态与耗时
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeRunInfo {
+    pub node_id: String,
+    /// 与NodeStateSnapshot::status一致
+    pub status: String,
+    /// 未执行的节点为None
+    pub duration_ms: Option<u64>,
+    /// 失败节点的错误信息
+    pub error: Option<String>,
+}
+
+impl From<&NodeStateSnapshot> for NodeRunInfo {
+    fn from(snapshot: &NodeStateSnapshot) -> Self {
+        Self {
+            node_id: snapshot.node_id.clone(),
+            status: snapshot.status().to_string(),
+            duration_ms: snapshot.duration.map(|duration| duration.as_millis() as u64),
+            error: match &snapshot.state {
+                MainState::Error(info) => Some(info.error.message.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// 指定为工作流输出的(节点ID, 端口)，端口为None表示节点的全部输出
+///
+/// 输出节点和配置了expose_outputs的端口是工作流输出；两者都没有时退回到没有下游的节点
+pub fn designated_outputs(workflow: &WorkflowData) -> Vec<(String, Option<Vec<String>>)> {
+    let designated: Vec<_> = workflow.nodes.iter()
+        .filter_map(|node| exposed_ports(node).map(|ports| (node.id.clone(), ports)))
+        .collect();
+    if !designated.is_empty() {
+        return designated;
+    }
+
+    workflow.nodes.iter()
+        .filter(|node| !workflow.edges.iter().any(|edge| edge.source == node.id))
+        .map(|node| (node.id.clone(), None))
+        .collect()
+}
+
+// Some(None)为全部端口，None为不是输出
+fn exposed_ports(node: &NodeData) -> Option<Option<Vec<String>>> {
+    if node.type == OUTPUT_NODE_TYPE {
+        return Some(None);
+    }
+    match node.config.as_ref()?.get(EXPOSE_KEY)? {
+        Value::Bool(true) => Some(None),
+        Value::Array(ports) => Some(Some(ports.iter().filter_map(Value::as_str).map(str::to_string).collect())),
+        _ => None,
+    }
+}
+
+/// 从运行上下文中取出指定的输出；未执行的节点（被跳过的分支）不出现在结果中
+pub fn collect_outputs(workflow: &WorkflowData, context: &ExecutionContext) -> WorkflowOutputs {
+    let mut collected = WorkflowOutputs::new();
+    for (node_id, ports) in designated_outputs(workflow) {
+        let Some(outputs) = context.get_outputs(&node_id) else { continue };
+        let selected: BTreeMap<String, DataValue> = outputs.iter()
+            .filter(|(port, _)| ports.as_ref().map_or(true, |ports| ports.contains(port)))
+            .map(|(port, value)| (port.clone(), value.clone()))
+            .collect();
+        if !selected.is_empty() {
+            collected.insert(node_id, selected);
+        }
+    }
+    collected
+}
+
+/// 端口值转为JSON，用于批量结果和运行历史
+pub fn outputs_to_json<'a, I, P>(outputs: I) -> JsonOutputs
+where
+    I: IntoIterator<Item = (&'a String, P)>,
+    P: IntoIterator<Item = (&'a String, &'a DataValue)>,
+{
+    outputs.into_iter()
+        .map(|(node_id, ports)| {
+            let ports = ports.into_iter()
+                .filter_map(|(port, value)| Some((port.clone(), value_to_json(value)?)))
+                .collect();
+            (node_id.clone(), ports)
+        })
+        .collect()
+}
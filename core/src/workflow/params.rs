@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::types::{DataType, DataValue};
+use crate::workflow::executor::WorkflowError;
+use crate::workflow::serialization::{WorkflowData, WorkflowParameter};
+
+/// 一次运行的参数值：参数名 -> JSON值
+pub type ParameterValues = HashMap<String, Value>;
+
+const TEMPLATE_OPEN: &str = "{{";
+const TEMPLATE_CLOSE: &str = "}}";
+
+/// 合并默认值与覆盖值并按声明的类型转换；覆盖未声明的参数或缺少必需参数时报错
+pub fn resolve_values(parameters: &[WorkflowParameter], overrides: &ParameterValues) -> Result<ParameterValues, WorkflowError> {
+    if let Some(name) = overrides.keys().find(|name| !parameters.iter().any(|p| &p.name == *name)) {
+        return Err(WorkflowError::InvalidParameter(format!("未声明的参数: {}", name)));
+    }
+
+    let mut values = ParameterValues::new();
+    for parameter in parameters {
+        let raw = overrides.get(&parameter.name)
+            .or(parameter.default.as_ref())
+            .ok_or_else(|| WorkflowError::InvalidParameter(format!("参数{}没有默认值，需要在运行时给出", parameter.name)))?;
+        let value = json_to_value(raw)
+            .and_then(|value| coerce(value, &parameter.data_type))
+            .and_then(|value| value_to_json(&value))
+            .ok_or_else(|| WorkflowError::InvalidParameter(format!("参数{}的值{}不能转换为{}", parameter.name, raw, parameter.data_type)))?;
+        values.insert(parameter.name.clone(), value);
+    }
+    Ok(values)
+}
+
+/// 代入参数：节点config/data/default_inputs中的"{{名称}}"替换为参数值
+///
+/// 字符串恰好是一个引用时替换为参数的JSON值（保留数值、布尔等类型），否则按文本拼接。
+/// 含子图的工作流应先展开（子图声明的参数随之并入），否则子图文件中的引用不会被替换。
+pub fn apply_parameters(workflow: &WorkflowData, overrides: &ParameterValues) -> Result<WorkflowData, WorkflowError> {
+    let values = resolve_values(&workflow.parameters, overrides)?;
+    let mut resolved = workflow.clone();
+    for node in &mut resolved.nodes {
+        let fields = [Some(&mut node.data), node.config.as_mut(), node.default_inputs.as_mut()];
+        for field in fields.into_iter().flatten() {
+            substitute(field, &values)
+                .map_err(|reason| WorkflowError::InvalidConfig { node_id: node.id.clone(), reason })?;
+        }
+    }
+    // 参数已代入，解析后的工作流（运行日志、运行历史）无需再提供参数值
+    resolved.parameters = Vec::new();
+    Ok(resolved)
+}
+
+// 列表/字典只检查形状，路径接受文本，其余按DataValue::convert_to转换（CSV中的值都是文本）
+fn coerce(value: DataValue, data_type: &DataType) -> Option<DataValue> {
+    match (value, data_type) {
+        (value @ DataValue::List(_), DataType::List(_)) | (value @ DataValue::Dict(_), DataType::Dict(..)) => Some(value),
+        (DataValue::Text(s), DataType::Path) => Some(DataValue::Path(s.into())),
+        (value, data_type) => value.convert_to(data_type.clone()).ok(),
+    }
+}
+
+fn substitute(value: &mut Value, values: &ParameterValues) -> Result<(), String> {
+    match value {
+        Value::String(text) => {
+            if let Some(replaced) = render(text, values)? {
+                *value = replaced;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                substitute(item, values)?;
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                substitute(item, values)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// 没有引用时返回None
+fn render(text: &str, values: &ParameterValues) -> Result<Option<Value>, String> {
+    if !text.contains(TEMPLATE_OPEN) {
+        return Ok(None);
+    }
+    let lookup = |name: &str| values.get(name.trim())
+        .ok_or_else(|| format!("引用了未声明的参数: {}", name.trim()));
+
+    // 整个字符串是单个引用
+    if let Some(name) = text.strip_prefix(TEMPLATE_OPEN).and_then(|rest| rest.strip_suffix(TEMPLATE_CLOSE)) {
+        if !name.contains(TEMPLATE_OPEN) && !name.contains(TEMPLATE_CLOSE) {
+            return lookup(name).map(|value| Some(value.clone()));
+        }
+    }
+
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(TEMPLATE_OPEN) {
+        let end = rest[start..].find(TEMPLATE_CLOSE)
+            .ok_or_else(|| format!("参数引用缺少结束符: {}", text))?;
+        rendered.push_str(&rest[..start]);
+        match lookup(&rest[start + TEMPLATE_OPEN.len()..start + end])? {
+            Value::String(s) => rendered.push_str(s),
+            other => rendered.push_str(&other.to_string()),
+        }
+        rest = &rest[start + end + TEMPLATE_CLOSE.len()..];
+    }
+    rendered.push_str(rest);
+    Ok(Some(Value::String(rendered)))
+}
+
+/// JSON常量转为DataValue，null视为未设置
+pub(crate) fn json_to_value(value: &Value) -> Option<DataValue> {
+    match value {
+        Value::Null => None,
+        Value::Bool(b) => Some(DataValue::Boolean(*b)),
+        Value::Number(n) => n.as_f64().map(DataValue::Number),
+        Value::String(s) => Some(DataValue::Text(s.clone())),
+        Value::Array(items) => Some(DataValue::List(items.iter().filter_map(json_to_value).collect())),
+        Value::Object(map) => Some(DataValue::Dict(map.iter().filter_map(|(k, v)| Some((k.clone(), json_to_value(v)?))).collect())),
+    }
+}
+
+/// DataValue转为JSON，二进制按base64文本表示，模型为其ID，流无法表示
+pub(crate) fn value_to_json(value: &DataValue) -> Option<Value> {
+    match value {
+        DataValue::Number(n) => serde_json::Number::from_f64(*n).map(Value::Number),
+        DataValue::Text(s) => Some(Value::String(s.clone())),
+        DataValue::Boolean(b) => Some(Value::Bool(*b)),
+        DataValue::Path(p) => Some(Value::String(p.to_string_lossy().to_string())),
+        DataValue::Binary(_) => match value.convert_to(DataType::Text) {
+            Ok(DataValue::Text(s)) => Some(Value::String(s)),
+            _ => None,
+        },
+        DataValue::List(items) => items.iter().map(value_to_json).collect::<Option<Vec<_>>>().map(Value::Array),
+        DataValue::Dict(map) => map.iter()
+            .map(|(k, v)| Some((k.clone(), value_to_json(v)?)))
+            .collect::<Option<serde_json::Map<_, _>>>()
+            .map(Value::Object),
+        DataValue::Model(id) => Some(Value::String(id.0.clone())),
+        DataValue::Stream(_) => None,
+    }
+}
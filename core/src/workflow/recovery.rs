@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use crate::engine::RecoveryAction;
+use crate::workflow::executor::WorkflowError;
+use crate::workflow::params::json_to_value;
+use crate::workflow::serialization::NodeData;
+
+/// 从节点config的"on_error"字段解析错误处理策略，未配置时为ImmediateFail
+///
+/// ```json
+/// { "on_error": { "action": "retry", "max_attempts": 3, "backoff_ms": 500 } }
+/// { "on_error": { "action": "fallback", "value": "默认回复" } }
+/// { "on_error": { "action": "skip" } }
+/// ```
+pub fn error_policy(node: &NodeData) -> Result<RecoveryAction, WorkflowError> {
+    let policy = match node.config.as_ref().and_then(|config| config.get("on_error")) {
+        Some(policy) => policy,
+        None => return Ok(RecoveryAction::ImmediateFail),
+    };
+    let invalid = |reason: &str| WorkflowError::InvalidConfig {
+        node_id: node.id.clone(),
+        reason: reason.to_string(),
+    };
+
+    let action = policy.get("action").and_then(|v| v.as_str())
+        .ok_or_else(|| invalid("on_error缺少action"))?;
+
+    match action {
+        "fail" => Ok(RecoveryAction::ImmediateFail),
+        "retry" => {
+            let max_attempts = policy.get("max_attempts").and_then(|v| v.as_u64()).unwrap_or(3) as u32;
+            let backoff_ms = policy.get("backoff_ms").and_then(|v| v.as_u64()).unwrap_or(1000);
+            if max_attempts == 0 {
+                return Err(invalid("max_attempts必须大于0"));
+            }
+            Ok(RecoveryAction::Retry { max_attempts, backoff: Duration::from_millis(backoff_ms) })
+        }
+        "fallback" => {
+            let value = policy.get("value").ok_or_else(|| invalid("fallback缺少value"))?;
+            let value = json_to_value(value).ok_or_else(|| invalid("fallback的value无法转换为DataValue"))?;
+            Ok(RecoveryAction::Fallback { value })
+        }
+        "skip" => Ok(RecoveryAction::Skip),
+        other => Err(invalid(&format!("未知的on_error动作: {}", other))),
+    }
+}
+
+/// 重试前等待时间的上限
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 第n次重试前的等待时间（指数退避：backoff, 2*backoff, 4*backoff...），不超过MAX_BACKOFF
+pub fn retry_delay(backoff: Duration, retry_count: u32) -> Duration {
+    backoff
+        .checked_mul(2u32.saturating_pow(retry_count.saturating_sub(1)))
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
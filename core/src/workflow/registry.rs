@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::engine::NodeError;
+use crate::vram::VramPool;
+use crate::workflow::executor::WorkflowError;
+use crate::workflow::nodes::{Node, TextInputNode, LLMNode, TextOutputNode, LoRASwitchNode, IfNode, SwitchNode, MergeNode};
+use crate::workflow::serialization::NodeData;
+
+/// 节点工厂：由节点数据（主要是config）构建节点实例
+pub type NodeFactory = Box<dyn Fn(&NodeData) -> Result<Box<dyn Node>, NodeError> + Send + Sync>;
+
+/// 节点注册表：类型字符串 -> 节点工厂
+pub struct NodeRegistry {
+    factories: HashMap<String, NodeFactory>,
+}
+
+impl NodeRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self { factories: HashMap::new() }
+    }
+
+    /// 创建包含内置节点（input/llm/output/lora_switch/if/switch/merge）的注册表
+    pub fn with_builtin_nodes(vram_pool: Arc<Mutex<VramPool>>) -> Self {
+        let mut registry = Self::new();
+
+        registry.register("input", |node| {
+            let text = node.config_str("text").or_else(|| node.config_str("label")).unwrap_or("");
+            Ok(Box::new(TextInputNode::new(text)))
+        });
+
+        let llm_pool = Arc::clone(&vram_pool);
+        registry.register("llm", move |node| {
+            let model_id = node.config_str("model_id").or_else(|| node.config_str("modelPath")).unwrap_or("");
+            Ok(Box::new(LLMNode::new(model_id, Arc::clone(&llm_pool))))
+        });
+
+        registry.register("output", |_| Ok(Box::new(TextOutputNode::new())));
+
+        registry.register("lora_switch", move |node| {
+            let model_id = node.config_str("model_id").unwrap_or("");
+            let lora_path = node.config_str("lora_path").unwrap_or("");
+            Ok(Box::new(LoRASwitchNode::new(model_id, lora_path, Arc::clone(&vram_pool))))
+        });
+
+        registry.register("if", |node| Ok(Box::new(IfNode::new(node.config_str("equals")))));
+
+        registry.register("switch", |node| {
+            let cases = node.config_value("cases")
+                .and_then(|v| v.as_array())
+                .map(|cases| cases.iter().filter_map(|c| c.as_str().map(str::to_string)).collect())
+                .unwrap_or_default();
+            Ok(Box::new(SwitchNode::new(cases)))
+        });
+
+        registry.register("merge", |node| {
+            let inputs = node.config_value("inputs").and_then(|v| v.as_u64()).unwrap_or(2);
+            Ok(Box::new(MergeNode::new(inputs as usize)))
+        });
+
+        registry
+    }
+
+    /// 注册节点类型，已存在的同名类型会被覆盖
+    pub fn register<F>(&mut self, node_type: &str, factory: F)
+    where F: Fn(&NodeData) -> Result<Box<dyn Node>, NodeError> + Send + Sync + 'static,
+    {
+        self.factories.insert(node_type.to_string(), Box::new(factory));
+    }
+
+    /// 是否已注册该节点类型
+    pub fn contains(&self, node_type: &str) -> bool {
+        self.factories.contains_key(node_type)
+    }
+
+    /// 获取所有已注册的节点类型
+    pub fn node_types(&self) -> Vec<&str> {
+        self.factories.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// 根据节点数据创建节点实例
+    pub fn create(&self, node: &NodeData) -> Result<Box<dyn Node>, WorkflowError> {
+        let factory = self.factories.get(&node.type)
+            .ok_or_else(|| WorkflowError::UnknownNodeType(node.type.clone()))?;
+
+        factory(node).map_err(|error| WorkflowError::NodeFailed { node_id: node.id.clone(), error })
+    }
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
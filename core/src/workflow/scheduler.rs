@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::workflow::serialization::WorkflowData;
+
+/// 调度器配置
+#[derive(Debug, Clone, Copy)]
+pub struct SchedulerConfig {
+    /// 同时执行的最大节点数
+    pub max_concurrency: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 4,
+        }
+    }
+}
+
+/// 就绪队列调度器：维护依赖计数，按VRAM槽位限制同时使用的模型数
+pub(crate) struct Scheduler {
+    config: SchedulerConfig,
+    remaining: HashMap<String, usize>,
+    dependents: HashMap<String, Vec<String>>,
+    stream_dependents: HashMap<String, Vec<String>>,
+    unthrottled: HashSet<String>,
+    ready: VecDeque<String>,
+    required_models: HashMap<String, String>,
+    models_in_use: HashMap<String, usize>,
+    model_slots: usize,
+    running: HashSet<String>,
+    skipped: HashSet<String>,
+    incoming: HashMap<String, usize>,
+    dead_incoming: HashMap<String, usize>,
+    partial_inputs: HashSet<String>,
+    pruned: Vec<String>,
+}
+
+impl Scheduler {
+    /// required_models: 节点ID -> 所需模型ID；model_slots: VRAM池可同时驻留的模型数
+    pub fn new(
+        workflow: &WorkflowData,
+        required_models: HashMap<String, String>,
+        model_slots: usize,
+        config: SchedulerConfig,
+    ) -> Self {
+        let mut remaining: HashMap<String, usize> = HashMap::new();
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+
+        for node in &workflow.nodes {
+            remaining.entry(node.id.clone()).or_insert(0);
+        }
+        for edge in &workflow.edges {
+            dependents.entry(edge.source.clone()).or_default().push(edge.target.clone());
+            *remaining.entry(edge.target.clone()).or_insert(0) += 1;
+        }
+
+        let incoming = remaining.clone();
+        
+        // 按节点声明顺序入队，保证调度顺序确定
+        let ready = workflow.nodes.iter()
+            .filter(|node| remaining.get(&node.id) == Some(&0))
+            .map(|node| node.id.clone())
+            .collect();
+
+        Self {
+            config,
+            remaining,
+            dependents,
+            stream_dependents: HashMap::new(),
+            unthrottled: HashSet::new(),
+            ready,
+            required_models,
+            models_in_use: HashMap::new(),
+            model_slots: model_slots.max(1),
+            running: HashSet::new(),
+            skipped: HashSet::new(),
+            incoming,
+            dead_incoming: HashMap::new(),
+            partial_inputs: HashSet::new(),
+            pruned: Vec::new(),
+        }
+    }
+
+    /// 设置汇合节点：至少一条入边存活即可执行（默认任一入边为死边即跳过）
+    pub fn with_partial_inputs(mut self, nodes: HashSet<String>) -> Self {
+        self.partial_inputs = nodes;
+        self
+    }
+
+    /// 设置流式边(source, target)：消费者在生产者开始执行时即可启动，而非等待其完成
+    pub fn with_stream_edges(mut self, edges: Vec<(String, String)>) -> Self {
+        for (source, target) in edges {
+            if let Some(targets) = self.dependents.get_mut(&source) {
+                if let Some(position) = targets.iter().position(|t| *t == target) {
+                    targets.remove(position);
+                    self.stream_dependents.entry(source).or_default().push(target);
+                }
+            }
+        }
+        self
+    }
+
+    /// 取出下一个可立即执行的节点；并发已满或所需模型无法驻留时返回None
+    ///
+    /// 流式消费者不受并发上限和模型槽位约束：生产者已在运行，消费者若排队等待，
+    /// 生产者会因通道写满而阻塞，形成死锁。
+    pub fn next_ready(&mut self) -> Option<String> {
+        let under_limit = self.running.len() < self.config.max_concurrency.max(1);
+        let position = self.ready.iter()
+            .position(|id| self.unthrottled.contains(id) || (under_limit && self.model_fits(id)))?;
+        let node_id = self.ready.remove(position)?;
+
+        if let Some(model) = self.required_models.get(&node_id) {
+            *self.models_in_use.entry(model.clone()).or_insert(0) += 1;
+        }
+        self.running.insert(node_id.clone());
+
+        // 生产者开始执行，其流式消费者的该条依赖即满足
+        let consumers = self.stream_dependents.get(&node_id).cloned().unwrap_or_default();
+        for consumer in consumers {
+            if let Some(degree) = self.remaining.get_mut(&consumer) {
+                *degree -= 1;
+                if *degree == 0 && !self.skipped.contains(&consumer) {
+                    self.unthrottled.insert(consumer.clone());
+                    self.ready.push_back(consumer);
+                }
+            }
+        }
+        Some(node_id)
+    }
+
+    /// 标记节点完成，释放模型占用并将新就绪的下游节点入队
+    #[cfg(test)]
+    pub fn complete(&mut self, node_id: &str) {
+        self.complete_with_dead_edges(node_id, &[]);
+    }
+
+    /// 标记分支节点完成：dead_targets为未选中分支的边的目标节点（每条死边一项）。
+    /// 依赖全部就绪但只经死边可达的节点被剪枝，其出边也随之成为死边
+    pub fn complete_with_dead_edges(&mut self, node_id: &str, dead_targets: &[String]) {
+        if !self.release(node_id) {
+            return;
+        }
+
+        // (节点, 死边目标, 是否被剪枝)；被剪枝的节点从未开始执行，其流式边也需要释放
+        let mut stack = vec![(node_id.to_string(), dead_targets.to_vec(), false)];
+        while let Some((current, dead_targets, pruned)) = stack.pop() {
+            for target in dead_targets {
+                *self.dead_incoming.entry(target).or_insert(0) += 1;
+            }
+
+            let mut targets = self.dependents.get(&current).cloned().unwrap_or_default();
+            if pruned {
+                targets.extend(self.stream_dependents.get(&current).cloned().unwrap_or_default());
+            }
+            for target in targets {
+                let degree = match self.remaining.get_mut(&target) {
+                    Some(degree) => degree,
+                    None => continue,
+                };
+                *degree -= 1;
+                if *degree > 0 || self.skipped.contains(&target) {
+                    continue;
+                }
+
+                if self.is_dead(&target) {
+                    self.skipped.insert(target.clone());
+                    self.pruned.push(target.clone());
+                    let mut outgoing = self.dependents.get(&target).cloned().unwrap_or_default();
+                    outgoing.extend(self.stream_dependents.get(&target).cloned().unwrap_or_default());
+                    stack.push((target, outgoing, true));
+                } else {
+                    self.ready.push_back(target);
+                }
+            }
+        }
+    }
+
+    /// 取出自上次调用以来因分支未选中而被剪枝的节点
+    pub fn take_pruned(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.pruned)
+    }
+
+    /// 跳过节点及其所有下游节点，返回被连带跳过的下游节点ID
+    pub fn skip(&mut self, node_id: &str) -> Vec<String> {
+        self.release(node_id);
+        self.skipped.insert(node_id.to_string());
+
+        let mut newly_skipped = Vec::new();
+        let mut stack = vec![node_id.to_string()];
+        while let Some(current) = stack.pop() {
+            let targets = self.dependents.get(&current).into_iter()
+                .chain(self.stream_dependents.get(&current))
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>();
+            for target in targets {
+                // 已开始执行的流式消费者照常结束
+                if self.running.contains(&target) {
+                    continue;
+                }
+                if self.skipped.insert(target.clone()) {
+                    self.ready.retain(|id| *id != target);
+                    newly_skipped.push(target.clone());
+                    stack.push(target);
+                }
+            }
+        }
+        newly_skipped
+    }
+
+    // 从运行集合中移除节点并释放其模型占用
+    fn release(&mut self, node_id: &str) -> bool {
+        if !self.running.remove(node_id) {
+            return false;
+        }
+
+        if let Some(model) = self.required_models.get(node_id) {
+            if let Some(count) = self.models_in_use.get_mut(model) {
+                *count -= 1;
+                if *count == 0 {
+                    self.models_in_use.remove(model);
+                }
+            }
+        }
+        true
+    }
+
+    // 普通节点任一入边为死边即不执行；汇合节点所有入边均为死边才不执行
+    fn is_dead(&self, node_id: &str) -> bool {
+        let dead = self.dead_incoming.get(node_id).copied().unwrap_or(0);
+        if self.partial_inputs.contains(node_id) {
+            dead > 0 && dead >= self.incoming.get(node_id).copied().unwrap_or(0)
+        } else {
+            dead > 0
+        }
+    }
+
+    /// 节点是否已被跳过或剪枝
+    pub fn is_skipped(&self, node_id: &str) -> bool {
+        self.skipped.contains(node_id)
+    }
+
+    /// 正在执行的节点数
+    pub fn active_tasks(&self) -> usize {
+        self.running.len()
+    }
+
+    /// 是否没有正在执行和等待执行的节点
+    pub fn is_idle(&self) -> bool {
+        self.running.is_empty() && self.ready.is_empty()
+    }
+
+    // 同一模型的节点可共享槽位；新模型只在仍有空闲槽位时才允许启动，避免LRU反复换入换出
+    fn model_fits(&self, node_id: &str) -> bool {
+        match self.required_models.get(node_id) {
+            None => true,
+            Some(model) => {
+                self.models_in_use.contains_key(model) || self.models_in_use.len() < self.model_slots
+            }
+        }
+    }
+}
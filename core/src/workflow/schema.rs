@@ -0,0 +1,185 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::workflow::serialization::WorkflowData;
+
+/// .mflow格式的当前版本
+///
+/// 版本历史：
+/// - 1.0：初始格式
+/// - 1.1：边增加sourceHandle/targetHandle（显式端口）
+/// - 1.2：节点增加parentId（循环体作用域）
+/// - 1.3：工作流增加parameters（工作流级参数）
+pub const CURRENT_VERSION: &str = "1.3";
+
+/// 未写版本号的文件视为最早的格式
+const INITIAL_VERSION: &str = "1.0";
+
+// 各层级允许出现的字段
+const WORKFLOW_FIELDS: &[&str] = &["version", "nodes", "edges", "parameters"];
+const PARAMETER_FIELDS: &[&str] = &["name", "type", "default", "description"];
+const NODE_FIELDS: &[&str] = &["id", "type", "position", "data", "config", "default_inputs", "input_types", "parentId"];
+const EDGE_FIELDS: &[&str] = &["id", "source", "target", "sourceHandle", "targetHandle", "animated"];
+const POSITION_FIELDS: &[&str] = &["x", "y"];
+
+/// 单步迁移：将JSON从上一版本原地升级到下一版本，必要时追加警告
+type Migration = fn(&mut Map<String, Value>, &mut Vec<String>);
+
+/// 迁移链：(源版本, 目标版本, 迁移函数)，按版本顺序排列
+const MIGRATIONS: &[(&str, &str, Migration)] = &[
+    ("1.0", "1.1", migrate_1_0_to_1_1),
+    ("1.1", "1.2", migrate_1_1_to_1_2),
+    ("1.2", "1.3", migrate_1_2_to_1_3),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("工作流解析失败: {0}")]
+    Parse(String),
+    #[error("无效的版本号: {0}")]
+    InvalidVersion(String),
+    #[error("不支持的版本: {found}（当前支持到{supported}）")]
+    UnsupportedVersion { found: String, supported: String },
+    #[error("未知字段: {path}.{field}")]
+    UnknownField { path: String, field: String },
+}
+
+/// 加载选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadOptions {
+    /// 严格模式：未知字段和高于当前的版本直接报错，而非警告
+    pub strict: bool,
+}
+
+impl LoadOptions {
+    pub fn strict() -> Self {
+        Self { strict: true }
+    }
+}
+
+/// 加载结果：升级到当前版本的工作流及加载过程中的警告
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedWorkflow {
+    pub workflow: WorkflowData,
+    pub warnings: Vec<String>,
+    /// 文件原始版本（经过迁移时）
+    pub migrated_from: Option<String>,
+}
+
+/// 解析.mflow文件：按迁移链升级到当前版本，再检查未知字段
+pub fn load_workflow(json: &str, options: LoadOptions) -> Result<LoadedWorkflow, SchemaError> {
+    let mut value: Value = serde_json::from_str(json).map_err(|e| SchemaError::Parse(e.to_string()))?;
+    let root = value.as_object_mut()
+        .ok_or_else(|| SchemaError::Parse("顶层必须是对象".to_string()))?;
+    let mut warnings = Vec::new();
+
+    let original = match root.get("version") {
+        Some(Value::String(version)) => version.clone(),
+        Some(other) => return Err(SchemaError::InvalidVersion(other.to_string())),
+        None => {
+            warnings.push(format!("缺少版本号，按{}处理", INITIAL_VERSION));
+            INITIAL_VERSION.to_string()
+        }
+    };
+
+    let found = parse_version(&original)?;
+    let current = parse_version(CURRENT_VERSION)?;
+    if found > current {
+        if options.strict {
+            return Err(SchemaError::UnsupportedVersion { found: original, supported: CURRENT_VERSION.to_string() });
+        }
+        warnings.push(format!("文件版本{}高于当前支持的{}，按当前版本解析", original, CURRENT_VERSION));
+    }
+
+    // 按数值比较，"1.01"与"1.1"是同一版本
+    let mut version = found;
+    for (from, to, migrate) in MIGRATIONS {
+        let from = parse_version(from)?;
+        if from < found {
+            continue;
+        }
+        if from != version {
+            return Err(SchemaError::UnsupportedVersion { found: original, supported: CURRENT_VERSION.to_string() });
+        }
+        migrate(root, &mut warnings);
+        version = parse_version(to)?;
+    }
+    let migrated_from = (found < current).then(|| original.clone());
+    if let Some(from) = &migrated_from {
+        warnings.push(format!("已从版本{}升级到{}", from, CURRENT_VERSION));
+        root.insert("version".to_string(), Value::String(CURRENT_VERSION.to_string()));
+    }
+
+    for (path, field) in unknown_fields(root) {
+        if options.strict {
+            return Err(SchemaError::UnknownField { path, field });
+        }
+        warnings.push(format!("忽略未知字段: {}.{}", path, field));
+    }
+
+    let workflow = serde_json::from_value(value).map_err(|e| SchemaError::Parse(e.to_string()))?;
+    Ok(LoadedWorkflow { workflow, warnings, migrated_from })
+}
+
+// "主版本.次版本"
+fn parse_version(version: &str) -> Result<(u32, u32), SchemaError> {
+    let invalid = || SchemaError::InvalidVersion(version.to_string());
+    let (major, minor) = version.split_once('.').ok_or_else(invalid)?;
+    Ok((major.parse().map_err(|_| invalid())?, minor.parse().map_err(|_| invalid())?))
+}
+
+// 1.0的边没有端口句柄，animated可省略
+fn migrate_1_0_to_1_1(root: &mut Map<String, Value>, _warnings: &mut Vec<String>) {
+    for edge in objects_mut(root, "edges") {
+        edge.entry("sourceHandle").or_insert(Value::Null);
+        edge.entry("targetHandle").or_insert(Value::Null);
+        edge.entry("animated").or_insert(Value::Bool(false));
+    }
+}
+
+// 1.2起节点可属于循环体；旧文件的节点都在顶层。旧版画布可能写入"parentNode"
+fn migrate_1_1_to_1_2(root: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    for node in objects_mut(root, "nodes") {
+        if let Some(parent) = node.remove("parentNode") {
+            warnings.push(format!("节点{}的parentNode已改名为parentId", node.get("id").unwrap_or(&Value::Null)));
+            node.insert("parentId".to_string(), parent);
+        }
+    }
+}
+
+// 1.3新增的parameters可省略，旧文件没有参数
+fn migrate_1_2_to_1_3(root: &mut Map<String, Value>, _warnings: &mut Vec<String>) {
+    root.entry("parameters").or_insert_with(|| Value::Array(Vec::new()));
+}
+
+fn objects_mut<'a>(root: &'a mut Map<String, Value>, key: &str) -> impl Iterator<Item = &'a mut Map<String, Value>> {
+    root.get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+// 返回(路径, 字段名)，路径形如"nodes[0].position"
+fn unknown_fields(root: &Map<String, Value>) -> Vec<(String, String)> {
+    let mut unknown = Vec::new();
+    let mut check = |path: &str, object: &Map<String, Value>, known: &[&str]| {
+        for field in object.keys().filter(|field| !known.contains(&field.as_str())) {
+            unknown.push((path.to_string(), field.clone()));
+        }
+    };
+
+    check("workflow", root, WORKFLOW_FIELDS);
+    for (key, known) in [("nodes", NODE_FIELDS), ("edges", EDGE_FIELDS), ("parameters", PARAMETER_FIELDS)] {
+        let items = root.get(key).and_then(Value::as_array).into_iter().flatten();
+        for (i, item) in items.enumerate() {
+            let Some(object) = item.as_object() else { continue };
+            let path = format!("{}[{}]", key, i);
+            check(&path, object, known);
+            if let Some(position) = object.get("position").and_then(Value::as_object) {
+                check(&format!("{}.position", path), position, POSITION_FIELDS);
+            }
+        }
+    }
+    unknown
+}
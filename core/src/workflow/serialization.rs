@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 use serde_json;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::types::DataType;
+use crate::workflow::schema::{self, CURRENT_VERSION, LoadOptions};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeData {
     pub id: String,
     pub type: String,
@@ -10,36 +13,80 @@ pub struct NodeData {
     pub config: Option<serde_json::Value>,
     pub default_inputs: Option<serde_json::Value>,
     pub input_types: Option<serde_json::Value>,
+    /// 所属的循环节点（ReactFlow的parentId），为空时位于顶层
+    #[serde(rename = "parentId", default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+impl NodeData {
+    /// 读取配置值：优先config，其次画布提交的data
+    pub fn config_value(&self, key: &str) -> Option<&serde_json::Value> {
+        self.config.as_ref()
+            .and_then(|config| config.get(key))
+            .or_else(|| self.data.get(key))
+    }
+    
+    /// 读取字符串配置值
+    pub fn config_str(&self, key: &str) -> Option<&str> {
+        self.config_value(key).and_then(|v| v.as_str())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub x: f64,
     pub y: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EdgeData {
     pub id: String,
     pub source: String,
     pub target: String,
+    /// 源端口（ReactFlow的sourceHandle），为空时使用节点默认输出端口
+    #[serde(rename = "sourceHandle", default)]
+    pub source_handle: Option<String>,
+    /// 目标端口（ReactFlow的targetHandle），为空时使用节点默认输入端口
+    #[serde(rename = "targetHandle", default)]
+    pub target_handle: Option<String>,
     pub animated: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 工作流级参数：节点的config/data/default_inputs中以"{{名称}}"引用，每次运行可覆盖
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: DataType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowData {
     pub version: String,
     pub nodes: Vec<NodeData>,
     pub edges: Vec<EdgeData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<WorkflowParameter>,
 }
 
 impl WorkflowData {
+    /// 以当前格式版本创建工作流
+    pub fn new(nodes: Vec<NodeData>, edges: Vec<EdgeData>) -> Self {
+        Self { version: CURRENT_VERSION.to_string(), nodes, edges, parameters: Vec::new() }
+    }
+    
     pub fn to_json(&self) -> String {
         serde_json::to_string(self).unwrap_or_default()
     }
     
-    pub fn from_json(s: &str) -> Result<Self, String> {
-        serde_json::from_str(s).map_err(|e| e.to_string())
+    /// 解析并升级到当前版本，同时返回迁移和未知字段的警告
+    pub fn from_json(s: &str) -> Result<(Self, Vec<String>), String> {
+        let loaded = schema::load_workflow(s, LoadOptions::default()).map_err(|e| e.to_string())?;
+        Ok((loaded.workflow, loaded.warnings))
     }
     
     pub fn get_node(&self, node_id: &str) -> Option<&NodeData> {
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use crate::parameter::PortId;
+use crate::types::{DataType, DataValue};
+
+/// 每条流式边的通道容量（块数），写满后生产者阻塞等待消费者
+pub const STREAM_BUFFER: usize = 64;
+
+/// 流式块的观察者：(端口, 块)，用于发布TokenGenerated事件
+pub type ChunkObserver = Arc<dyn Fn(&str, &DataValue) + Send + Sync>;
+
+/// 是否为流式端口类型
+pub fn is_stream_type(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Stream(_))
+}
+
+/// 节点执行时的流式端口：输出端口连接的每条流式边一个发送端，输入端口一个接收端
+///
+/// 发送端即下游拿到的`DataValue::Stream`通道；节点返回后发送端被丢弃，消费者随之收到流结束。
+#[derive(Default)]
+pub struct NodeStreams {
+    outputs: HashMap<PortId, Vec<DataValue>>,
+    inputs: HashMap<PortId, Receiver<DataValue>>,
+    observer: Option<ChunkObserver>,
+}
+
+impl NodeStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_output(&mut self, port: PortId, sender: Sender<DataValue>) {
+        self.outputs.entry(port).or_default().push(DataValue::Stream(sender));
+    }
+
+    pub fn add_input(&mut self, port: PortId, receiver: Receiver<DataValue>) {
+        self.inputs.insert(port, receiver);
+    }
+
+    /// 每个发送的块（无论是否有消费者）和每个收到的块都交给observer
+    pub fn set_observer(&mut self, observer: ChunkObserver) {
+        self.observer = Some(observer);
+    }
+
+    /// 输出端口是否连接了消费者
+    pub fn has_output(&self, port: &str) -> bool {
+        self.outputs.contains_key(port)
+    }
+
+    /// 输入端口是否连接了流
+    pub fn has_input(&self, port: &str) -> bool {
+        self.inputs.contains_key(port)
+    }
+
+    /// 向输出端口的所有消费者发送一个块（阻塞直到通道有空位）；已退出的消费者被忽略
+    pub fn send(&self, port: &str, chunk: DataValue) {
+        if let Some(observer) = &self.observer {
+            observer(port, &chunk);
+        }
+        for stream in self.outputs.get(port).into_iter().flatten() {
+            if let DataValue::Stream(sender) = stream {
+                let _ = sender.blocking_send(chunk.clone());
+            }
+        }
+    }
+
+    /// 阻塞读取输入流的下一个块，流结束返回None
+    pub fn recv(&mut self, port: &str) -> Option<DataValue> {
+        let chunk = self.inputs.get_mut(port)?.blocking_recv()?;
+        if let Some(observer) = &self.observer {
+            observer(port, &chunk);
+        }
+        Some(chunk)
+    }
+}
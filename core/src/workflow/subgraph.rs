@@ -0,0 +1,232 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::parameter::{DynamicPorts, Port};
+use crate::workflow::executor::{WorkflowError, sole_port};
+use crate::workflow::serialization::{NodeData, WorkflowData};
+
+/// 子图节点类型
+pub const SUBGRAPH_TYPE: &str = "subgraph";
+
+/// 展开后内部节点ID的路径分隔符："<子图节点ID>/<内部节点ID>"
+pub const PATH_SEPARATOR: &str = "/";
+
+/// 查询节点端口（由执行器通过注册表提供）
+pub type PortsOf<'a> = dyn Fn(&NodeData) -> Result<DynamicPorts, WorkflowError> + 'a;
+
+/// 子图来源：config.workflow内联，或config.path引用.mflow文件（相对于所在工作流文件的目录）
+pub enum SubgraphSource {
+    Inline(WorkflowData),
+    File(PathBuf),
+}
+
+impl SubgraphSource {
+    /// 由节点解析子图来源，非子图节点返回None
+    pub fn from_node(node: &NodeData) -> Result<Option<Self>, WorkflowError> {
+        if node.type != SUBGRAPH_TYPE {
+            return Ok(None);
+        }
+        let invalid = |reason: String| WorkflowError::InvalidConfig { node_id: node.id.clone(), reason };
+
+        if let Some(inline) = node.config_value("workflow") {
+            let workflow = serde_json::from_value(inline.clone())
+                .map_err(|e| invalid(format!("内联子图解析失败: {}", e)))?;
+            Ok(Some(SubgraphSource::Inline(workflow)))
+        } else if let Some(path) = node.config_str("path") {
+            Ok(Some(SubgraphSource::File(PathBuf::from(path))))
+        } else {
+            Err(invalid("子图需要配置workflow或path".to_string()))
+        }
+    }
+}
+
+// 子图暴露的端口：端口ID为"<内部节点ID>.<端口ID>"
+struct ExposedPort {
+    port: Port,
+    node_id: String,
+    port_id: String,
+}
+
+struct ExposedPorts {
+    inputs: Vec<ExposedPort>,
+    outputs: Vec<ExposedPort>,
+}
+
+impl ExposedPorts {
+    fn to_dynamic_ports(&self) -> DynamicPorts {
+        let mut ports = DynamicPorts::new();
+        for exposed in &self.inputs {
+            ports.add_input(exposed.port.clone());
+        }
+        for exposed in &self.outputs {
+            ports.add_output(exposed.port.clone());
+        }
+        ports
+    }
+}
+
+/// 展开工作流中的所有子图节点（递归）：内部节点和边的ID加上"<子图节点ID>/"前缀，
+/// 连到子图节点的边改为连到对应的内部节点端口
+pub fn expand_subgraphs(workflow: &WorkflowData, base_dir: &Path, ports_of: &PortsOf) -> Result<WorkflowData, WorkflowError> {
+    expand(workflow, base_dir, ports_of, &mut Vec::new())
+}
+
+/// 子图节点暴露的端口：内部顶层节点上未连接的输入/输出端口
+pub fn subgraph_ports(node: &NodeData, base_dir: &Path, ports_of: &PortsOf) -> Result<DynamicPorts, WorkflowError> {
+    let mut stack = Vec::new();
+    let (inner, inner_dir) = load(node, base_dir, &mut stack)?;
+    let inner = expand(&inner, &inner_dir, ports_of, &mut stack)?;
+    Ok(exposed_ports(&inner, ports_of)?.to_dynamic_ports())
+}
+
+fn expand(workflow: &WorkflowData, base_dir: &Path, ports_of: &PortsOf, stack: &mut Vec<PathBuf>) -> Result<WorkflowData, WorkflowError> {
+    if !workflow.nodes.iter().any(|node| node.type == SUBGRAPH_TYPE) {
+        return Ok(workflow.clone());
+    }
+
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut parameters = workflow.parameters.clone();
+    let mut exposed: HashMap<String, ExposedPorts> = HashMap::new();
+
+    for node in &workflow.nodes {
+        if node.type != SUBGRAPH_TYPE {
+            nodes.push(node.clone());
+            continue;
+        }
+
+        let depth = stack.len();
+        let (inner, inner_dir) = load(node, base_dir, stack)?;
+        let inner = expand(&inner, &inner_dir, ports_of, stack)?;
+        stack.truncate(depth);
+
+        exposed.insert(node.id.clone(), exposed_ports(&inner, ports_of)?);
+        // 子图声明的参数并入外层，同名时外层优先
+        for parameter in inner.parameters {
+            if !parameters.iter().any(|p| p.name == parameter.name) {
+                parameters.push(parameter);
+            }
+        }
+        for mut inner_node in inner.nodes {
+            inner_node.id = nested_id(&node.id, &inner_node.id);
+            inner_node.parent_id = match inner_node.parent_id {
+                Some(parent) => Some(nested_id(&node.id, &parent)),
+                None => node.parent_id.clone(),
+            };
+            nodes.push(inner_node);
+        }
+        for mut inner_edge in inner.edges {
+            inner_edge.id = nested_id(&node.id, &inner_edge.id);
+            inner_edge.source = nested_id(&node.id, &inner_edge.source);
+            inner_edge.target = nested_id(&node.id, &inner_edge.target);
+            edges.push(inner_edge);
+        }
+    }
+
+    for edge in &workflow.edges {
+        let mut edge = edge.clone();
+        if let Some(ports) = exposed.get(&edge.source) {
+            let port = resolve(&ports.outputs, edge.source_handle.as_deref(), &edge.source)?;
+            edge.source = nested_id(&edge.source, &port.node_id);
+            edge.source_handle = Some(port.port_id.clone());
+        }
+        if let Some(ports) = exposed.get(&edge.target) {
+            let port = resolve(&ports.inputs, edge.target_handle.as_deref(), &edge.target)?;
+            edge.target = nested_id(&edge.target, &port.node_id);
+            edge.target_handle = Some(port.port_id.clone());
+        }
+        edges.push(edge);
+    }
+
+    Ok(WorkflowData { version: workflow.version.clone(), nodes, edges, parameters })
+}
+
+/// 加载子图，文件引用入栈用于检测递归包含；返回子图及其相对路径的基准目录
+fn load(node: &NodeData, base_dir: &Path, stack: &mut Vec<PathBuf>) -> Result<(WorkflowData, PathBuf), WorkflowError> {
+    let invalid = |reason: String| WorkflowError::InvalidConfig { node_id: node.id.clone(), reason };
+
+    match SubgraphSource::from_node(node)? {
+        Some(SubgraphSource::Inline(workflow)) => Ok((workflow, base_dir.to_path_buf())),
+        Some(SubgraphSource::File(path)) => {
+            let path = base_dir.join(path);
+            let path = path.canonicalize().unwrap_or(path);
+            if stack.contains(&path) {
+                let chain: Vec<String> = stack.iter().chain(std::iter::once(&path))
+                    .map(|p| p.display().to_string())
+                    .collect();
+                return Err(WorkflowError::RecursiveSubgraph(chain.join(" -> ")));
+            }
+
+            let json = fs::read_to_string(&path)
+                .map_err(|e| invalid(format!("读取子图{:?}失败: {}", path, e)))?;
+            // 子图文件的迁移警告不影响展开
+            let (workflow, _warnings) = WorkflowData::from_json(&json)
+                .map_err(|e| invalid(format!("解析子图{:?}失败: {}", path, e)))?;
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            stack.push(path);
+            Ok((workflow, dir))
+        }
+        None => Err(invalid("不是子图节点".to_string())),
+    }
+}
+
+fn exposed_ports(inner: &WorkflowData, ports_of: &PortsOf) -> Result<ExposedPorts, WorkflowError> {
+    let top_level: Vec<&NodeData> = inner.nodes.iter().filter(|node| node.parent_id.is_none()).collect();
+    let mut node_ports = HashMap::new();
+    for node in &top_level {
+        node_ports.insert(node.id.as_str(), ports_of(node)?);
+    }
+
+    // 内部边已占用的端口；循环体内的边不涉及循环节点的外部端口
+    let mut connected_inputs: HashSet<(&str, String)> = HashSet::new();
+    let mut connected_outputs: HashSet<(&str, String)> = HashSet::new();
+    for edge in &inner.edges {
+        let (source_ports, target_ports) = match (node_ports.get(edge.source.as_str()), node_ports.get(edge.target.as_str())) {
+            (Some(source), Some(target)) => (source, target),
+            _ => continue,
+        };
+        if let Some(port) = edge.source_handle.clone().or_else(|| sole_port(source_ports.outputs())) {
+            connected_outputs.insert((edge.source.as_str(), port));
+        }
+        if let Some(port) = edge.target_handle.clone().or_else(|| sole_port(target_ports.inputs())) {
+            connected_inputs.insert((edge.target.as_str(), port));
+        }
+    }
+
+    let mut exposed = ExposedPorts { inputs: Vec::new(), outputs: Vec::new() };
+    for node in &top_level {
+        let ports = &node_ports[node.id.as_str()];
+        let expose = |port: &Port| ExposedPort {
+            port: Port { id: format!("{}.{}", node.id, port.id), ..port.clone() },
+            node_id: node.id.clone(),
+            port_id: port.id.clone(),
+        };
+        exposed.inputs.extend(ports.inputs()
+            .filter(|port| !connected_inputs.contains(&(node.id.as_str(), port.id.clone())))
+            .map(expose));
+        exposed.outputs.extend(ports.outputs()
+            .filter(|port| !connected_outputs.contains(&(node.id.as_str(), port.id.clone())))
+            .map(expose));
+    }
+    // 按端口ID排序，保证暴露顺序确定
+    exposed.inputs.sort_by(|a, b| a.port.id.cmp(&b.port.id));
+    exposed.outputs.sort_by(|a, b| a.port.id.cmp(&b.port.id));
+    Ok(exposed)
+}
+
+fn resolve<'a>(ports: &'a [ExposedPort], handle: Option<&str>, subgraph_id: &str) -> Result<&'a ExposedPort, WorkflowError> {
+    match handle {
+        Some(handle) => ports.iter().find(|p| p.port.id == handle)
+            .ok_or_else(|| WorkflowError::InvalidConfig {
+                node_id: subgraph_id.to_string(),
+                reason: format!("子图没有暴露端口{}", handle),
+            }),
+        None if ports.len() == 1 => Ok(&ports[0]),
+        None => Err(WorkflowError::PortNotSpecified(subgraph_id.to_string())),
+    }
+}
+
+fn nested_id(subgraph_id: &str, inner_id: &str) -> String {
+    format!("{}{}{}", subgraph_id, PATH_SEPARATOR, inner_id)
+}
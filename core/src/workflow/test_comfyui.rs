@@ -0,0 +1,108 @@
+use super::comfyui::{import_comfyui, ComfyFormat, ImportError, PLACEHOLDER_TYPE};
+use super::WorkflowExecutor;
+use super::test_support::mock_context;
+use crate::types::DataValue;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_api_format() {
+        let json = r#"{
+            "4": {"class_type": "CheckpointLoaderSimple", "inputs": {"ckpt_name": "sd15.safetensors"}},
+            "6": {"class_type": "CLIPTextEncode", "inputs": {"text": "一只猫", "clip": ["4", 1]}, "_meta": {"title": "正向提示词"}},
+            "7": {"class_type": "OllamaGenerate", "inputs": {"prompt": ["6", 0], "model": "qwen2.gguf"}}
+        }"#;
+
+        let imported = import_comfyui(json).unwrap();
+        let workflow = &imported.workflow;
+        assert_eq!(imported.report.format, ComfyFormat::Api);
+        assert_eq!(imported.report.mapped_nodes, 2);
+        assert_eq!(imported.report.placeholders.len(), 1);
+        assert_eq!(imported.report.placeholders[0].class_type, "CheckpointLoaderSimple");
+        // CLIPTextEncode映射为文本输入，没有clip端口
+        assert_eq!(imported.report.dropped_links.len(), 1);
+
+        assert_eq!(workflow.get_node("4").unwrap().type, PLACEHOLDER_TYPE);
+        assert_eq!(workflow.get_node("6").unwrap().config_str("text"), Some("一只猫"));
+        assert_eq!(workflow.get_node("7").unwrap().config_str("model_id"), Some("qwen2.gguf"));
+
+        assert_eq!(workflow.edges.len(), 1);
+        assert_eq!(workflow.edges[0].source_handle.as_deref(), Some("text"));
+        assert_eq!(workflow.edges[0].target_handle.as_deref(), Some("prompt"));
+        // 按深度分列排布
+        assert!(workflow.get_node("7").unwrap().position.x > workflow.get_node("6").unwrap().position.x);
+    }
+
+    #[test]
+    fn test_import_ui_format_preserves_positions() {
+        let json = r#"{
+            "nodes": [
+                {"id": 1, "type": "PrimitiveStringMultiline", "pos": [120, 80], "outputs": [{"name": "STRING"}], "widgets_values": ["你好"]},
+                {"id": 2, "type": "Reroute", "pos": [300, 80], "inputs": [{"name": "", "link": 1}], "outputs": [{"name": ""}]},
+                {"id": 3, "type": "LoraLoader", "pos": {"0": 500, "1": 90}, "widgets_values": ["style.safetensors", 1.0, 1.0]},
+                {"id": 4, "type": "ShowText|pysssss", "pos": [700, 80], "inputs": [{"name": "text", "link": 2}]}
+            ],
+            "links": [[1, 1, 0, 2, 0, "STRING"], [2, 2, 0, 4, 0, "STRING"]]
+        }"#;
+
+        let imported = import_comfyui(json).unwrap();
+        let workflow = &imported.workflow;
+        assert_eq!(imported.report.format, ComfyFormat::Ui);
+        assert!(imported.report.placeholders.is_empty());
+
+        let lora = workflow.get_node("3").unwrap();
+        assert_eq!(lora.type, "lora_switch");
+        assert_eq!(lora.config_str("lora_path"), Some("style.safetensors"));
+        assert_eq!((lora.position.x, lora.position.y), (500.0, 90.0));
+
+        // 中转节点被折叠：文本直接连到输出
+        assert!(workflow.get_node("2").is_none());
+        assert_eq!(workflow.edges.len(), 1);
+        assert_eq!(workflow.edges[0].source, "1");
+        assert_eq!(workflow.edges[0].target, "4");
+    }
+
+    #[test]
+    fn test_lora_loader_links_kept() {
+        let json = r#"{
+            "1": {"class_type": "PrimitiveString", "inputs": {"value": "base"}},
+            "3": {"class_type": "CheckpointLoaderSimple", "inputs": {"ckpt_name": "sd15.safetensors"}},
+            "2": {"class_type": "LoraLoader", "inputs": {"model": ["1", 0], "clip": ["3", 1], "lora_name": "style.safetensors", "strength_model": 1.0, "strength_clip": 1.0}},
+            "5": {"class_type": "KSampler", "inputs": {"model": ["2", 0], "seed": 1}}
+        }"#;
+
+        let imported = import_comfyui(json).unwrap();
+        let edge = |source: &str, target: &str| imported.workflow.edges.iter()
+            .find(|e| e.source == source && e.target == target)
+            .map(|e| (e.source_handle.as_deref().unwrap_or(""), e.target_handle.as_deref().unwrap_or("")));
+
+        assert_eq!(edge("1", "2"), Some(("text", "model_id")));
+        assert_eq!(edge("2", "5"), Some(("model_id", "model")));
+        // 只有clip连线没有对应端口
+        assert_eq!(imported.report.dropped_links.len(), 1);
+        assert!(imported.report.dropped_links[0].contains("clip"));
+    }
+
+    #[test]
+    fn test_imported_constant_inputs_used_when_running() {
+        // prompt是常量，导入后位于default_inputs，没有连线
+        let json = r#"{
+            "1": {"class_type": "OllamaGenerate", "inputs": {"prompt": "你好", "model": "base"}},
+            "2": {"class_type": "ShowText|pysssss", "inputs": {"text": ["1", 0]}}
+        }"#;
+        let imported = import_comfyui(json).unwrap();
+        assert_eq!(imported.workflow.get_node("1").unwrap().default_inputs, Some(serde_json::json!({ "prompt": "你好" })));
+
+        let executor = WorkflowExecutor::new(mock_context());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&imported.workflow)).unwrap();
+        assert_eq!(result.output("2", "output"), Some(&DataValue::Text("[模型base响应] 你输入了: 你好".to_string())));
+    }
+
+    #[test]
+    fn test_unknown_format_rejected() {
+        assert!(matches!(import_comfyui(r#"{"foo": 1}"#), Err(ImportError::UnknownFormat)));
+    }
+}
@@ -0,0 +1,1434 @@
+use super::{WorkflowExecutor, ExecutionContext, ExecutionEvent, ExecutionResult, WorkflowError, WorkflowData, NodeData, EdgeData, Position, Node, detect_workflow_cycles, EdgeError, EdgeErrorKind, IssueCode, Severity};
+use super::test_support::{mock_context, mock_context_with};
+use crate::model::{MockBackend, MockModelSpec};
+use crate::engine::{CancellationToken, NodeError, MainState, RecoveryAction, RunningSubState};
+use crate::workflow::nodes::ERR_CANCELLED;
+use crate::workflow::scheduler::SchedulerConfig;
+use crate::workflow::recovery::{error_policy, retry_delay, MAX_BACKOFF};
+use crate::parameter::{DynamicPorts, Port, PortId};
+use crate::vram::{Tier, VramPool};
+use crate::ffi::{FfiError, LoadParams};
+use crate::types::{DataType, DataValue};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 创建测试节点
+    fn create_node(id: &str, node_type: &str, label: &str) -> NodeData {
+        NodeData {
+            id: id.to_string(),
+            type: node_type.to_string(),
+            position: Position { x: 0.0, y: 0.0 },
+            data: serde_json::json!({ "label": label }),
+            config: Some(serde_json::json!({ "model_id": "test" })),
+            default_inputs: None,
+            input_types: None,
+            parent_id: None,
+        }
+    }
+
+    // 创建测试边
+    fn create_edge(id: &str, source: &str, target: &str, handles: Option<(&str, &str)>) -> EdgeData {
+        EdgeData {
+            id: id.to_string(),
+            source: source.to_string(),
+            target: target.to_string(),
+            source_handle: handles.map(|(s, _)| s.to_string()),
+            target_handle: handles.map(|(_, t)| t.to_string()),
+            animated: false,
+        }
+    }
+
+    // 测试用自定义节点：将文本转为大写
+    struct UpperNode {
+        ports: DynamicPorts,
+    }
+
+    impl UpperNode {
+        fn new() -> Self {
+            let mut ports = DynamicPorts::new();
+            ports.add_input(Port { id: "in".to_string(), data_type: DataType::Text, multiple: false });
+            ports.add_output(Port { id: "out".to_string(), data_type: DataType::Text, multiple: false });
+            Self { ports }
+        }
+    }
+
+    impl Node for UpperNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            let text = inputs.get("in").and_then(|v| v.as_text()).unwrap_or("").to_uppercase();
+            let mut outputs = HashMap::new();
+            outputs.insert("out".to_string(), DataValue::Text(text));
+            Ok(outputs)
+        }
+
+        fn ports(&self) -> DynamicPorts { self.ports.clone() }
+    }
+
+    // 测试用节点：总是失败
+    struct FailNode;
+
+    impl Node for FailNode {
+        fn execute(&self, _inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            Err(NodeError::new("boom".to_string(), 1, None))
+        }
+
+        fn ports(&self) -> DynamicPorts {
+            let mut ports = DynamicPorts::new();
+            ports.add_input(Port { id: "in".to_string(), data_type: DataType::Text, multiple: false });
+            ports.add_output(Port { id: "out".to_string(), data_type: DataType::Text, multiple: false });
+            ports
+        }
+    }
+
+    // 测试用节点：前n次调用失败
+    struct FlakyNode {
+        failures_left: AtomicUsize,
+    }
+
+    impl Node for FlakyNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            if self.failures_left.load(Ordering::SeqCst) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                return Err(NodeError::new("flaky".to_string(), 2, None));
+            }
+            let mut outputs = HashMap::new();
+            outputs.insert("out".to_string(), inputs.get("in").cloned().unwrap_or(DataValue::Text(String::new())));
+            Ok(outputs)
+        }
+
+        fn ports(&self) -> DynamicPorts { FailNode.ports() }
+    }
+
+    // 测试用节点：阻塞直到被取消
+    struct WaitForCancelNode;
+
+    impl Node for WaitForCancelNode {
+        fn execute(&self, _inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            Ok(HashMap::new())
+        }
+
+        fn execute_with_cancel(&self, _inputs: HashMap<PortId, DataValue>, cancel: &CancellationToken) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            while !cancel.is_cancelled() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            Err(NodeError::new("cancelled".to_string(), ERR_CANCELLED, None))
+        }
+
+        fn ports(&self) -> DynamicPorts { FailNode.ports() }
+    }
+
+    // 测试用节点：统计执行次数
+    struct CountingNode {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Node for CountingNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            UpperNode::new().execute(inputs)
+        }
+
+        fn ports(&self) -> DynamicPorts { UpperNode::new().ports() }
+    }
+
+    // 测试用节点：有副作用、不可缓存，统计执行次数
+    struct SideEffectNode {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Node for SideEffectNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            UpperNode::new().execute(inputs)
+        }
+
+        fn cacheable(&self) -> bool { false }
+
+        fn ports(&self) -> DynamicPorts { UpperNode::new().ports() }
+    }
+
+    // 测试用节点：等待一段时间后转为大写
+    struct SlowNode;
+
+    impl Node for SlowNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            UpperNode::new().execute(inputs)
+        }
+
+        fn ports(&self) -> DynamicPorts { UpperNode::new().ports() }
+    }
+
+    // 测试用节点：原样传递文本，并记录执行时模型a是否仍被固定
+    struct PinProbeNode {
+        pool: Arc<Mutex<VramPool>>,
+        seen: Arc<Mutex<Vec<bool>>>,
+    }
+
+    impl Node for PinProbeNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            let pinned = self.pool.lock().unwrap().slot_stats("a").map_or(false, |stats| stats.is_pinned());
+            self.seen.lock().unwrap().push(pinned);
+            let mut outputs = HashMap::new();
+            outputs.insert("out".to_string(), inputs.get("in").cloned().unwrap_or(DataValue::Text(String::new())));
+            Ok(outputs)
+        }
+
+        fn ports(&self) -> DynamicPorts { UpperNode::new().ports() }
+    }
+
+    // 测试用节点：稍等片刻后加载模型b，记录加载是否因显存不足失败
+    struct LoaderNode {
+        pool: Arc<Mutex<VramPool>>,
+        out_of_memory: Arc<AtomicBool>,
+    }
+
+    impl Node for LoaderNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            let params = LoadParams { n_gpu_layers: -1, ..LoadParams::default() };
+            let result = VramPool::load_shared(&self.pool, "b", std::path::Path::new("b"), params, &AtomicBool::new(false));
+            self.out_of_memory.store(matches!(result, Err(FfiError::OutOfMemory { .. })), Ordering::SeqCst);
+            UpperNode::new().execute(inputs)
+        }
+
+        fn ports(&self) -> DynamicPorts { UpperNode::new().ports() }
+    }
+
+    fn with_error_policy(mut node: NodeData, policy: serde_json::Value) -> NodeData {
+        node.config = Some(serde_json::json!({ "on_error": policy }));
+        node
+    }
+
+    fn run(workflow: &WorkflowData) -> Result<ExecutionResult, WorkflowError> {
+        run_with(WorkflowExecutor::new(mock_context()), workflow)
+    }
+
+    fn run_with(executor: WorkflowExecutor, workflow: &WorkflowData) -> Result<ExecutionResult, WorkflowError> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(executor.execute_workflow(workflow))
+    }
+
+    #[test]
+    fn test_data_flows_through_default_ports() {
+        // 未指定端口句柄的边使用默认端口
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "你好"),
+                create_node("2", "llm", "LLM"),
+                create_node("3", "output", "输出"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let result = run(&workflow).unwrap();
+        assert_eq!(
+            result.output("3", "output"),
+            Some(&DataValue::Text("[模型test响应] 你输入了: 你好".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_data_flows_through_explicit_handles() {
+        // 显式端口句柄
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "直连"),
+                create_node("2", "output", "输出"),
+            ],
+            edges: vec![create_edge("e1", "1", "2", Some(("text", "text")))],
+            parameters: Vec::new(),
+        };
+
+        let result = run(&workflow).unwrap();
+        assert_eq!(result.output("2", "output"), Some(&DataValue::Text("直连".to_string())));
+    }
+
+    #[test]
+    fn test_edge_types_checked_by_port() {
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "x"),
+                create_node("2", "if", "条件"),
+                create_node("3", "foreach", "循环"),
+                create_node("4", "output", "输出"),
+                create_node("5", "input", "y"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", Some(("text", "condition"))),
+                create_edge("e2", "1", "3", Some(("text", "list"))),
+                create_edge("e3", "1", "4", None),
+                create_edge("e4", "5", "4", Some(("text", "text"))),
+                create_edge("e5", "2", "5", Some(("true", "nope"))),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let executor = WorkflowExecutor::new(mock_context());
+        let report = executor.validate_edges(&workflow).unwrap();
+
+        // Text -> Boolean可转换，只给出建议
+        assert_eq!(report.conversions.len(), 1);
+        assert_eq!(report.conversions[0].edge_id, "e1");
+
+        let errors: Vec<(&str, &EdgeErrorKind)> = report.errors.iter().map(|e| (e.edge_id.as_str(), &e.kind)).collect();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0], ("e2", EdgeErrorKind::TypeMismatch { .. })));
+        assert!(matches!(errors[1], ("e4", EdgeErrorKind::FanInNotAllowed { .. })));
+        assert!(matches!(errors[2], ("e5", EdgeErrorKind::UnknownPort { .. })));
+    }
+
+    #[test]
+    fn test_validation_report_lists_all_issues() {
+        let mut with_model = create_node("2", "llm", "LLM");
+        with_model.config = Some(serde_json::json!({ "modelPath": "no/such/model.gguf" }));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "x"),
+                with_model,
+                create_node("3", "llm", "LLM"),
+                create_node("4", "llm", "LLM"),
+                create_node("5", "llm", "LLM"),
+                create_node("1", "input", "重复"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "3", "4", None),
+                create_edge("e3", "4", "3", None),
+                create_edge("e4", "1", "ghost", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let executor = WorkflowExecutor::new(mock_context());
+        let report = executor.validate_workflow(&workflow);
+        let find = |code: IssueCode| report.issues.iter().filter(move |issue| issue.code == code);
+
+        assert!(report.has_errors());
+        assert_eq!(find(IssueCode::DuplicateNodeId).next().unwrap().node_ids, vec!["1".to_string()]);
+        assert_eq!(find(IssueCode::DanglingEdge).next().unwrap().edge_ids, vec!["e4".to_string()]);
+        assert_eq!(find(IssueCode::MissingModelFile).next().unwrap().node_ids, vec!["2".to_string()]);
+        assert_eq!(find(IssueCode::UnconnectedInput).next().unwrap().node_ids, vec!["5".to_string()]);
+
+        let cycle = find(IssueCode::Cycle).next().unwrap();
+        assert_eq!(cycle.node_ids.len(), 2);
+        assert_eq!(cycle.edge_ids.len(), 2);
+
+        let mut unreachable: Vec<&str> = find(IssueCode::UnreachableNode).map(|issue| issue.node_ids[0].as_str()).collect();
+        unreachable.sort();
+        assert_eq!(unreachable, vec!["3", "4", "5"]);
+        assert!(find(IssueCode::UnreachableNode).all(|issue| issue.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn test_missing_source_port() {
+        // 上游不存在的端口应报告缺少输入
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "x"),
+                create_node("2", "output", "输出"),
+            ],
+            edges: vec![create_edge("e1", "1", "2", Some(("missing", "text")))],
+            parameters: Vec::new(),
+        };
+
+        assert!(matches!(
+            run(&workflow),
+            Err(WorkflowError::MissingInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_node_type() {
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "upper", "")],
+            edges: vec![],
+            parameters: Vec::new(),
+        };
+
+        assert!(matches!(run(&workflow), Err(WorkflowError::UnknownNodeType(t)) if t == "upper"));
+    }
+
+    #[test]
+    fn test_registered_custom_node() {
+        // 外部注册的节点类型无需修改执行器
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hello"),
+                create_node("2", "upper", ""),
+            ],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        };
+
+        let result = run_with(executor, &workflow).unwrap();
+        assert_eq!(result.output("2", "out"), Some(&DataValue::Text("HELLO".to_string())));
+    }
+
+    #[test]
+    fn test_node_states_after_success() {
+        let executor = WorkflowExecutor::new(mock_context());
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                create_node("2", "output", "输出"),
+            ],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+
+        let states = executor.node_states();
+        assert_eq!(states.len(), 2);
+        assert!(states.iter().all(|s| s.state == MainState::Completed));
+        assert_eq!(executor.run_state(), MainState::Completed);
+    }
+
+    #[test]
+    fn test_node_states_after_failure() {
+        // 失败节点为Error，下游节点被跳过
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("fail", |_| Ok(Box::new(FailNode)));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                create_node("2", "fail", ""),
+                create_node("3", "output", "输出"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow));
+        assert!(matches!(result, Err(WorkflowError::NodeFailed { ref node_id, .. }) if node_id == "2"));
+
+        let states = executor.node_states();
+        assert_eq!(states[0].status(), "Completed");
+        assert_eq!(states[1].status(), "Error");
+        assert_eq!(states[2].status(), "Skipped");
+        assert_eq!(executor.run_state().as_str(), "Error");
+    }
+
+    #[test]
+    fn test_outputs_keyed_by_node_and_port() {
+        // 两个节点的同名端口不会互相覆盖；没有输出节点和标记端口时取无下游的节点
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+        let mut workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "a"),
+                create_node("2", "input", "b"),
+                create_node("3", "upper", ""),
+                create_node("4", "upper", ""),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "3", None),
+                create_edge("e2", "2", "4", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.outputs.keys().collect::<Vec<_>>(), vec!["3", "4"]);
+        assert_eq!(result.output("3", "out"), Some(&DataValue::Text("A".to_string())));
+        assert_eq!(result.output("4", "out"), Some(&DataValue::Text("B".to_string())));
+
+        let statuses: Vec<(&str, &str)> = result.nodes.iter().map(|n| (n.node_id.as_str(), n.status.as_str())).collect();
+        assert_eq!(statuses, vec![("1", "Completed"), ("2", "Completed"), ("3", "Completed"), ("4", "Completed")]);
+        assert!(result.nodes.iter().all(|n| n.duration_ms.is_some()));
+
+        // 标记了expose_outputs的端口是唯一的输出
+        workflow.nodes[0].config = Some(serde_json::json!({ "expose_outputs": ["text"] }));
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.outputs.keys().collect::<Vec<_>>(), vec!["1"]);
+        assert_eq!(result.output("1", "text"), Some(&DataValue::Text("a".to_string())));
+    }
+
+    #[test]
+    fn test_events_published_during_run() {
+        let executor = WorkflowExecutor::new(mock_context());
+        let mut events = executor.subscribe();
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                create_node("2", "llm", "LLM"),
+                create_node("3", "output", "输出"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+
+        assert!(matches!(received.first(), Some(ExecutionEvent::RunStarted { run_id, .. }) if *run_id == result.run_id));
+        assert!(matches!(received.last(), Some(ExecutionEvent::RunFinished { status, error: None, .. }) if status == "Completed"));
+        for node_id in ["1", "2", "3"] {
+            let started = received.iter().position(|e| matches!(e, ExecutionEvent::NodeStarted { node_id: id, .. } if id == node_id));
+            let completed = received.iter().position(|e| matches!(e, ExecutionEvent::NodeCompleted { node_id: id, .. } if id == node_id));
+            assert!(started.unwrap() < completed.unwrap());
+        }
+        // 没有流式边时LLM的token也会发布
+        let tokens: String = received.iter()
+            .filter_map(|e| match e {
+                ExecutionEvent::TokenGenerated { node_id, token, .. } if node_id == "2" => Some(token.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tokens, "[模型test响应] 你输入了: hi");
+        // 只有输出节点的值作为工作流输出发布
+        let produced: Vec<_> = received.iter()
+            .filter_map(|e| match e {
+                ExecutionEvent::OutputProduced { node_id, port, value, .. } => Some((node_id.as_str(), port.as_str(), value.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(produced, vec![("3", "output", serde_json::json!("[模型test响应] 你输入了: hi"))]);
+    }
+
+    #[test]
+    fn test_failure_and_retry_events() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("flaky", |_| Ok(Box::new(FlakyNode { failures_left: AtomicUsize::new(5) })));
+        let mut events = executor.subscribe();
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "flaky", ""), serde_json::json!({ "action": "retry", "max_attempts": 2, "backoff_ms": 1 })),
+            ],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        assert!(runtime.block_on(executor.execute_workflow(&workflow)).is_err());
+        let mut failures = Vec::new();
+        let mut finished = None;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                ExecutionEvent::NodeFailed { node_id, will_retry, .. } => failures.push((node_id, will_retry)),
+                ExecutionEvent::RunFinished { status, .. } => finished = Some(status),
+                _ => {}
+            }
+        }
+        assert_eq!(failures, vec![("2".to_string(), true), ("2".to_string(), false)]);
+        assert_eq!(finished.as_deref(), Some("Error"));
+    }
+
+    #[test]
+    fn test_retry_recovers_flaky_node() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("flaky", |_| Ok(Box::new(FlakyNode { failures_left: AtomicUsize::new(2) })));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "flaky", ""), serde_json::json!({ "action": "retry", "max_attempts": 3, "backoff_ms": 1 })),
+            ],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.output("2", "out"), Some(&DataValue::Text("hi".to_string())));
+        assert_eq!(executor.node_states()[1].state, MainState::Completed);
+    }
+
+    #[test]
+    fn test_retry_exhausted() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("flaky", |_| Ok(Box::new(FlakyNode { failures_left: AtomicUsize::new(5) })));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "flaky", ""), serde_json::json!({ "action": "retry", "max_attempts": 2, "backoff_ms": 1 })),
+            ],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        assert!(runtime.block_on(executor.execute_workflow(&workflow)).is_err());
+        match &executor.node_states()[1].state {
+            MainState::Error(info) => assert_eq!(info.retry_count, 1),
+            other => panic!("Expected Error state, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lora_switch_failure_fails_node() {
+        // LoRA文件不存在：切换失败时节点报错，而不是输出success=false后继续运行
+        let mut lora = create_node("1", "lora_switch", "LoRA");
+        lora.config = Some(serde_json::json!({ "model_id": "a", "lora_path": "missing.safetensors" }));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![lora],
+            edges: Vec::new(),
+            parameters: Vec::new(),
+        };
+
+        let result = run(&workflow);
+        assert!(matches!(result, Err(WorkflowError::NodeFailed { ref node_id, .. }) if node_id == "1"));
+    }
+
+    #[test]
+    fn test_fallback_value_on_failure() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("fail", |_| Ok(Box::new(FailNode)));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "fail", ""), serde_json::json!({ "action": "fallback", "value": "默认" })),
+                create_node("3", "output", ""),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.output("3", "output"), Some(&DataValue::Text("默认".to_string())));
+    }
+
+    #[test]
+    fn test_fallback_value_converted_like_parameters() {
+        // fallback的value与参数、default_inputs使用同一转换：对象为Dict，null不能作为值
+        let node = with_error_policy(create_node("2", "fail", ""), serde_json::json!({
+            "action": "fallback",
+            "value": { "score": 1, "tags": ["a"] },
+        }));
+        let expected = DataValue::Dict(HashMap::from([
+            ("score".to_string(), DataValue::Number(1.0)),
+            ("tags".to_string(), DataValue::List(vec![DataValue::Text("a".to_string())])),
+        ]));
+        assert!(matches!(error_policy(&node), Ok(RecoveryAction::Fallback { value }) if value == expected));
+
+        let null = with_error_policy(create_node("2", "fail", ""), serde_json::json!({ "action": "fallback", "value": null }));
+        assert!(matches!(error_policy(&null), Err(WorkflowError::InvalidConfig { .. })));
+    }
+
+    #[test]
+    fn test_skip_node_and_dependents() {
+        // 跳过失败节点及其下游，独立分支照常执行
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("fail", |_| Ok(Box::new(FailNode)));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "fail", ""), serde_json::json!({ "action": "skip" })),
+                create_node("3", "output", ""),
+                create_node("4", "output", ""),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+                create_edge("e3", "1", "4", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.output("4", "output"), Some(&DataValue::Text("hi".to_string())));
+
+        let states = executor.node_states();
+        assert_eq!(states[1].status(), "Skipped");
+        assert_eq!(states[2].status(), "Skipped");
+        assert_eq!(states[3].status(), "Completed");
+    }
+
+    #[test]
+    fn test_invalid_error_policy() {
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![with_error_policy(create_node("1", "input", "hi"), serde_json::json!({ "action": "explode" }))],
+            edges: vec![],
+            parameters: Vec::new(),
+        };
+
+        assert!(matches!(run(&workflow), Err(WorkflowError::InvalidConfig { .. })));
+    }
+
+    #[test]
+    fn test_cancel_before_start() {
+        let executor = WorkflowExecutor::new(mock_context());
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "input", "hi")],
+            edges: vec![],
+            parameters: Vec::new(),
+        };
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow_with_cancel(&workflow, &cancel));
+        assert!(matches!(result, Err(WorkflowError::Cancelled)));
+        assert_eq!(executor.node_states()[0].state, MainState::Cancelled);
+        assert_eq!(executor.run_state(), MainState::Cancelled);
+    }
+
+    #[test]
+    fn test_cancel_during_retry_backoff() {
+        // 退避等待10秒，取消后立即结束，不等退避到期
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("fail", |_| Ok(Box::new(FailNode)));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "fail", ""), serde_json::json!({ "action": "retry", "max_attempts": 3, "backoff_ms": 10_000 })),
+            ],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        };
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            trigger.cancel();
+        });
+
+        let started = std::time::Instant::now();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow_with_cancel(&workflow, &cancel));
+        assert!(matches!(result, Err(WorkflowError::Cancelled)));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(executor.node_states()[1].state, MainState::Cancelled);
+    }
+
+    #[test]
+    fn test_retry_delay_capped() {
+        let backoff = std::time::Duration::from_millis(500);
+        assert_eq!(retry_delay(backoff, 1), backoff);
+        assert_eq!(retry_delay(backoff, 3), backoff * 4);
+        assert_eq!(retry_delay(backoff, 40), MAX_BACKOFF);
+        assert_eq!(retry_delay(std::time::Duration::from_secs(u64::MAX), 2), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_cancel_running_workflow() {
+        // 运行中的节点响应取消，下游节点保持未执行并标记为取消
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("wait", |_| Ok(Box::new(WaitForCancelNode)));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "wait", ""), serde_json::json!({ "action": "retry", "max_attempts": 5, "backoff_ms": 1 })),
+                create_node("3", "output", ""),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+            ],
+            parameters: Vec::new(),
+        };
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            trigger.cancel();
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow_with_cancel(&workflow, &cancel));
+        assert!(matches!(result, Err(WorkflowError::Cancelled)));
+
+        let states = executor.node_states();
+        assert_eq!(states[0].state, MainState::Completed);
+        assert_eq!(states[1].state, MainState::Cancelled);
+        assert_eq!(states[2].state, MainState::Cancelled);
+    }
+
+    #[test]
+    fn test_overlapping_runs_keep_own_states() {
+        // 同一执行器上重叠的两次运行各自记录节点状态，后开始的运行不覆盖前一次
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("slow", |_| Ok(Box::new(SlowNode)));
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+        let chain = |first: &str, second: &str, node_type: &str| WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node(first, "input", "hi"), create_node(second, node_type, "")],
+            edges: vec![create_edge("e1", first, second, None)],
+            parameters: Vec::new(),
+        };
+        let slow = chain("1", "2", "slow");
+        let fast = chain("3", "4", "upper");
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (slow_result, fast_result) = runtime.block_on(async {
+            tokio::join!(executor.execute_workflow(&slow), executor.execute_workflow(&fast))
+        });
+        let node_ids = |result: &ExecutionResult| {
+            let mut ids: Vec<String> = result.nodes.iter().map(|info| info.node_id.clone()).collect();
+            ids.sort();
+            ids
+        };
+        let slow_result = slow_result.unwrap();
+        let fast_result = fast_result.unwrap();
+        assert_eq!(node_ids(&slow_result), vec!["1", "2"]);
+        assert_eq!(node_ids(&fast_result), vec!["3", "4"]);
+        assert!(slow_result.nodes.iter().all(|info| info.status == "Completed"));
+        assert_eq!(slow_result.outputs["2"]["out"], DataValue::Text("HI".to_string()));
+
+        // 执行器的当前状态为最近开始的运行
+        let mut current: Vec<String> = executor.node_states().iter().map(|s| s.node_id.clone()).collect();
+        current.sort();
+        assert_eq!(current, vec!["3", "4"]);
+        assert_eq!(executor.run_state(), MainState::Completed);
+    }
+
+    // 1(input) -> 2(llm) -> 3(output)，100ms后取消
+    fn run_llm_cancelled(executor: &WorkflowExecutor, model_id: &str) -> Result<ExecutionResult, WorkflowError> {
+        let mut llm = create_node("2", "llm", "LLM");
+        llm.config = Some(serde_json::json!({ "model_id": model_id }));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "input", "请介绍一下你自己"), llm, create_node("3", "output", "")],
+            edges: vec![create_edge("e1", "1", "2", None), create_edge("e2", "2", "3", None)],
+            parameters: Vec::new(),
+        };
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            trigger.cancel();
+        });
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(executor.execute_workflow_with_cancel(&workflow, &cancel))
+    }
+
+    #[test]
+    fn test_cancel_during_model_load() {
+        // 加载完成时已取消：模型不放入池
+        let slow = MockModelSpec { load_delay: std::time::Duration::from_millis(300), ..MockModelSpec::new(1024 * 1024) };
+        let executor = WorkflowExecutor::new(mock_context_with(MockBackend::new().with_model("slow", slow)));
+
+        assert!(matches!(run_llm_cancelled(&executor, "slow"), Err(WorkflowError::Cancelled)));
+        assert_eq!(executor.node_states()[1].state, MainState::Cancelled);
+        assert!(executor.slot_status().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_during_generation() {
+        // 生成循环响应取消：已产出的token照常发布，节点标记为取消，模型留在池中
+        let chatty = MockModelSpec { token_delay: std::time::Duration::from_millis(20), ..MockModelSpec::new(1024 * 1024) };
+        let executor = WorkflowExecutor::new(mock_context_with(MockBackend::new().with_model("chatty", chatty)));
+        let mut events = executor.subscribe();
+
+        assert!(matches!(run_llm_cancelled(&executor, "chatty"), Err(WorkflowError::Cancelled)));
+        let states = executor.node_states();
+        assert_eq!(states[1].state, MainState::Cancelled);
+        assert_eq!(states[2].state, MainState::Cancelled);
+        let mut tokens = 0;
+        while let Ok(event) = events.try_recv() {
+            if matches!(event, ExecutionEvent::TokenGenerated { ref node_id, .. } if node_id == "2") {
+                tokens += 1;
+            }
+        }
+        // 完整响应有二十多个token
+        assert!(tokens > 0 && tokens < 20);
+        assert_eq!(executor.slot_status()[0].tier, Tier::Gpu);
+    }
+
+    // 1(input) -> 2(if) -true-> 3(upper) -> 5(merge)
+    //                  -false-> 4(fail)  -> 5(merge)
+    fn branch_workflow(condition: &str) -> WorkflowData {
+        WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", condition),
+                create_node("2", "if", ""),
+                create_node("3", "upper", ""),
+                create_node("4", "fail", ""),
+                create_node("5", "merge", ""),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", Some(("text", "condition"))),
+                create_edge("e2", "2", "3", Some(("true", "in"))),
+                create_edge("e3", "2", "4", Some(("false", "in"))),
+                create_edge("e4", "3", "5", Some(("out", "in1"))),
+                create_edge("e5", "4", "5", Some(("out", "in2"))),
+            ],
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_if_skips_dead_branch() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+        executor.registry_mut().register("fail", |_| Ok(Box::new(FailNode)));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let result = runtime.block_on(executor.execute_workflow(&branch_workflow("yes"))).unwrap();
+        assert_eq!(result.output("5", "out"), Some(&DataValue::Text("YES".to_string())));
+
+        let states = executor.node_states();
+        assert_eq!(states[3].status(), "Skipped");
+        assert_eq!(states[4].state, MainState::Completed);
+
+        let history = executor.node_history("2");
+        assert!(history.contains(&MainState::Running(RunningSubState::Branching {
+            condition: true,
+            selected_branch: "e2".to_string(),
+        })));
+    }
+
+    #[test]
+    fn test_if_false_branch_runs() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+        executor.registry_mut().register("fail", |_| Ok(Box::new(FailNode)));
+
+        // 走false分支时执行失败节点
+        assert!(matches!(
+            run_with(executor, &branch_workflow("no")),
+            Err(WorkflowError::NodeFailed { node_id, .. }) if node_id == "4"
+        ));
+    }
+
+    // 测试用节点：按逗号拆分文本为列表
+    struct SplitNode;
+
+    impl Node for SplitNode {
+        fn execute(&self, inputs: HashMap<PortId, DataValue>) -> Result<HashMap<PortId, DataValue>, NodeError> {
+            let text = inputs.get("in").and_then(|v| v.as_text()).unwrap_or("");
+            let items = text.split(',').map(|s| DataValue::Text(s.to_string())).collect();
+            let mut outputs = HashMap::new();
+            outputs.insert("out".to_string(), DataValue::List(items));
+            Ok(outputs)
+        }
+
+        fn ports(&self) -> DynamicPorts {
+            let mut ports = DynamicPorts::new();
+            ports.add_input(Port { id: "in".to_string(), data_type: DataType::Text, multiple: false });
+            ports.add_output(Port { id: "out".to_string(), data_type: DataType::List(Box::new(DataType::Text)), multiple: false });
+            ports
+        }
+    }
+
+    fn in_loop(mut node: NodeData, loop_id: &str) -> NodeData {
+        node.parent_id = Some(loop_id.to_string());
+        node
+    }
+
+    #[test]
+    fn test_foreach_maps_list() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("split", |_| Ok(Box::new(SplitNode)));
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+
+        // 3(foreach)的循环体为4(upper)：item -> in，out -> result
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "a,b"),
+                create_node("2", "split", ""),
+                create_node("3", "foreach", ""),
+                in_loop(create_node("4", "upper", ""), "3"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+                create_edge("e3", "3", "4", Some(("item", "in"))),
+                create_edge("e4", "4", "3", Some(("out", "result"))),
+            ],
+            parameters: Vec::new(),
+        };
+        assert!(detect_workflow_cycles(&workflow).is_ok());
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(
+            result.output("3", "results"),
+            Some(&DataValue::List(vec![DataValue::Text("A".to_string()), DataValue::Text("B".to_string())]))
+        );
+        assert!(executor.node_history("3").contains(&MainState::Running(RunningSubState::Iterating { current: 1, total: Some(2) })));
+    }
+
+    #[test]
+    fn test_while_runs_until_max_iterations() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+
+        let mut while_node = create_node("2", "while", "");
+        while_node.config = Some(serde_json::json!({ "max_iterations": 3 }));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "a"),
+                while_node,
+                in_loop(create_node("3", "upper", ""), "2"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", None),
+                create_edge("e3", "3", "2", None),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let result = run_with(executor, &workflow).unwrap();
+        assert_eq!(result.output("2", "value"), Some(&DataValue::Text("A".to_string())));
+        assert_eq!(result.output("2", "iterations"), Some(&DataValue::Number(3.0)));
+    }
+
+    #[test]
+    fn test_edge_across_loop_boundary_rejected() {
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "a"),
+                create_node("2", "foreach", ""),
+                in_loop(create_node("3", "output", ""), "2"),
+            ],
+            edges: vec![create_edge("e1", "1", "3", None)],
+            parameters: Vec::new(),
+        };
+
+        assert!(matches!(run(&workflow), Err(WorkflowError::InvalidScope(_))));
+    }
+
+    fn subgraph_node(id: &str, config: serde_json::Value) -> NodeData {
+        let mut node = create_node(id, "subgraph", "");
+        node.config = Some(config);
+        node
+    }
+
+    #[test]
+    fn test_inline_subgraph_expanded() {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.registry_mut().register("upper", |_| Ok(Box::new(UpperNode::new())));
+
+        // 子图内u1 -> u2，暴露u1.in和u2.out
+        let inner = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("u1", "upper", ""), create_node("u2", "upper", "")],
+            edges: vec![create_edge("e1", "u1", "u2", None)],
+            parameters: Vec::new(),
+        };
+        let sub = subgraph_node("2", serde_json::json!({ "workflow": inner }));
+        let ports = executor.subgraph_ports(&sub).unwrap();
+        assert!(ports.get_input("u1.in").is_some());
+        assert!(ports.get_output("u2.out").is_some());
+        assert!(ports.get_output("u1.out").is_none());
+
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "input", "hi"), sub, create_node("3", "output", "")],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", Some(("u2.out", "text"))),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.output("3", "output"), Some(&DataValue::Text("HI".to_string())));
+
+        let ids: Vec<String> = executor.node_states().into_iter().map(|s| s.node_id).collect();
+        assert!(ids.contains(&"2/u1".to_string()));
+        assert!(ids.contains(&"2/u2".to_string()));
+    }
+
+    #[test]
+    fn test_recursive_subgraph_rejected() {
+        let dir = std::env::temp_dir().join(format!("microflow-subgraph-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let looped = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![subgraph_node("self", serde_json::json!({ "path": "self.mflow" }))],
+            edges: vec![],
+            parameters: Vec::new(),
+        };
+        std::fs::write(dir.join("self.mflow"), looped.to_json()).unwrap();
+
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.set_subgraph_dir(&dir);
+        let result = run_with(executor, &looped);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(matches!(result, Err(WorkflowError::RecursiveSubgraph(_))));
+    }
+
+    fn counting_executor(calls: &Arc<AtomicUsize>) -> WorkflowExecutor {
+        let mut executor = WorkflowExecutor::new(mock_context());
+        let calls = Arc::clone(calls);
+        executor.registry_mut().register("count", move |_| Ok(Box::new(CountingNode { calls: Arc::clone(&calls) })));
+        executor
+    }
+
+    fn counting_workflow(text: &str, count_config: serde_json::Value) -> WorkflowData {
+        let mut count_node = create_node("2", "count", "");
+        count_node.config = Some(count_config);
+        WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "input", text), count_node],
+            edges: vec![create_edge("e1", "1", "2", None)],
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged_nodes_reuse_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let executor = counting_executor(&calls);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let first = runtime.block_on(executor.execute_workflow(&counting_workflow("a", serde_json::json!({})))).unwrap();
+        assert!(first.cached_nodes.is_empty());
+
+        let second = runtime.block_on(executor.execute_workflow(&counting_workflow("a", serde_json::json!({})))).unwrap();
+        assert_eq!(second.cached_nodes, vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(second.output("2", "out"), Some(&DataValue::Text("A".to_string())));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 上游输入变化，下游重新执行
+        runtime.block_on(executor.execute_workflow(&counting_workflow("b", serde_json::json!({})))).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_always_rerun_bypasses_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let executor = counting_executor(&calls);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let workflow = counting_workflow("a", serde_json::json!({ "always_rerun": true }));
+
+        runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_uncacheable_node_bypasses_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut executor = WorkflowExecutor::new(mock_context());
+        let node_calls = Arc::clone(&calls);
+        executor.registry_mut().register("count", move |_| Ok(Box::new(SideEffectNode { calls: Arc::clone(&node_calls) })));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let workflow = counting_workflow("a", serde_json::json!({}));
+
+        runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        let second = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(second.cached_nodes, vec!["1".to_string()]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_edited_data_invalidates_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let executor = counting_executor(&calls);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let workflow = counting_workflow("a", serde_json::json!({}));
+        runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+
+        // 只修改画布上输入节点的data.label，类型和config不变
+        let mut edited = workflow.clone();
+        edited.nodes[0].data = serde_json::json!({ "label": "b" });
+        let result = runtime.block_on(executor.execute_workflow(&edited)).unwrap();
+        assert!(result.cached_nodes.is_empty());
+        assert_eq!(result.output("2", "out"), Some(&DataValue::Text("B".to_string())));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_resume_skips_completed_nodes() {
+        let journal_dir = std::env::temp_dir().join(format!("microflow-journal-{}", std::process::id()));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut executor = counting_executor(&calls);
+        executor.set_journal_dir(&journal_dir);
+        // 只有第一次创建的实例会失败
+        let failures = Arc::new(AtomicUsize::new(1));
+        executor.registry_mut().register("flaky", move |_| {
+            Ok(Box::new(FlakyNode { failures_left: AtomicUsize::new(failures.swap(0, Ordering::SeqCst)) }))
+        });
+
+        let mut workflow = counting_workflow("a", serde_json::json!({}));
+        workflow.nodes.push(create_node("3", "flaky", ""));
+        workflow.edges.push(create_edge("e2", "2", "3", None));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        assert!(runtime.block_on(executor.execute_workflow(&workflow)).is_err());
+        let run_id = executor.last_run_id().unwrap();
+
+        // 编辑过的工作流不能按原日志恢复
+        let mut edited = workflow.clone();
+        edited.nodes[0].data = serde_json::json!({ "label": "b" });
+        let rejected = runtime.block_on(executor.resume_workflow(&edited, &run_id));
+        let resumed = runtime.block_on(executor.resume_workflow(&workflow, &run_id)).unwrap();
+        let _ = std::fs::remove_dir_all(&journal_dir);
+
+        assert!(matches!(rejected, Err(WorkflowError::Journal(_))));
+        assert_eq!(resumed.run_id, run_id);
+        assert_eq!(resumed.resumed_nodes, vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(resumed.output("3", "out"), Some(&DataValue::Text("A".to_string())));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_llm_streams_tokens_to_output() {
+        // 生成文本超过通道容量，且并发上限为1：消费者必须与生产者同时运行，否则死锁
+        let prompt = "流".repeat(100);
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", &prompt),
+                create_node("2", "llm", "LLM"),
+                create_node("3", "output", "输出"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", Some(("stream", "stream"))),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let executor = WorkflowExecutor::with_config(mock_context(), SchedulerConfig { max_concurrency: 1 });
+        let mut events = executor.subscribe();
+        let result = run_with(executor, &workflow).unwrap();
+        let expected = format!("[模型test响应] 你输入了: {}", prompt);
+        assert_eq!(result.output("3", "output"), Some(&DataValue::Text(expected.clone())));
+
+        // 输出节点读取的块同样作为事件发布
+        let mut received = String::new();
+        while let Ok(event) = events.try_recv() {
+            if let ExecutionEvent::TokenGenerated { node_id, port, token, .. } = event {
+                if node_id == "3" && port == "stream" {
+                    received.push_str(&token);
+                }
+            }
+        }
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_llm_generates_with_pool_model() {
+        // LLM节点从VRAM池加载模型并用它生成；后端的生成错误使节点失败
+        let backend = MockBackend::new()
+            .with_model("base", MockModelSpec::new(1024 * 1024))
+            .with_model("mute", MockModelSpec { fail_generate: true, ..MockModelSpec::new(1024 * 1024) });
+        let chain = |model_id: &str| {
+            let mut llm = create_node("2", "llm", "LLM");
+            llm.config = Some(serde_json::json!({ "model_id": model_id }));
+            WorkflowData {
+                version: "1.0".to_string(),
+                nodes: vec![create_node("1", "input", "你好"), llm, create_node("3", "output", "输出")],
+                edges: vec![create_edge("e1", "1", "2", None), create_edge("e2", "2", "3", None)],
+                parameters: Vec::new(),
+            }
+        };
+        let executor = WorkflowExecutor::new(mock_context_with(backend));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let result = runtime.block_on(executor.execute_workflow(&chain("base"))).unwrap();
+        assert_eq!(result.output("3", "output"), Some(&DataValue::Text("[模型base响应] 你输入了: 你好".to_string())));
+        let status = executor.slot_status();
+        assert_eq!((status[0].model_id.as_str(), status[0].tier), ("base", Tier::Gpu));
+
+        let failed = runtime.block_on(executor.execute_workflow(&chain("mute")));
+        assert!(matches!(failed, Err(WorkflowError::NodeFailed { ref node_id, .. }) if node_id == "2"));
+        let missing = runtime.block_on(executor.execute_workflow(&chain("missing")));
+        assert!(matches!(missing, Err(WorkflowError::NodeFailed { ref node_id, .. }) if node_id == "2"));
+    }
+
+    #[test]
+    fn test_model_unpinned_when_its_node_finishes() {
+        // 预算10MiB、预留2MiB，两个6MiB的模型不能同时在显存中：a的节点结束后立即解除固定，b加载时可以淘汰a
+        const MIB: usize = 1024 * 1024;
+        let backend = MockBackend::new()
+            .with_model("a", MockModelSpec::new(6 * MIB))
+            .with_model("b", MockModelSpec::new(6 * MIB));
+        let pool = Arc::new(Mutex::new(VramPool::new(10 * MIB, 2 * MIB).with_backend(Arc::new(backend))));
+        let mut executor = WorkflowExecutor::new(ExecutionContext::with_pool(Arc::clone(&pool)));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (probe_pool, probe_seen) = (Arc::clone(&pool), Arc::clone(&seen));
+        executor.registry_mut().register("probe", move |_| {
+            Ok(Box::new(PinProbeNode { pool: Arc::clone(&probe_pool), seen: Arc::clone(&probe_seen) }))
+        });
+        let llm = |id: &str, model_id: &str| {
+            let mut node = create_node(id, "llm", "LLM");
+            node.config = Some(serde_json::json!({ "model_id": model_id }));
+            node
+        };
+        // 1(input) -> 2(llm a) -> 3(probe) -> 4(llm b)
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "input", "你好"), llm("2", "a"), create_node("3", "probe", ""), llm("4", "b")],
+            edges: vec![create_edge("e1", "1", "2", None), create_edge("e2", "2", "3", None), create_edge("e3", "3", "4", None)],
+            parameters: Vec::new(),
+        };
+
+        // 第二次运行时a已驻留，派发时被固定；清空输出缓存使节点重新执行
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        for _ in 0..2 {
+            executor.clear_cache();
+            let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+            assert_eq!(
+                result.output("4", "response"),
+                Some(&DataValue::Text("[模型b响应] 你输入了: [模型a响应] 你输入了: 你好".to_string()))
+            );
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![false, false]);
+        let status = executor.slot_status();
+        assert_eq!((status[0].tier, status[1].tier), (Tier::Cpu, Tier::Gpu));
+        assert!(status.iter().all(|slot| !slot.pinned));
+    }
+
+    #[test]
+    fn test_generating_model_not_evicted_by_concurrent_load() {
+        // 只放得下一个6MiB模型：a首次使用时才加载，生成期间另一节点加载b，不能淘汰a
+        const MIB: usize = 1024 * 1024;
+        let mut slow = MockModelSpec::new(6 * MIB);
+        slow.token_delay = std::time::Duration::from_millis(20);
+        let backend = MockBackend::new()
+            .with_model("a", slow)
+            .with_model("b", MockModelSpec::new(6 * MIB));
+        let pool = Arc::new(Mutex::new(VramPool::new(10 * MIB, 2 * MIB).with_backend(Arc::new(backend))));
+        let mut executor = WorkflowExecutor::new(ExecutionContext::with_pool(Arc::clone(&pool)));
+        let out_of_memory = Arc::new(AtomicBool::new(false));
+        let (loader_pool, loader_flag) = (Arc::clone(&pool), Arc::clone(&out_of_memory));
+        executor.registry_mut().register("loader", move |_| {
+            Ok(Box::new(LoaderNode { pool: Arc::clone(&loader_pool), out_of_memory: Arc::clone(&loader_flag) }))
+        });
+        let mut llm = create_node("2", "llm", "LLM");
+        llm.config = Some(serde_json::json!({ "model_id": "a" }));
+        // 1(input) -> 2(llm a)，1 -> 3(loader)
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("1", "input", "你好"), llm, create_node("3", "loader", "")],
+            edges: vec![create_edge("e1", "1", "2", None), create_edge("e2", "1", "3", None)],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow)).unwrap();
+        assert_eq!(result.output("2", "response"), Some(&DataValue::Text("[模型a响应] 你输入了: 你好".to_string())));
+        assert!(out_of_memory.load(Ordering::SeqCst));
+        let status = executor.slot_status();
+        assert_eq!(status.len(), 1);
+        assert_eq!((status[0].model_id.as_str(), status[0].tier), ("a", Tier::Gpu));
+    }
+
+    #[test]
+    fn test_stream_producer_not_retried() {
+        // 消费者已处理了部分块，流式输出的生产者失败后不按重试策略重新执行
+        let failing = MockModelSpec { fail_generate: true, ..MockModelSpec::new(1024 * 1024) };
+        let executor = WorkflowExecutor::new(mock_context_with(MockBackend::new().with_default_model(failing)));
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                with_error_policy(create_node("2", "llm", "LLM"), serde_json::json!({ "action": "retry", "max_attempts": 3, "backoff_ms": 1 })),
+                create_node("3", "output", "输出"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", Some(("stream", "stream"))),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow(&workflow));
+        assert!(matches!(result, Err(WorkflowError::NodeFailed { ref node_id, .. }) if node_id == "2"));
+        match &executor.node_states()[1].state {
+            MainState::Error(info) => assert_eq!(info.retry_count, 0),
+            other => panic!("Expected Error state, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stream_and_value_edge_to_same_node_rejected() {
+        // 输出节点既读流又等LLM完成：生产者写满通道后会永远阻塞
+        let workflow = WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![
+                create_node("1", "input", "hi"),
+                create_node("2", "llm", "LLM"),
+                create_node("3", "output", "输出"),
+            ],
+            edges: vec![
+                create_edge("e1", "1", "2", None),
+                create_edge("e2", "2", "3", Some(("stream", "stream"))),
+                create_edge("e3", "2", "3", Some(("response", "text"))),
+            ],
+            parameters: Vec::new(),
+        };
+
+        let executor = WorkflowExecutor::new(mock_context());
+        let report = executor.validate_workflow(&workflow);
+        let issue = report.errors().find(|issue| issue.code == IssueCode::InvalidEdge).unwrap();
+        assert_eq!(issue.edge_ids, vec!["e3".to_string()]);
+        assert!(matches!(
+            run(&workflow),
+            Err(WorkflowError::InvalidEdge(EdgeError { kind: EdgeErrorKind::StreamWithValueEdge { .. }, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_tier_change_event_json() {
+        // 前端按type和小写的层名解析
+        let event = ExecutionEvent::ModelTierChanged {
+            model_id: "base".to_string(),
+            from: Tier::Gpu,
+            to: Tier::Cpu,
+            duration_ms: 120,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "type": "ModelTierChanged", "model_id": "base", "from": "gpu", "to": "cpu", "duration_ms": 120 })
+        );
+        
+        // 未加载过模型时没有任何槽位
+        let executor = WorkflowExecutor::new(mock_context());
+        assert!(executor.slot_status().is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use super::{WorkflowExecutor, WorkflowError, HistoryStore, ParameterValues, diff_runs};
+use super::test_support::{parameterized_workflow, mock_context};
+use crate::engine::CancellationToken;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(value: &str) -> ParameterValues {
+        [("prompt".to_string(), serde_json::json!(value))].into_iter().collect()
+    }
+
+    fn history_executor(name: &str) -> (WorkflowExecutor, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("microflow-history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.set_history_dir(&dir);
+        (executor, dir)
+    }
+
+    #[test]
+    fn test_runs_recorded_with_parameters_and_node_records() {
+        let (executor, dir) = history_executor("record");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(executor.execute_workflow_with_params(
+            &parameterized_workflow(),
+            &prompt("你好"),
+            &CancellationToken::new(),
+        )).unwrap();
+
+        let store = executor.history().unwrap();
+        let runs = store.list().unwrap();
+        let record = store.get(&result.run_id).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].run_id, result.run_id);
+        assert_eq!(record.status, "Completed");
+        assert_eq!(record.parameters.get("prompt"), Some(&serde_json::json!("你好")));
+        assert_eq!(record.parameters.get("model"), Some(&serde_json::json!("base")));
+        assert!(record.workflow.parameters.is_empty());
+        assert_eq!(record.models.len(), 1);
+        assert_eq!(record.models[0].node_id, "2");
+
+        let llm = record.nodes.iter().find(|node| node.node_id == "2").unwrap();
+        assert_eq!(llm.node_type, "llm");
+        assert_eq!(llm.inputs.get("prompt"), Some(&serde_json::json!("问题: 你好")));
+        assert!(llm.duration_ms.is_some());
+        assert_eq!(
+            record.outputs.get("3").and_then(|outputs| outputs.get("output")),
+            Some(&serde_json::json!("[模型base响应] 你输入了: 问题: 你好"))
+        );
+    }
+
+    #[test]
+    fn test_diff_shows_changed_parameters_and_outputs() {
+        let (executor, dir) = history_executor("diff");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let workflow = parameterized_workflow();
+        let first = runtime.block_on(executor.execute_workflow_with_params(&workflow, &prompt("a"), &CancellationToken::new())).unwrap();
+        let second = runtime.block_on(executor.execute_workflow_with_params(&workflow, &prompt("b"), &CancellationToken::new())).unwrap();
+
+        let store = executor.history().unwrap();
+        let diff = diff_runs(&store.get(&first.run_id).unwrap(), &store.get(&second.run_id).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 同一工作流只有参数不同
+        assert!(!diff.workflow_changed);
+        assert!(diff.models.is_empty());
+        assert_eq!(diff.parameters.len(), 1);
+        assert_eq!(diff.parameters[0].key, "prompt");
+        assert_eq!(diff.outputs.len(), 1);
+        assert_eq!(diff.outputs[0].key, "3.output");
+    }
+
+    #[test]
+    fn test_replay_reruns_recorded_workflow() {
+        let (executor, dir) = history_executor("replay");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let original = runtime.block_on(executor.execute_workflow_with_params(
+            &parameterized_workflow(),
+            &prompt("再来一次"),
+            &CancellationToken::new(),
+        )).unwrap();
+        let replayed = runtime.block_on(executor.replay_run(&original.run_id, &CancellationToken::new())).unwrap();
+
+        let store = executor.history().unwrap();
+        let record = store.get(&replayed.run_id).unwrap();
+        let diff = diff_runs(&store.get(&original.run_id).unwrap(), &record);
+        let missing = runtime.block_on(executor.replay_run("missing", &CancellationToken::new()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_ne!(replayed.run_id, original.run_id);
+        assert_eq!(replayed.outputs, original.outputs);
+        // 重放不使用输出缓存
+        assert!(replayed.cached_nodes.is_empty());
+        assert_eq!(record.replay_of.as_deref(), Some(original.run_id.as_str()));
+        assert!(diff.is_empty());
+        assert!(matches!(missing, Err(WorkflowError::History(_))));
+    }
+
+    #[test]
+    fn test_corrupt_history_reported() {
+        let (executor, dir) = history_executor("corrupt");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let run = runtime.block_on(executor.execute_workflow_with_params(&parameterized_workflow(), &prompt("a"), &CancellationToken::new())).unwrap();
+        let path = dir.join("runs.jsonl");
+        let valid = std::fs::read_to_string(&path).unwrap();
+
+        // 写入中途崩溃留下的末行被忽略
+        std::fs::write(&path, format!("{}{{\"run_id\":", valid)).unwrap();
+        let truncated = HistoryStore::new(&dir).list();
+        // 中间损坏的行返回错误
+        std::fs::write(&path, format!("{{\"run_id\":\n{}", valid)).unwrap();
+        let corrupt = HistoryStore::new(&dir).list();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(truncated.unwrap().iter().map(|run| run.run_id.clone()).collect::<Vec<_>>(), vec![run.run_id]);
+        assert!(matches!(corrupt, Err(WorkflowError::History(message)) if message.contains("第1行")));
+    }
+}
@@ -0,0 +1,144 @@
+use super::{WorkflowExecutor, WorkflowError, WorkflowData, ParameterValues, BatchRecord, apply_parameters, read_parameter_sets};
+use super::test_support::{create_node, create_edge, parameter, parameterized_workflow, mock_context};
+use crate::engine::CancellationToken;
+use crate::types::{DataType, DataValue};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(pairs: &[(&str, serde_json::Value)]) -> ParameterValues {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+    }
+
+    #[test]
+    fn test_parameters_substituted_with_defaults_and_overrides() {
+        let workflow = parameterized_workflow();
+        let executor = WorkflowExecutor::new(mock_context());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let result = runtime.block_on(executor.execute_workflow_with_params(
+            &workflow,
+            &overrides(&[("prompt", serde_json::json!("你好"))]),
+            &CancellationToken::new(),
+        )).unwrap();
+        assert_eq!(
+            result.output("3", "output"),
+            Some(&DataValue::Text("[模型base响应] 你输入了: 问题: 你好".to_string()))
+        );
+
+        let result = runtime.block_on(executor.execute_workflow_with_params(
+            &workflow,
+            &overrides(&[("prompt", serde_json::json!("你好")), ("model", serde_json::json!("lora-a"))]),
+            &CancellationToken::new(),
+        )).unwrap();
+        assert_eq!(
+            result.output("3", "output"),
+            Some(&DataValue::Text("[模型lora-a响应] 你输入了: 问题: 你好".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_single_reference_keeps_parameter_type() {
+        let mut workflow = parameterized_workflow();
+        workflow.parameters.push(parameter("temperature", DataType::Number, Some(serde_json::json!(0.7))));
+        workflow.nodes[1].config = Some(serde_json::json!({ "model_id": "{{model}}", "temperature": "{{temperature}}" }));
+
+        // CSV中的值是文本，按声明的类型转换
+        let resolved = apply_parameters(&workflow, &overrides(&[("prompt", serde_json::json!("x")), ("temperature", serde_json::json!("0.2"))])).unwrap();
+        assert_eq!(resolved.nodes[1].config, Some(serde_json::json!({ "model_id": "base", "temperature": 0.2 })));
+    }
+
+    #[test]
+    fn test_invalid_parameters_rejected() {
+        let workflow = parameterized_workflow();
+
+        // 缺少没有默认值的参数
+        assert!(matches!(apply_parameters(&workflow, &ParameterValues::new()), Err(WorkflowError::InvalidParameter(_))));
+        // 覆盖未声明的参数
+        assert!(matches!(
+            apply_parameters(&workflow, &overrides(&[("prompt", serde_json::json!("x")), ("seed", serde_json::json!(1))])),
+            Err(WorkflowError::InvalidParameter(_))
+        ));
+        // 引用未声明的参数
+        let mut undeclared = workflow.clone();
+        undeclared.nodes[0].data = serde_json::json!({ "label": "{{missing}}" });
+        assert!(matches!(
+            apply_parameters(&undeclared, &overrides(&[("prompt", serde_json::json!("x"))])),
+            Err(WorkflowError::InvalidConfig { node_id, .. }) if node_id == "1"
+        ));
+    }
+
+    #[test]
+    fn test_subgraph_parameters_substituted_and_overridable() {
+        // 子图文件声明greeting参数，内部输入节点引用它
+        let dir = std::env::temp_dir().join(format!("microflow-subgraph-params-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let inner = WorkflowData {
+            version: "1.3".to_string(),
+            nodes: vec![create_node("i", "input", "{{greeting}}, 世界", "")],
+            edges: Vec::new(),
+            parameters: vec![parameter("greeting", DataType::Text, Some(serde_json::json!("你好")))],
+        };
+        std::fs::write(dir.join("greet.mflow"), serde_json::to_string(&inner).unwrap()).unwrap();
+
+        let mut sub = create_node("2", "subgraph", "", "");
+        sub.config = Some(serde_json::json!({ "path": "greet.mflow" }));
+        let mut edge = create_edge("e1", "2", "3");
+        edge.source_handle = Some("i.text".to_string());
+        edge.target_handle = Some("text".to_string());
+        let workflow = WorkflowData {
+            version: "1.3".to_string(),
+            nodes: vec![sub, create_node("3", "output", "输出", "")],
+            edges: vec![edge],
+            parameters: Vec::new(),
+        };
+
+        let mut executor = WorkflowExecutor::new(mock_context());
+        executor.set_subgraph_dir(&dir);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let run = |values: ParameterValues| runtime.block_on(executor.execute_workflow_with_params(&workflow, &values, &CancellationToken::new()));
+        let defaults = run(ParameterValues::new());
+        let overridden = run(overrides(&[("greeting", serde_json::json!("再见"))]));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(defaults.unwrap().output("3", "output"), Some(&DataValue::Text("你好, 世界".to_string())));
+        assert_eq!(overridden.unwrap().output("3", "output"), Some(&DataValue::Text("再见, 世界".to_string())));
+    }
+
+    #[test]
+    fn test_batch_runs_each_csv_row() {
+        let dir = std::env::temp_dir().join(format!("microflow-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let params_path = dir.join("params.csv");
+        let results_path = dir.join("results.jsonl");
+        std::fs::write(&params_path, "prompt,model\n你好,base\n\"a, \"\"b\"\"\",lora-a\n").unwrap();
+
+        let parameter_sets = read_parameter_sets(&params_path).unwrap();
+        assert_eq!(parameter_sets.len(), 2);
+        assert_eq!(parameter_sets[1].get("prompt"), Some(&serde_json::json!("a, \"b\"")));
+
+        let executor = WorkflowExecutor::new(mock_context());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let summary = runtime.block_on(executor.run_batch(
+            &parameterized_workflow(),
+            &parameter_sets,
+            &results_path,
+            &CancellationToken::new(),
+        )).unwrap();
+
+        let records: Vec<BatchRecord> = std::fs::read_to_string(&results_path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!((summary.total, summary.succeeded, summary.failed), (2, 2, 0));
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].index, 1);
+        assert_eq!(
+            records[1].outputs.get("3").and_then(|outputs| outputs.get("output")),
+            Some(&serde_json::json!("[模型lora-a响应] 你输入了: 问题: a, \"b\""))
+        );
+    }
+}
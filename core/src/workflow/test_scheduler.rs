@@ -0,0 +1,168 @@
+use super::scheduler::{Scheduler, SchedulerConfig};
+use super::{WorkflowData, NodeData, EdgeData, Position};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_node(id: &str) -> NodeData {
+        NodeData {
+            id: id.to_string(),
+            type: "llm".to_string(),
+            position: Position { x: 0.0, y: 0.0 },
+            data: serde_json::json!({}),
+            config: None,
+            default_inputs: None,
+            input_types: None,
+            parent_id: None,
+        }
+    }
+
+    fn create_edge(source: &str, target: &str) -> EdgeData {
+        EdgeData {
+            id: format!("{}-{}", source, target),
+            source: source.to_string(),
+            target: target.to_string(),
+            source_handle: None,
+            target_handle: None,
+            animated: false,
+        }
+    }
+
+    // a -> c, b -> c
+    fn create_workflow() -> WorkflowData {
+        WorkflowData {
+            version: "1.0".to_string(),
+            nodes: vec![create_node("a"), create_node("b"), create_node("c")],
+            edges: vec![create_edge("a", "c"), create_edge("b", "c")],
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_independent_branches_run_together() {
+        let workflow = create_workflow();
+        let mut scheduler = Scheduler::new(&workflow, HashMap::new(), 2, SchedulerConfig::default());
+
+        assert_eq!(scheduler.next_ready(), Some("a".to_string()));
+        assert_eq!(scheduler.next_ready(), Some("b".to_string()));
+        assert_eq!(scheduler.next_ready(), None); // c等待依赖
+        assert_eq!(scheduler.active_tasks(), 2);
+
+        scheduler.complete("a");
+        assert_eq!(scheduler.next_ready(), None);
+        scheduler.complete("b");
+        assert_eq!(scheduler.next_ready(), Some("c".to_string()));
+        scheduler.complete("c");
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let workflow = create_workflow();
+        let config = SchedulerConfig { max_concurrency: 1 };
+        let mut scheduler = Scheduler::new(&workflow, HashMap::new(), 2, config);
+
+        assert_eq!(scheduler.next_ready(), Some("a".to_string()));
+        assert_eq!(scheduler.next_ready(), None);
+        scheduler.complete("a");
+        assert_eq!(scheduler.next_ready(), Some("b".to_string()));
+    }
+
+    #[test]
+    fn test_models_limited_by_vram_slots() {
+        // 单槽位时，需要不同模型的节点不能同时运行
+        let workflow = create_workflow();
+        let mut models = HashMap::new();
+        models.insert("a".to_string(), "model-1".to_string());
+        models.insert("b".to_string(), "model-2".to_string());
+        let mut scheduler = Scheduler::new(&workflow, models, 1, SchedulerConfig::default());
+
+        assert_eq!(scheduler.next_ready(), Some("a".to_string()));
+        assert_eq!(scheduler.next_ready(), None);
+        scheduler.complete("a");
+        assert_eq!(scheduler.next_ready(), Some("b".to_string()));
+    }
+
+    #[test]
+    fn test_same_model_shares_slot() {
+        let workflow = create_workflow();
+        let mut models = HashMap::new();
+        models.insert("a".to_string(), "model-1".to_string());
+        models.insert("b".to_string(), "model-1".to_string());
+        let mut scheduler = Scheduler::new(&workflow, models, 1, SchedulerConfig::default());
+
+        assert_eq!(scheduler.next_ready(), Some("a".to_string()));
+        assert_eq!(scheduler.next_ready(), Some("b".to_string()));
+    }
+
+    // branch -> x -> m, branch -> y -> m, x -> z
+    fn create_branch_workflow() -> WorkflowData {
+        WorkflowData {
+            version: "1.0".to_string(),
+            nodes: ["branch", "x", "y", "m", "z"].iter().map(|id| create_node(id)).collect(),
+            edges: vec![
+                create_edge("branch", "x"),
+                create_edge("branch", "y"),
+                create_edge("x", "m"),
+                create_edge("y", "m"),
+                create_edge("x", "z"),
+            ],
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_dead_branch_pruned() {
+        let workflow = create_branch_workflow();
+        let partial: HashSet<String> = ["m".to_string()].into_iter().collect();
+        let mut scheduler = Scheduler::new(&workflow, HashMap::new(), 2, SchedulerConfig::default())
+            .with_partial_inputs(partial);
+
+        assert_eq!(scheduler.next_ready(), Some("branch".to_string()));
+        scheduler.complete_with_dead_edges("branch", &["x".to_string()]);
+
+        // x及只经x可达的z被剪枝，汇合节点m仍等待y
+        assert_eq!(scheduler.take_pruned(), vec!["x".to_string(), "z".to_string()]);
+        assert_eq!(scheduler.next_ready(), Some("y".to_string()));
+        assert_eq!(scheduler.next_ready(), None);
+        scheduler.complete("y");
+        assert_eq!(scheduler.next_ready(), Some("m".to_string()));
+        scheduler.complete("m");
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn test_dead_input_prunes_non_merge_node() {
+        let workflow = create_branch_workflow();
+        let mut scheduler = Scheduler::new(&workflow, HashMap::new(), 2, SchedulerConfig::default());
+
+        scheduler.next_ready();
+        scheduler.complete_with_dead_edges("branch", &["x".to_string()]);
+        scheduler.take_pruned();
+        scheduler.next_ready();
+        scheduler.complete("y");
+
+        assert_eq!(scheduler.take_pruned(), vec!["m".to_string()]);
+        assert!(scheduler.is_idle());
+    }
+
+    #[test]
+    fn test_stream_consumer_starts_with_producer() {
+        // b -> c 为流式边：b开始执行时c即就绪，且不受并发上限约束
+        let workflow = create_workflow();
+        let config = SchedulerConfig { max_concurrency: 1 };
+        let mut scheduler = Scheduler::new(&workflow, HashMap::new(), 2, config)
+            .with_stream_edges(vec![("b".to_string(), "c".to_string())]);
+
+        assert_eq!(scheduler.next_ready(), Some("a".to_string()));
+        assert_eq!(scheduler.next_ready(), None);
+        scheduler.complete("a");
+        assert_eq!(scheduler.next_ready(), Some("b".to_string()));
+        assert_eq!(scheduler.next_ready(), Some("c".to_string()));
+        scheduler.complete("c");
+        scheduler.complete("b");
+        assert!(scheduler.is_idle());
+    }
+}
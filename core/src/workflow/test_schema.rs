@@ -0,0 +1,84 @@
+use super::schema::{load_workflow, LoadOptions, SchemaError, CURRENT_VERSION};
+use super::{WorkflowData, NodeData, Position};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1.0格式：边没有端口句柄和animated，节点没有parentId
+    const V1_0: &str = r#"{
+        "version": "1.0",
+        "nodes": [
+            {"id": "1", "type": "input", "position": {"x": 0, "y": 0}, "data": {"label": "你好"}, "config": null, "default_inputs": null, "input_types": null},
+            {"id": "2", "type": "output", "position": {"x": 100, "y": 0}, "data": {}, "config": null, "default_inputs": null, "input_types": null}
+        ],
+        "edges": [{"id": "e1", "source": "1", "target": "2"}]
+    }"#;
+
+    #[test]
+    fn test_old_version_migrated() {
+        let loaded = load_workflow(V1_0, LoadOptions::default()).unwrap();
+
+        assert_eq!(loaded.workflow.version, CURRENT_VERSION);
+        assert_eq!(loaded.migrated_from.as_deref(), Some("1.0"));
+        assert!(!loaded.workflow.edges[0].animated);
+        assert_eq!(loaded.workflow.edges[0].source_handle, None);
+        assert!(!loaded.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_version_compared_numerically() {
+        let json = V1_0.replace(r#""version": "1.0""#, r#""version": "1.00""#);
+        let loaded = load_workflow(&json, LoadOptions::default()).unwrap();
+        assert_eq!(loaded.migrated_from.as_deref(), Some("1.00"));
+        assert_eq!(loaded.workflow.version, CURRENT_VERSION);
+
+        let (workflow, warnings) = WorkflowData::from_json(&json).unwrap();
+        assert_eq!(workflow.version, CURRENT_VERSION);
+        assert!(warnings.iter().any(|w| w.contains("1.00")));
+    }
+
+    #[test]
+    fn test_current_version_loads_without_warnings() {
+        let workflow = WorkflowData::new(
+            vec![NodeData {
+                id: "1".to_string(),
+                type: "input".to_string(),
+                position: Position { x: 0.0, y: 0.0 },
+                data: serde_json::json!({ "label": "x" }),
+                config: None,
+                default_inputs: None,
+                input_types: None,
+                parent_id: None,
+            }],
+            Vec::new(),
+        );
+
+        let loaded = load_workflow(&workflow.to_json(), LoadOptions::strict()).unwrap();
+        assert!(loaded.warnings.is_empty());
+        assert_eq!(loaded.migrated_from, None);
+    }
+
+    #[test]
+    fn test_unknown_field_rejected_in_strict_mode() {
+        let json = V1_0.replace(r#""source": "1""#, r#""source": "1", "label": "x""#);
+
+        let lenient = load_workflow(&json, LoadOptions::default()).unwrap();
+        assert!(lenient.warnings.iter().any(|w| w.contains("edges[0].label")));
+
+        assert!(matches!(
+            load_workflow(&json, LoadOptions::strict()),
+            Err(SchemaError::UnknownField { path, field }) if path == "edges[0]" && field == "label"
+        ));
+    }
+
+    #[test]
+    fn test_newer_version_rejected_in_strict_mode() {
+        let json = V1_0.replace(r#""version": "1.0""#, r#""version": "9.0""#);
+
+        assert!(matches!(
+            load_workflow(&json, LoadOptions::strict()),
+            Err(SchemaError::UnsupportedVersion { .. })
+        ));
+    }
+}
@@ -0,0 +1,62 @@
+//! 工作流测试共用的构造函数：节点、边、参数化工作流和使用模拟后端的执行上下文
+
+use super::{ExecutionContext, WorkflowData, WorkflowParameter, NodeData, EdgeData, Position};
+use crate::model::{MockBackend, MockModelSpec};
+use crate::types::DataType;
+use crate::vram::VramPool;
+use std::sync::{Arc, Mutex};
+
+/// 任意模型ID都由模拟后端加载（1MiB），LLM节点输出"[模型<ID>响应] 你输入了: <提示词>"
+pub(crate) fn mock_context() -> ExecutionContext {
+    mock_context_with(MockBackend::new().with_default_model(MockModelSpec::new(1024 * 1024)))
+}
+
+pub(crate) fn mock_context_with(backend: MockBackend) -> ExecutionContext {
+    let pool = VramPool::default().with_backend(Arc::new(backend));
+    ExecutionContext::with_pool(Arc::new(Mutex::new(pool)))
+}
+
+pub(crate) fn create_node(id: &str, node_type: &str, label: &str, model_id: &str) -> NodeData {
+    NodeData {
+        id: id.to_string(),
+        type: node_type.to_string(),
+        position: Position { x: 0.0, y: 0.0 },
+        data: serde_json::json!({ "label": label }),
+        config: Some(serde_json::json!({ "model_id": model_id })),
+        default_inputs: None,
+        input_types: None,
+        parent_id: None,
+    }
+}
+
+pub(crate) fn create_edge(id: &str, source: &str, target: &str) -> EdgeData {
+    EdgeData {
+        id: id.to_string(),
+        source: source.to_string(),
+        target: target.to_string(),
+        source_handle: None,
+        target_handle: None,
+        animated: false,
+    }
+}
+
+pub(crate) fn parameter(name: &str, data_type: DataType, default: Option<serde_json::Value>) -> WorkflowParameter {
+    WorkflowParameter { name: name.to_string(), data_type, default, description: None }
+}
+
+// 输入 -> LLM -> 输出，提示词和模型由参数给出
+pub(crate) fn parameterized_workflow() -> WorkflowData {
+    WorkflowData {
+        version: "1.3".to_string(),
+        nodes: vec![
+            create_node("1", "input", "问题: {{prompt}}", ""),
+            create_node("2", "llm", "LLM", "{{model}}"),
+            create_node("3", "output", "输出", ""),
+        ],
+        edges: vec![create_edge("e1", "1", "2"), create_edge("e2", "2", "3")],
+        parameters: vec![
+            parameter("prompt", DataType::Text, None),
+            parameter("model", DataType::Text, Some(serde_json::json!("base"))),
+        ],
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::engine::{ErrorInfo, MainState, RunningSubState, StateError, StateMachineContext};
+use crate::workflow::events::{EventBus, ExecutionEvent};
+
+/// 单个节点的状态快照（供UI/CLI展示）
+#[derive(Debug, Clone)]
+pub struct NodeStateSnapshot {
+    pub node_id: String,
+    pub state: MainState,
+    /// 节点未执行即被取消（上游失败或分支未选中）
+    pub skipped: bool,
+    /// 首次开始执行到结束的耗时（含重试），未执行的节点为None
+    pub duration: Option<Duration>,
+}
+
+impl NodeStateSnapshot {
+    /// 状态的字符串表示，被跳过的节点显示为"Skipped"
+    pub fn status(&self) -> &'static str {
+        if self.skipped { "Skipped" } else { self.state.as_str() }
+    }
+}
+
+struct NodeTrack {
+    machine: StateMachineContext,
+    skipped: bool,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+}
+
+/// 节点状态跟踪器：每个节点一个状态机，Idle → Pending → Running → Completed/Error/Cancelled
+///
+/// 设置了事件通道时，每次状态变化发布对应的执行事件
+pub struct NodeStateTracker {
+    order: Vec<String>,
+    nodes: HashMap<String, NodeTrack>,
+    events: Option<EventBus>,
+    run_id: String,
+}
+
+impl NodeStateTracker {
+    pub fn new() -> Self {
+        Self { order: Vec::new(), nodes: HashMap::new(), events: None, run_id: String::new() }
+    }
+
+    /// 状态变化发布到events
+    pub fn with_events(events: EventBus) -> Self {
+        Self { events: Some(events), ..Self::new() }
+    }
+
+    /// 之后发布的事件所属的运行
+    pub fn set_run_id(&mut self, run_id: &str) {
+        self.run_id = run_id.to_string();
+    }
+
+    /// 为一次运行初始化所有节点（Idle）
+    pub fn reset<I: IntoIterator<Item = String>>(&mut self, node_ids: I) {
+        self.order.clear();
+        self.nodes.clear();
+        for node_id in node_ids {
+            self.nodes.insert(node_id.clone(), NodeTrack {
+                machine: StateMachineContext::new(MainState::Idle),
+                skipped: false,
+                started: None,
+                finished: None,
+            });
+            self.order.push(node_id);
+        }
+    }
+
+    /// 等待依赖完成
+    pub fn mark_pending(&mut self, node_id: &str) -> Result<(), StateError> {
+        self.transition(node_id, MainState::Pending)
+    }
+
+    /// 开始执行（或子状态变化）
+    pub fn mark_running(&mut self, node_id: &str, substate: RunningSubState) -> Result<(), StateError> {
+        self.transition(node_id, MainState::Running(substate))
+    }
+
+    pub fn mark_completed(&mut self, node_id: &str) -> Result<(), StateError> {
+        self.transition(node_id, MainState::Completed)
+    }
+
+    pub fn mark_failed(&mut self, node_id: &str, info: ErrorInfo) -> Result<(), StateError> {
+        self.transition(node_id, MainState::Error(info))
+    }
+
+    pub fn mark_cancelled(&mut self, node_id: &str) -> Result<(), StateError> {
+        self.transition(node_id, MainState::Cancelled)
+    }
+
+    /// 节点不会执行：从Pending直接取消并标记为跳过
+    pub fn mark_skipped(&mut self, node_id: &str) -> Result<(), StateError> {
+        if let Some(MainState::Idle) = self.state(node_id) {
+            self.apply(node_id, MainState::Pending)?;
+        }
+        self.apply(node_id, MainState::Cancelled)?;
+        if let Some(track) = self.nodes.get_mut(node_id) {
+            track.skipped = true;
+        }
+        self.publish(ExecutionEvent::NodeSkipped { run_id: self.run_id.clone(), node_id: node_id.to_string() });
+        Ok(())
+    }
+
+    /// 将所有仍在等待的节点标记为跳过
+    pub fn skip_pending(&mut self) {
+        let pending: Vec<String> = self.order.iter()
+            .filter(|id| matches!(self.state(id), Some(MainState::Idle) | Some(MainState::Pending)))
+            .cloned()
+            .collect();
+        for node_id in pending {
+            let _ = self.mark_skipped(&node_id);
+        }
+    }
+
+    /// 将所有未结束的节点（等待中或运行中）标记为取消
+    pub fn cancel_unfinished(&mut self) {
+        let unfinished: Vec<String> = self.order.iter()
+            .filter(|id| matches!(self.state(id), Some(MainState::Idle) | Some(MainState::Pending) | Some(MainState::Running(_))))
+            .cloned()
+            .collect();
+        for node_id in unfinished {
+            if let Some(MainState::Idle) = self.state(&node_id) {
+                let _ = self.mark_pending(&node_id);
+            }
+            let _ = self.mark_cancelled(&node_id);
+        }
+    }
+
+    pub fn state(&self, node_id: &str) -> Option<&MainState> {
+        self.nodes.get(node_id).map(|track| track.machine.current_state())
+    }
+
+    pub fn is_skipped(&self, node_id: &str) -> bool {
+        self.nodes.get(node_id).map(|track| track.skipped).unwrap_or(false)
+    }
+
+    /// 获取节点的完整状态历史
+    pub fn history(&self, node_id: &str) -> Option<&Vec<MainState>> {
+        self.nodes.get(node_id).map(|track| track.machine.previous_states())
+    }
+
+    /// 按节点声明顺序返回所有节点的状态快照
+    pub fn snapshot(&self) -> Vec<NodeStateSnapshot> {
+        self.order.iter()
+            .filter_map(|id| self.nodes.get(id).map(|track| NodeStateSnapshot {
+                node_id: id.clone(),
+                state: track.machine.current_state().clone(),
+                skipped: track.skipped,
+                duration: track.started.map(|started| track.finished.unwrap_or_else(Instant::now) - started),
+            }))
+            .collect()
+    }
+
+    fn transition(&mut self, node_id: &str, new_state: MainState) -> Result<(), StateError> {
+        self.apply(node_id, new_state)?;
+        if let Some(event) = self.state_event(node_id) {
+            self.publish(event);
+        }
+        Ok(())
+    }
+
+    // 节点当前状态对应的事件；Idle/Pending不发布
+    fn state_event(&self, node_id: &str) -> Option<ExecutionEvent> {
+        let track = self.nodes.get(node_id)?;
+        let run_id = self.run_id.clone();
+        let node_id = node_id.to_string();
+        Some(match track.machine.current_state() {
+            MainState::Idle | MainState::Pending => return None,
+            MainState::Running(RunningSubState::Normal) => ExecutionEvent::NodeStarted { run_id, node_id },
+            MainState::Running(substate) => ExecutionEvent::NodeProgress { run_id, node_id, substate: substate.clone() },
+            MainState::Completed => ExecutionEvent::NodeCompleted {
+                run_id,
+                node_id,
+                duration_ms: track.started.zip(track.finished).map(|(started, finished)| (finished - started).as_millis() as u64),
+            },
+            MainState::Error(info) => ExecutionEvent::NodeFailed {
+                run_id,
+                node_id,
+                error: info.error.message.clone(),
+                will_retry: info.recoverable,
+            },
+            MainState::Cancelled => ExecutionEvent::NodeCancelled { run_id, node_id },
+        })
+    }
+
+    fn publish(&self, event: ExecutionEvent) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+
+    // 状态转换和计时，不发布事件
+    fn apply(&mut self, node_id: &str, new_state: MainState) -> Result<(), StateError> {
+        let track = self.nodes.get_mut(node_id)
+            .ok_or_else(|| StateError::InvalidTransition(format!("Unknown node {}", node_id)))?;
+        let (starts, finishes) = match &new_state {
+            MainState::Running(_) => (true, false),
+            MainState::Completed | MainState::Error(_) | MainState::Cancelled => (false, true),
+            _ => (false, false),
+        };
+        track.machine.transition(new_state)?;
+
+        // 计时：首次进入Running开始，进入结束状态时截止；重试重新进入Running时继续计时
+        let now = Instant::now();
+        if starts {
+            track.started.get_or_insert(now);
+            track.finished = None;
+        }
+        if finishes {
+            track.finished = Some(now);
+        }
+        Ok(())
+    }
+}
+
+impl Default for NodeStateTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,29 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::parameter::{DynamicPorts, Port};
+use crate::types::DataType;
+use crate::workflow::executor::{sole_port, WorkflowError};
+use crate::workflow::loops::{check_scopes, is_loop_type, scope_view, scopes};
+use crate::workflow::nodes::Node;
+use crate::workflow::serialization::{EdgeData, NodeData, WorkflowData};
+use crate::workflow::stream::is_stream_type;
+use crate::workflow::subgraph::PortsOf;
 
 /// 检测工作流中的循环依赖
 /// edges: 边的集合，格式为 (from_node_id, to_node_id)
 pub fn detect_cycles(edges: &[(String, String)]) -> Result<(), String> {
+    match find_cycle(edges) {
+        Some(cycle) => Err(format!("工作流中存在循环依赖: {}", cycle.join(" -> "))),
+        None => Ok(()),
+    }
+}
+
+/// 查找一个循环，返回首尾相同的节点路径（如a -> b -> a）
+pub fn find_cycle(edges: &[(String, String)]) -> Option<Vec<String>> {
     // 构建邻接表
     let mut adjacency: HashMap<&String, Vec<&String>> = HashMap::new();
     for (from, to) in edges {
@@ -11,58 +32,238 @@ pub fn detect_cycles(edges: &[(String, String)]) -> Result<(), String> {
     
     // 记录访问过的节点和当前路径
     let mut visited: HashSet<&String> = HashSet::new();
-    let mut path: HashSet<&String> = HashSet::new();
+    let mut path: Vec<&String> = Vec::new();
     
     // 对每个未访问的节点进行深度优先搜索
     for (from, _) in edges {
         if !visited.contains(from) {
-            if has_cycle(from, &adjacency, &mut visited, &mut path) {
-                return Err("工作流中存在循环依赖".to_string());
+            if let Some(cycle) = has_cycle(from, &adjacency, &mut visited, &mut path) {
+                return Some(cycle);
             }
         }
     }
     
+    None
+}
+
+/// 按作用域检测工作流中的循环依赖：循环体内部单独检查，
+/// 循环节点与其子节点之间的边（循环体的输入和回传）不算作循环依赖
+pub fn detect_workflow_cycles(workflow: &WorkflowData) -> Result<(), String> {
+    check_scopes(workflow)?;
+    for scope in scopes(workflow) {
+        let view = scope_view(workflow, scope);
+        detect_cycles(&edge_pairs(&view))?;
+    }
     Ok(())
 }
 
-/// 深度优先搜索检测循环
-fn has_cycle(
-    node: &String, 
-    adjacency: &HashMap<&String, Vec<&String>>, 
-    visited: &mut HashSet<&String>, 
-    path: &mut HashSet<&String>
-) -> bool {
+fn edge_pairs(workflow: &WorkflowData) -> Vec<(String, String)> {
+    workflow.edges
+        .iter()
+        .map(|e| (e.source.clone(), e.target.clone()))
+        .collect()
+}
+
+/// 深度优先搜索检测循环，返回循环路径
+fn has_cycle<'a>(
+    node: &'a String, 
+    adjacency: &HashMap<&'a String, Vec<&'a String>>, 
+    visited: &mut HashSet<&'a String>, 
+    path: &mut Vec<&'a String>
+) -> Option<Vec<String>> {
     // 如果节点已经在当前路径中，说明存在循环
-    if path.contains(node) {
-        return true;
+    if let Some(start) = path.iter().position(|n| *n == node) {
+        let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+        cycle.push(node.clone());
+        return Some(cycle);
     }
     
     // 如果节点已经访问过，直接返回
     if visited.contains(node) {
-        return false;
+        return None;
     }
     
     // 标记节点为已访问，并加入当前路径
     visited.insert(node);
-    path.insert(node);
+    path.push(node);
     
     // 递归访问相邻节点
     if let Some(neighbors) = adjacency.get(node) {
         for neighbor in neighbors {
-            if has_cycle(neighbor, adjacency, visited, path) {
-                return true;
+            if let Some(cycle) = has_cycle(neighbor, adjacency, visited, path) {
+                return Some(cycle);
             }
         }
     }
     
     // 从当前路径中移除节点
-    path.remove(node);
-    false
+    path.pop();
+    None
+}
+
+/// 边检查错误，edge_id供画布高亮出错的边
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[error("边{edge_id}: {kind}")]
+pub struct EdgeError {
+    pub edge_id: String,
+    pub kind: EdgeErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "code")]
+pub enum EdgeErrorKind {
+    #[error("节点{node_id}不可用: {reason}")]
+    NodeUnavailable { node_id: String, reason: String },
+    #[error("节点{node_id}没有端口{port_id}")]
+    UnknownPort { node_id: String, port_id: String },
+    #[error("节点{node_id}需要在边上显式指定端口")]
+    PortNotSpecified { node_id: String },
+    #[error("类型不兼容: {from} 不能连接到 {to}")]
+    TypeMismatch { from: DataType, to: DataType },
+    #[error("端口{node_id}.{port_id}不接受多个输入")]
+    FanInNotAllowed { node_id: String, port_id: String },
+    /// 消费者要等普通边的源节点完成，而生产者在流通道写满后阻塞等待消费者，运行会死锁
+    #[error("节点{consumer}已通过流式边连接{producer}，不能再用普通边连接同一节点")]
+    StreamWithValueEdge { producer: String, consumer: String },
+}
+
+/// 类型不同但可由`DataValue::convert_to`转换的边，建议在两端之间做隐式转换
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImplicitConversion {
+    pub edge_id: String,
+    pub from: DataType,
+    pub to: DataType,
+}
+
+/// 端口类型检查结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EdgeTypeReport {
+    pub errors: Vec<EdgeError>,
+    pub conversions: Vec<ImplicitConversion>,
+}
+
+impl EdgeTypeReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// 将每条边解析到具体的源/目标端口，检查DataType兼容性和扇入（Port.multiple）
+///
+/// 工作流需已展开子图。循环节点与其子节点之间的边连接的是循环体端口，只检查子节点一端。
+pub fn validate_edge_types(workflow: &WorkflowData, ports_of: &PortsOf) -> EdgeTypeReport {
+    let mut report = EdgeTypeReport::default();
+    let mut node_ports: HashMap<&str, Result<DynamicPorts, String>> = HashMap::new();
+    for node in &workflow.nodes {
+        node_ports.insert(node.id.as_str(), ports_of(node).map_err(|e| e.to_string()));
+    }
+    let is_body_edge = |edge: &EdgeData, loop_id: &str, child_id: &str| {
+        workflow.get_node(loop_id).map_or(false, |node| is_loop_type(&node.type))
+            && workflow.get_node(child_id).and_then(|node| node.parent_id.as_deref()) == Some(loop_id)
+            && (edge.source == loop_id || edge.target == loop_id)
+    };
+
+    let mut fan_in: HashMap<(String, String), Vec<String>> = HashMap::new();
+    let mut stream_pairs: HashSet<(&str, &str)> = HashSet::new();
+    let mut value_edges: Vec<&EdgeData> = Vec::new();
+    for edge in &workflow.edges {
+        let error = |kind: EdgeErrorKind| EdgeError { edge_id: edge.id.clone(), kind };
+        let source = if is_body_edge(edge, &edge.source, &edge.target) {
+            None
+        } else {
+            match resolve_port(&node_ports, &edge.source, edge.source_handle.as_deref(), true) {
+                Ok(port) => Some(port),
+                Err(kind) => { report.errors.push(error(kind)); continue; }
+            }
+        };
+        let target = if is_body_edge(edge, &edge.target, &edge.source) {
+            None
+        } else {
+            match resolve_port(&node_ports, &edge.target, edge.target_handle.as_deref(), false) {
+                Ok(port) => Some(port),
+                Err(kind) => { report.errors.push(error(kind)); continue; }
+            }
+        };
+
+        if let Some(target) = &target {
+            fan_in.entry((edge.target.clone(), target.id.clone())).or_default().push(edge.id.clone());
+        }
+        match &source {
+            Some(source) if is_stream_type(&source.data_type) => { stream_pairs.insert((&edge.source, &edge.target)); }
+            _ => value_edges.push(edge),
+        }
+        let (Some(source), Some(target)) = (source, target) else { continue };
+        if source.data_type == target.data_type {
+            continue;
+        }
+        if source.data_type.can_convert_to(&target.data_type) {
+            report.conversions.push(ImplicitConversion {
+                edge_id: edge.id.clone(),
+                from: source.data_type,
+                to: target.data_type,
+            });
+        } else {
+            report.errors.push(error(EdgeErrorKind::TypeMismatch { from: source.data_type, to: target.data_type }));
+        }
+    }
+
+    // 不允许扇入的端口：除第一条边外均报错
+    for ((node_id, port_id), edge_ids) in fan_in {
+        let multiple = node_ports.get(node_id.as_str())
+            .and_then(|ports| ports.as_ref().ok())
+            .and_then(|ports| ports.get_input(&port_id))
+            .map_or(false, |port| port.multiple);
+        if multiple {
+            continue;
+        }
+        for edge_id in edge_ids.into_iter().skip(1) {
+            report.errors.push(EdgeError {
+                edge_id,
+                kind: EdgeErrorKind::FanInNotAllowed { node_id: node_id.clone(), port_id: port_id.clone() },
+            });
+        }
+    }
+    for edge in value_edges {
+        if stream_pairs.contains(&(edge.source.as_str(), edge.target.as_str())) {
+            report.errors.push(EdgeError {
+                edge_id: edge.id.clone(),
+                kind: EdgeErrorKind::StreamWithValueEdge { producer: edge.source.clone(), consumer: edge.target.clone() },
+            });
+        }
+    }
+    report.errors.sort_by(|a, b| a.edge_id.cmp(&b.edge_id));
+    report
 }
 
-/// 验证类型匹配
+// 按边上的句柄查找输出（output为true）或输入端口；未指定句柄时使用节点唯一的非流式端口
+fn resolve_port(
+    node_ports: &HashMap<&str, Result<DynamicPorts, String>>,
+    node_id: &str,
+    handle: Option<&str>,
+    output: bool,
+) -> Result<Port, EdgeErrorKind> {
+    let ports = match node_ports.get(node_id) {
+        Some(Ok(ports)) => ports,
+        Some(Err(reason)) => return Err(EdgeErrorKind::NodeUnavailable { node_id: node_id.to_string(), reason: reason.clone() }),
+        None => return Err(EdgeErrorKind::NodeUnavailable { node_id: node_id.to_string(), reason: "节点不存在".to_string() }),
+    };
+    let port_id = match handle {
+        Some(handle) => handle.to_string(),
+        None => {
+            let sole = if output { sole_port(ports.outputs()) } else { sole_port(ports.inputs()) };
+            sole.ok_or_else(|| EdgeErrorKind::PortNotSpecified { node_id: node_id.to_string() })?
+        }
+    };
+    let port = if output { ports.get_output(&port_id) } else { ports.get_input(&port_id) };
+    port.cloned()
+        .ok_or_else(|| EdgeErrorKind::UnknownPort { node_id: node_id.to_string(), port_id })
+}
+
+/// 验证类型匹配（按节点类型的固定规则）
 /// from: 源节点类型
 /// to: 目标节点类型
+///
+/// 不考虑端口和DataType，新代码应使用`validate_edge_types`
 pub fn validate_type_match(from: &str, to: &str) -> Result<(), String> {
     // 简单的类型匹配规则
     // 实际应用中可能需要更复杂的类型系统
@@ -79,3 +280,221 @@ pub fn validate_type_match(from: &str, to: &str) -> Result<(), String> {
         Err(format!("类型不匹配: {} 不能连接到 {}", from, to))
     }
 }
+
+/// 按节点数据构建节点实例（由执行器通过注册表提供），用于查询端口和必需输入
+pub type NodeOf<'a> = dyn Fn(&NodeData) -> Result<Box<dyn Node>, WorkflowError> + 'a;
+
+/// 模型文件路径所在的配置键
+const MODEL_PATH_KEYS: &[&str] = &["modelPath", "model_path", "lora_path"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 问题代码（序列化为snake_case字符串）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCode {
+    /// 子图无法展开
+    InvalidSubgraph,
+    /// 循环体作用域不合法
+    InvalidScope,
+    DuplicateNodeId,
+    DanglingEdge,
+    UnknownNodeType,
+    Cycle,
+    /// 端口解析失败、类型不兼容或不允许的扇入
+    InvalidEdge,
+    /// 类型不同但可隐式转换
+    ImplicitConversion,
+    UnconnectedInput,
+    UnreachableNode,
+    MissingModelFile,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub code: IssueCode,
+    pub message: String,
+    pub node_ids: Vec<String>,
+    pub edge_ids: Vec<String>,
+}
+
+/// 工作流验证报告：列出所有问题，而非在第一个问题处失败
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// 是否存在阻止执行的错误
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    fn push(&mut self, severity: Severity, code: IssueCode, message: String, node_ids: Vec<String>, edge_ids: Vec<String>) {
+        self.issues.push(ValidationIssue { severity, code, message, node_ids, edge_ids });
+    }
+
+    pub(crate) fn error(&mut self, code: IssueCode, message: String, node_ids: Vec<String>, edge_ids: Vec<String>) {
+        self.push(Severity::Error, code, message, node_ids, edge_ids);
+    }
+
+    fn warning(&mut self, code: IssueCode, message: String, node_ids: Vec<String>, edge_ids: Vec<String>) {
+        self.push(Severity::Warning, code, message, node_ids, edge_ids);
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            let level = match issue.severity {
+                Severity::Error => "错误",
+                Severity::Warning => "警告",
+            };
+            writeln!(f, "[{}] {}", level, issue.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// 验证工作流（需已展开子图），收集所有问题
+///
+/// model_dir为模型文件相对路径的基准目录。
+pub fn validate_workflow(workflow: &WorkflowData, nodes_of: &NodeOf, model_dir: &Path) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    // 重复ID
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for node in &workflow.nodes {
+        *counts.entry(node.id.as_str()).or_insert(0) += 1;
+    }
+    let mut duplicates: Vec<&str> = counts.iter().filter(|(_, n)| **n > 1).map(|(id, _)| *id).collect();
+    duplicates.sort();
+    for id in duplicates {
+        report.error(IssueCode::DuplicateNodeId, format!("节点ID重复: {}", id), vec![id.to_string()], Vec::new());
+    }
+
+    // 悬空边：后续检查只使用两端节点都存在的边
+    let mut edges = Vec::new();
+    for edge in &workflow.edges {
+        let missing: Vec<String> = [&edge.source, &edge.target].into_iter()
+            .filter(|id| !counts.contains_key(id.as_str()))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            edges.push(edge.clone());
+        } else {
+            report.error(IssueCode::DanglingEdge, format!("边{}指向不存在的节点: {}", edge.id, missing.join(", ")), missing, vec![edge.id.clone()]);
+        }
+    }
+    let workflow = WorkflowData { edges, ..workflow.clone() };
+
+    let mut instances: HashMap<&str, Box<dyn Node>> = HashMap::new();
+    for node in &workflow.nodes {
+        match nodes_of(node) {
+            Ok(instance) => { instances.insert(node.id.as_str(), instance); }
+            Err(e) => report.error(IssueCode::UnknownNodeType, format!("节点{}无法创建: {}", node.id, e), vec![node.id.clone()], Vec::new()),
+        }
+    }
+
+    // 循环依赖（按作用域），报告实际路径
+    match check_scopes(&workflow) {
+        Err(e) => report.error(IssueCode::InvalidScope, e, Vec::new(), Vec::new()),
+        Ok(()) => {
+            for scope in scopes(&workflow) {
+                let view = scope_view(&workflow, scope);
+                if let Some(cycle) = find_cycle(&edge_pairs(&view)) {
+                    let edge_ids = cycle.windows(2)
+                        .filter_map(|pair| view.edges.iter().find(|e| e.source == pair[0] && e.target == pair[1]))
+                        .map(|e| e.id.clone())
+                        .collect();
+                    let message = format!("循环依赖: {}", cycle.join(" -> "));
+                    let mut node_ids = cycle;
+                    node_ids.pop();
+                    report.error(IssueCode::Cycle, message, node_ids, edge_ids);
+                }
+            }
+        }
+    }
+
+    // 端口类型；无法创建的节点已单独报告，不再重复报告其连线
+    let edge_report = validate_edge_types(&workflow, &|node| {
+        nodes_of(node).map(|instance| instance.ports())
+    });
+    for error in edge_report.errors {
+        if let EdgeErrorKind::NodeUnavailable { .. } = error.kind {
+            continue;
+        }
+        let edge = workflow.edges.iter().find(|e| e.id == error.edge_id);
+        let node_ids = edge.map(|e| vec![e.source.clone(), e.target.clone()]).unwrap_or_default();
+        report.error(IssueCode::InvalidEdge, error.to_string(), node_ids, vec![error.edge_id]);
+    }
+    for conversion in edge_report.conversions {
+        report.warning(
+            IssueCode::ImplicitConversion,
+            format!("边{}: {}将隐式转换为{}", conversion.edge_id, conversion.from, conversion.to),
+            Vec::new(),
+            vec![conversion.edge_id],
+        );
+    }
+
+    // 未连接的必需输入
+    for node in &workflow.nodes {
+        let Some(instance) = instances.get(node.id.as_str()) else { continue };
+        let ports = instance.ports();
+        let connected: HashSet<String> = workflow.edges.iter()
+            .filter(|e| e.target == node.id)
+            .filter_map(|e| e.target_handle.clone().or_else(|| sole_port(ports.inputs())))
+            .collect();
+        for port_id in instance.required_inputs() {
+            let has_default = node.default_inputs.as_ref().map_or(false, |d| d.get(&port_id).is_some());
+            if !connected.contains(&port_id) && !has_default {
+                report.error(IssueCode::UnconnectedInput, format!("节点{}的输入{}未连接", node.id, port_id), vec![node.id.clone()], Vec::new());
+            }
+        }
+    }
+
+    // 不可达节点：从没有输入端口的入口节点沿边无法到达
+    let mut reached: HashSet<&str> = workflow.nodes.iter()
+        .filter(|node| instances.get(node.id.as_str()).map_or(true, |i| i.ports().inputs().next().is_none()))
+        .map(|node| node.id.as_str())
+        .collect();
+    let mut queue: Vec<&str> = reached.iter().copied().collect();
+    while let Some(current) = queue.pop() {
+        for edge in workflow.edges.iter().filter(|e| e.source == current) {
+            if reached.insert(edge.target.as_str()) {
+                queue.push(edge.target.as_str());
+            }
+        }
+    }
+    for node in workflow.nodes.iter().filter(|node| !reached.contains(node.id.as_str())) {
+        report.warning(IssueCode::UnreachableNode, format!("节点{}无法从任何入口节点到达", node.id), vec![node.id.clone()], Vec::new());
+    }
+
+    // 模型文件（引用工作流参数的路径在运行时才确定，不检查）
+    for node in &workflow.nodes {
+        let paths = MODEL_PATH_KEYS.iter()
+            .filter_map(|key| node.config_str(key))
+            .chain(node.config_str("model_id").filter(|id| id.ends_with(".gguf")));
+        for path in paths.filter(|path| !path.is_empty() && !path.contains("{{")) {
+            if !model_dir.join(path).exists() {
+                report.error(IssueCode::MissingModelFile, format!("节点{}的模型文件不存在: {}", node.id, path), vec![node.id.clone()], Vec::new());
+            }
+        }
+    }
+
+    report
+}
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::io::{BufRead, Write};
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use serde_json::{Value, to_value, from_value};
 use thiserror::Error;
 
@@ -30,6 +32,15 @@ pub enum PythonError {
     
     #[error("I/O 错误: {0}")]
     IoError(#[from] std::io::Error),
+    
+    #[error("执行已取消")]
+    Cancelled,
+}
+
+/// 读取响应时的终止原因
+enum ReadInterrupt {
+    Timeout,
+    Cancelled,
 }
 
 pub struct PythonExecutor {
@@ -65,6 +76,21 @@ impl PythonExecutor {
         inputs: HashMap<String, Value>,
         timeout: Duration
     ) -> Result<HashMap<String, Value>, PythonError> {
+        self.execute_with_cancel(code, inputs, timeout, &AtomicBool::new(false))
+    }
+    
+    /// 可取消的执行：cancel置位时杀死子进程并重启，返回PythonError::Cancelled
+    pub fn execute_with_cancel(
+        &self, 
+        code: &str, 
+        inputs: HashMap<String, Value>,
+        timeout: Duration,
+        cancel: &AtomicBool,
+    ) -> Result<HashMap<String, Value>, PythonError> {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(PythonError::Cancelled);
+        }
+        
         let start_time = Instant::now();
         
         // 构建 JSON-RPC 请求
@@ -96,9 +122,7 @@ impl PythonExecutor {
         
         // 读取响应
         let mut response_json = String::new();
-        let read_result = std::thread::timeout(timeout, || {
-            process.stdout.read_line(&mut response_json)
-        });
+        let read_result = read_response(process, &mut response_json, timeout, cancel);
         
         match read_result {
             Ok(Ok(_)) => {
@@ -127,11 +151,16 @@ impl PythonExecutor {
                 let _ = manager.restart_process(process_id);
                 Err(PythonError::IoError(e))
             },
-            Err(_) => {
+            Err(ReadInterrupt::Timeout) => {
                 // 超时，杀死进程并重启
                 let _ = manager.restart_process(process_id);
                 Err(PythonError::Timeout(timeout.as_secs()))
             },
+            Err(ReadInterrupt::Cancelled) => {
+                // 已取消，进程已被杀死，重启
+                let _ = manager.restart_process(process_id);
+                Err(PythonError::Cancelled)
+            },
         }
     }
     
@@ -161,3 +190,44 @@ impl PythonExecutor {
         Ok(result)
     }
 }
+
+/// 读取一行响应；超时或取消时由监视线程杀死子进程以中断阻塞读取
+fn read_response(
+    process: &mut PythonProcess,
+    buf: &mut String,
+    timeout: Duration,
+    cancel: &AtomicBool,
+) -> Result<std::io::Result<usize>, ReadInterrupt> {
+    let start = Instant::now();
+    let done = AtomicBool::new(false);
+    let PythonProcess { child, stdout, .. } = process;
+    
+    let (read, interrupt) = std::thread::scope(|scope| {
+        let watcher = scope.spawn(|| {
+            while !done.load(Ordering::SeqCst) {
+                let interrupt = if cancel.load(Ordering::SeqCst) {
+                    Some(ReadInterrupt::Cancelled)
+                } else if start.elapsed() > timeout {
+                    Some(ReadInterrupt::Timeout)
+                } else {
+                    None
+                };
+                if interrupt.is_some() {
+                    let _ = child.kill();
+                    return interrupt;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            None
+        });
+        
+        let read = stdout.read_line(buf);
+        done.store(true, Ordering::SeqCst);
+        (read, watcher.join().unwrap_or(None))
+    });
+    
+    match interrupt {
+        Some(interrupt) => Err(interrupt),
+        None => Ok(read),
+    }
+}
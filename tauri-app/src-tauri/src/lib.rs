@@ -1,9 +1,10 @@
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use tauri::api::dialog::FileDialogBuilder;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use microflow_core::vram::pool::VramPool;
-use microflow_core::workflow::{detect_cycles, WorkflowData, NodeData, EdgeData, WorkflowExecutor, ExecutionContext};
+use microflow_core::vram::pool::{SlotStatus, VramPool};
+use microflow_core::engine::CancellationToken;
+use microflow_core::workflow::{diff_runs as diff_records, read_parameter_sets, BatchSummary, ExecutionResult, RunDiff, RunRecord, RunSummary, import_comfyui as import_comfy, ComfyImport, load_workflow as load_mflow, LoadOptions, LoadedWorkflow, ValidationReport, WorkflowData, WorkflowParameter, NodeData, EdgeData, WorkflowExecutor, ExecutionContext};
 use serde::{Deserialize, Serialize};
 use std::fs;
 
@@ -23,56 +24,171 @@ async fn execute_node(node_type: String, inputs: String) -> Result<String, Strin
     Ok(format!("Executed {} with {}", node_type, inputs))
 }
 
+/// 执行类命令的错误：验证失败时返回完整的报告，前端据此高亮问题节点
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail")]
+pub enum CommandError {
+    Validation(ValidationReport),
+    Failed(String),
+}
+
+#[derive(Serialize)]
+pub struct SavedWorkflow {
+    pub json: String,
+    pub report: ValidationReport,
+}
+
 #[tauri::command]
-async fn save_workflow(nodes: Vec<NodeData>, edges: Vec<EdgeData>) -> Result<String, String> {
-    // 1. 检测循环依赖
-    let edge_pairs: Vec<(String, String)> = edges
-        .iter()
-        .map(|e| (e.source.clone(), e.target.clone()))
-        .collect();
+async fn save_workflow(
+    state: tauri::State<'_, AppState>,
+    nodes: Vec<NodeData>,
+    edges: Vec<EdgeData>,
+    parameters: Option<Vec<WorkflowParameter>>,
+) -> Result<SavedWorkflow, String> {
+    // 1. 构建工作流数据（写入当前格式版本）
+    let workflow_data = WorkflowData { parameters: parameters.unwrap_or_default(), ..WorkflowData::new(nodes, edges) };
     
-    detect_cycles(&edge_pairs)?;
-    
-    // 2. 构建工作流数据
-    let workflow_data = WorkflowData {
-        version: "1.0".to_string(),
-        nodes,
-        edges,
-    };
+    // 2. 完整验证；有问题也允许保存未完成的工作流，报告交给前端展示
+    let report = state.executor.lock().await.validate_workflow(&workflow_data);
     
     // 3. 转JSON返回
-    Ok(workflow_data.to_json())
+    Ok(SavedWorkflow { json: workflow_data.to_json(), report })
+}
+
+#[tauri::command]
+async fn load_workflow(json: String, strict: Option<bool>) -> Result<LoadedWorkflow, String> {
+    // 从JSON加载工作流数据，旧版本自动升级，警告返回给前端
+    let options = LoadOptions { strict: strict.unwrap_or(false) };
+    load_mflow(&json, options).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn import_comfyui(json: String) -> Result<ComfyImport, String> {
+    // ComfyUI的API格式或UI格式，未支持的节点导入为占位节点并列入报告
+    import_comfy(&json).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn load_workflow(json: String) -> Result<WorkflowData, String> {
-    // 从JSON加载工作流数据
-    WorkflowData::from_json(&json)
+async fn validate_workflow(
+    state: tauri::State<'_, AppState>,
+    workflow_json: String,
+) -> Result<ValidationReport, String> {
+    // 问题带node_ids/edge_ids供画布高亮；迁移警告已在load_workflow时返回给前端
+    let (workflow, _warnings) = WorkflowData::from_json(&workflow_json)?;
+    let executor = state.executor.lock().await;
+    Ok(executor.validate_workflow(&workflow))
 }
 
+/// 执行事件在窗口上的事件名，负载为序列化的ExecutionEvent
+const WORKFLOW_EVENT: &str = "workflow-event";
+
 #[tauri::command]
 async fn execute_workflow(
+    window: tauri::Window,
     state: tauri::State<'_, AppState>,
     workflow_json: String,
-) -> Result<String, String> {
+) -> Result<ExecutionResult, CommandError> {
     // 解析工作流
-    let workflow = WorkflowData::from_json(&workflow_json)
-        .map_err(|e| format!("解析失败: {}", e))?;
+    let (workflow, _warnings) = WorkflowData::from_json(&workflow_json)
+        .map_err(|e| CommandError::Failed(format!("解析失败: {}", e)))?;
+    
+    // 验证：有错误时返回完整报告
+    let executor = state.executor.lock().await;
+    let report = executor.validate_workflow(&workflow);
+    if report.has_errors() {
+        return Err(CommandError::Validation(report));
+    }
     
-    // 验证
-    let edge_pairs: Vec<(String, String)> = workflow.edges
-        .iter()
-        .map(|e| (e.source.clone(), e.target.clone()))
-        .collect();
-    detect_cycles(&edge_pairs)
-        .map_err(|e| e.to_string())?;
+    // 执行，期间将执行事件转发到窗口
+    let result = forward_events(&window, &executor, executor.execute_workflow(&workflow)).await;
+    
+    // 结果中的输出按节点ID和端口区分，并带各节点的状态与耗时
+    result.map_err(|e| CommandError::Failed(format!("执行失败: {}", e)))
+}
+
+// 等待运行结束，期间将执行器的事件转发到窗口
+async fn forward_events<F: std::future::Future>(window: &tauri::Window, executor: &WorkflowExecutor, run: F) -> F::Output {
+    let mut events = executor.subscribe();
+    tokio::pin!(run);
+    let output = loop {
+        tokio::select! {
+            output = &mut run => break output,
+            Ok(event) = events.recv() => {
+                let _ = window.emit(WORKFLOW_EVENT, &event);
+            }
+        }
+    };
+    // 运行结束前发布、尚未转发的事件（包括RunFinished）
+    while let Ok(event) = events.try_recv() {
+        let _ = window.emit(WORKFLOW_EVENT, &event);
+    }
+    output
+}
+
+#[tauri::command]
+async fn get_slot_status(state: tauri::State<'_, AppState>) -> Result<Vec<SlotStatus>, String> {
+    // 各模型所在的层（gpu/cpu/disk）及最近一次迁移耗时
+    Ok(state.executor.lock().await.slot_status())
+}
+
+#[tauri::command]
+async fn list_runs(state: tauri::State<'_, AppState>) -> Result<Vec<RunSummary>, String> {
+    let executor = state.executor.lock().await;
+    let store = executor.history().ok_or("未配置运行历史")?;
+    store.list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_run(state: tauri::State<'_, AppState>, run_id: String) -> Result<RunRecord, String> {
+    // 参数、各节点的输入输出/耗时/错误及使用的模型版本
+    let executor = state.executor.lock().await;
+    let store = executor.history().ok_or("未配置运行历史")?;
+    store.get(&run_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn diff_runs(state: tauri::State<'_, AppState>, left: String, right: String) -> Result<RunDiff, String> {
+    let executor = state.executor.lock().await;
+    let store = executor.history().ok_or("未配置运行历史")?;
+    let left = store.get(&left).map_err(|e| e.to_string())?;
+    let right = store.get(&right).map_err(|e| e.to_string())?;
+    Ok(diff_records(&left, &right))
+}
+
+#[tauri::command]
+async fn replay_run(
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+    run_id: String,
+) -> Result<ExecutionResult, String> {
+    // 以记录的工作流和参数重新运行（不使用输出缓存），产生新的运行记录
+    let executor = state.executor.lock().await;
+    let cancel = CancellationToken::new();
+    forward_events(&window, &executor, executor.replay_run(&run_id, &cancel)).await
+        .map_err(|e| format!("重放失败: {}", e))
+}
+
+#[tauri::command]
+async fn run_batch(
+    state: tauri::State<'_, AppState>,
+    workflow_json: String,
+    params_path: String,
+    output_path: String,
+) -> Result<BatchSummary, CommandError> {
+    // 参数文件为CSV（首行参数名）或JSONL，每组参数的结果逐行写入output_path
+    let (workflow, _warnings) = WorkflowData::from_json(&workflow_json)
+        .map_err(|e| CommandError::Failed(format!("解析失败: {}", e)))?;
+    let parameter_sets = read_parameter_sets(std::path::Path::new(&params_path))
+        .map_err(|e| CommandError::Failed(e.to_string()))?;
     
-    // 执行
-    let mut executor = state.executor.lock().await;
-    let result = executor.execute_workflow(&workflow).await
-        .map_err(|e| format!("执行失败: {}", e))?;
+    let executor = state.executor.lock().await;
+    let report = executor.validate_workflow(&workflow);
+    if report.has_errors() {
+        return Err(CommandError::Validation(report));
+    }
     
-    Ok(format!("执行成功: {:?}", result.final_outputs))
+    executor.run_batch(&workflow, &parameter_sets, std::path::Path::new(&output_path), &CancellationToken::new()).await
+        .map_err(|e| CommandError::Failed(format!("批量运行失败: {}", e)))
 }
 
 #[tauri::command]
@@ -106,16 +222,30 @@ async fn open_file_dialog(window: tauri::Window) -> Result<String, String> {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .manage(AppState {
-            vram_pool: Arc::new(Mutex::new(VramPool::new(2))),
-            executor: Arc::new(Mutex::new(WorkflowExecutor::new(ExecutionContext::new())))
+        .setup(|app| {
+            // 运行历史记录在应用数据目录下
+            let mut executor = WorkflowExecutor::new(ExecutionContext::new());
+            executor.set_history_dir(app.path().app_data_dir()?.join("history"));
+            app.manage(AppState {
+                vram_pool: Arc::new(Mutex::new(VramPool::default())),
+                executor: Arc::new(Mutex::new(executor)),
+            });
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_system_info,
             execute_node,
             save_workflow,
             load_workflow,
+            import_comfyui,
+            validate_workflow,
             execute_workflow,
+            run_batch,
+            get_slot_status,
+            list_runs,
+            get_run,
+            diff_runs,
+            replay_run,
             save_file_dialog,
             open_file_dialog
         ])
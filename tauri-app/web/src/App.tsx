@@ -7,7 +7,7 @@ import './App.css';
 
 function App() {
   const [status, setStatus] = useState('');
-  const { selectedNode, validateWorkflow, saveWorkflow, loadWorkflow, executeWorkflow, isExecuting, executionResult } = useWorkflowStore();
+  const { selectedNode, validateWorkflow, saveWorkflow, loadWorkflow, executeWorkflow, isExecuting, executionResult, nodeStatus, streamingText } = useWorkflowStore();
 
   const testBackend = async () => {
     const result = await invoke('get_system_info');
@@ -57,6 +57,16 @@ function App() {
             <button onClick={handleLoad} style={{marginLeft:10}}>📂 加载</button>
             <span style={{marginLeft:10,color:'#666'}}>{status}</span>
           </div>
+          {Object.keys(nodeStatus).length > 0 && (
+            <div style={{marginTop:10, fontSize:12, color:'#444'}}>
+              {Object.entries(nodeStatus).map(([nodeId, text]) => (
+                <div key={nodeId}>
+                  <strong>{nodeId}</strong> {text}
+                  {streamingText[nodeId] && <span style={{marginLeft:8, color:'#888'}}>{streamingText[nodeId]}</span>}
+                </div>
+              ))}
+            </div>
+          )}
           {executionResult && (
             <div style={{marginTop:10, padding:10, background:'#f0f0f0', borderRadius:4}}>
               <strong>执行结果:</strong> {executionResult}
@@ -1,15 +1,115 @@
 import { create } from 'zustand';
 import { Node, Edge } from '@xyflow/react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+// 与core的schema::CURRENT_VERSION保持一致
+const SCHEMA_VERSION = "1.3";
+
+interface ValidationIssue {
+  severity: 'error' | 'warning';
+  code: string;
+  message: string;
+  node_ids: string[];
+  edge_ids: string[];
+}
+
+interface ValidationReport {
+  issues: ValidationIssue[];
+}
+
+// execute_workflow/run_batch的错误：验证失败时带完整报告
+type CommandError =
+  | { kind: 'Validation'; detail: ValidationReport }
+  | { kind: 'Failed'; detail: string };
+
+// 工作流级参数，节点配置中以{{name}}引用
+interface WorkflowParameter {
+  name: string;
+  type: string;
+  default?: unknown;
+  description?: string;
+}
+
+// 构造提交给后端的工作流数据
+const toWorkflow = (nodes: Node[], edges: Edge[], parameters: WorkflowParameter[]) => ({
+  version: SCHEMA_VERSION,
+  parameters,
+  nodes: nodes.map(n => ({
+    id: n.id,
+    type: n.type,
+    position: n.position,
+    data: n.data,
+    parentId: n.parentId ?? null
+  })),
+  edges: edges.map(e => ({
+    id: e.id,
+    source: e.source,
+    target: e.target,
+    sourceHandle: e.sourceHandle ?? null,
+    targetHandle: e.targetHandle ?? null,
+    animated: e.animated || false
+  }))
+});
+
+// 模型所在的层：显存、CPU内存或仅在磁盘上
+type ModelTier = 'gpu' | 'cpu' | 'disk';
+
+// 后端ExecutionEvent，按type区分
+type ExecutionEvent =
+  | { type: 'RunStarted'; run_id: string; node_ids: string[] }
+  | { type: 'NodeStarted'; run_id: string; node_id: string }
+  | { type: 'NodeProgress'; run_id: string; node_id: string; substate: { kind: string; current?: number; total?: number | null } }
+  | { type: 'TokenGenerated'; run_id: string; node_id: string; port: string; token: string }
+  | { type: 'NodeCompleted'; run_id: string; node_id: string; duration_ms: number | null }
+  | { type: 'OutputProduced'; run_id: string; node_id: string; port: string; value: unknown }
+  | { type: 'NodeFailed'; run_id: string; node_id: string; error: string; will_retry: boolean }
+  | { type: 'NodeSkipped'; run_id: string; node_id: string }
+  | { type: 'NodeCancelled'; run_id: string; node_id: string }
+  | { type: 'ModelLoaded'; model_id: string }
+  | { type: 'ModelEvicted'; model_id: string }
+  | { type: 'ModelReleased'; model_id: string }
+  | { type: 'ModelDemotionFailed'; model_id: string; error: string }
+  | { type: 'ModelPrefetchFailed'; model_id: string; error: string }
+  | { type: 'ModelTierChanged'; model_id: string; from: ModelTier; to: ModelTier; duration_ms: number }
+  | { type: 'RunFinished'; run_id: string; status: string; duration_ms: number; error: string | null };
+
+interface ExecutionResult {
+  run_id: string;
+  outputs: Record<string, Record<string, unknown>>;
+  nodes: { node_id: string; status: string; duration_ms: number | null; error: string | null }[];
+}
+
+const formatOutputs = (result: ExecutionResult) =>
+  Object.entries(result.outputs)
+    .flatMap(([nodeId, ports]) => Object.entries(ports).map(([port, value]) => `${nodeId}.${port}: ${JSON.stringify(value)}`))
+    .join('\n');
+
+// 节点在画布上显示的进度文字
+const describeProgress = (event: ExecutionEvent & { type: 'NodeProgress' }) => {
+  const { substate } = event;
+  if (substate.kind === 'iterating') {
+    return `迭代 ${substate.current}${substate.total != null ? `/${substate.total}` : ''}`;
+  }
+  return substate.kind;
+};
+
+const formatIssues = (issues: ValidationIssue[]) =>
+  issues.map(i => `[${i.severity === 'error' ? '错误' : '警告'}] ${i.message}`).join('\n');
 
 interface WorkflowState {
   nodes: Node[];
   edges: Edge[];
+  parameters: WorkflowParameter[];
   selectedNode: Node | null;
   isExecuting: boolean;
   executionResult: string | null;
+  // 执行中各节点的状态文字与流式输出，由执行事件更新
+  nodeStatus: Record<string, string>;
+  streamingText: Record<string, string>;
   setNodes: (nodes: Node[]) => void;
   setEdges: (edges: Edge[]) => void;
+  setParameters: (parameters: WorkflowParameter[]) => void;
   setSelectedNode: (node: Node | null) => void;
   updateNodeData: (nodeId: string, data: any) => void;
   validateWorkflow: () => Promise<boolean>;
@@ -25,11 +125,15 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
     { id: '3', type: 'output', position: { x: 500, y: 100 }, data: { label: '输出结果' } }
   ],
   edges: [],
+  parameters: [],
   selectedNode: null,
   isExecuting: false,
   executionResult: null,
+  nodeStatus: {},
+  streamingText: {},
   setNodes: (nodes) => set({ nodes }),
   setEdges: (edges) => set({ edges }),
+  setParameters: (parameters) => set({ parameters }),
   setSelectedNode: (node) => set({ selectedNode: node }),
   updateNodeData: (nodeId, data) => set((state) => ({
     nodes: state.nodes.map((n) =>
@@ -38,9 +142,15 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
   })),
   validateWorkflow: async () => {
     try {
-      const { edges } = get();
-      const edgePairs = edges.map(e => [e.source, e.target]);
-      await invoke('detect_cycles', { edges: edgePairs });
+      const { nodes, edges, parameters } = get();
+      const report = await invoke('validate_workflow', {
+        workflow_json: JSON.stringify(toWorkflow(nodes, edges, parameters))
+      }) as ValidationReport;
+      const errors = report.issues.filter(i => i.severity === 'error');
+      if (errors.length > 0) {
+        alert(`验证失败:\n${formatIssues(report.issues)}`);
+        return false;
+      }
       return true;
     } catch (error) {
       alert(`验证失败: ${error}`);
@@ -49,12 +159,18 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
   },
   saveWorkflow: async () => {
     try {
-      const { nodes, edges } = get();
-      const json = await invoke('save_workflow', { nodes, edges });
+      const { nodes, edges, parameters } = get();
+      const { json, report } = await invoke('save_workflow', { nodes, edges, parameters }) as {
+        json: string;
+        report: ValidationReport;
+      };
+      if (report.issues.length > 0) {
+        alert(`工作流存在问题（仍可保存）:\n${formatIssues(report.issues)}`);
+      }
       if (json) {
         await invoke('save_file_dialog', { content: json });
       }
-      return json as string;
+      return json;
     } catch (error) {
       alert(`保存失败: ${error}`);
       throw error;
@@ -64,8 +180,17 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
     try {
       const content = await invoke('open_file_dialog') as string;
       if (content) {
-        const data = JSON.parse(content);
-        set({ nodes: data.nodes, edges: data.edges });
+        // 由后端解析，旧版本文件自动升级
+        const loaded = await invoke('load_workflow', { json: content }) as {
+          workflow: { nodes: any[]; edges: any[]; parameters?: WorkflowParameter[] };
+          warnings: string[];
+        };
+        loaded.warnings.forEach(w => console.warn(`加载警告: ${w}`));
+        set({
+          nodes: loaded.workflow.nodes,
+          edges: loaded.workflow.edges,
+          parameters: loaded.workflow.parameters ?? []
+        });
       }
     } catch (error) {
       alert(`加载失败: ${error}`);
@@ -73,38 +198,55 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
     }
   },
   executeWorkflow: async () => {
-    const { nodes, edges, validateWorkflow } = get();
+    const { nodes, edges, parameters, validateWorkflow } = get();
     
     if (!await validateWorkflow()) return;
     
-    set({ isExecuting: true, executionResult: null });
+    set({ isExecuting: true, executionResult: null, nodeStatus: {}, streamingText: {} });
+    
+    const setStatus = (nodeId: string, status: string) =>
+      set((state) => ({ nodeStatus: { ...state.nodeStatus, [nodeId]: status } }));
+    const unlisten = await listen<ExecutionEvent>('workflow-event', ({ payload: event }) => {
+      switch (event.type) {
+        case 'NodeStarted': setStatus(event.node_id, '运行中'); break;
+        case 'NodeProgress': setStatus(event.node_id, describeProgress(event)); break;
+        case 'NodeCompleted': setStatus(event.node_id, `完成 ${event.duration_ms ?? 0}ms`); break;
+        case 'NodeFailed': setStatus(event.node_id, event.will_retry ? `重试: ${event.error}` : `失败: ${event.error}`); break;
+        case 'NodeSkipped': setStatus(event.node_id, '跳过'); break;
+        case 'NodeCancelled': setStatus(event.node_id, '已取消'); break;
+        case 'TokenGenerated':
+          set((state) => ({
+            streamingText: { ...state.streamingText, [event.node_id]: (state.streamingText[event.node_id] ?? '') + event.token }
+          }));
+          break;
+        case 'OutputProduced': console.info(`输出 ${event.node_id}.${event.port}:`, event.value); break;
+        case 'ModelLoaded': console.info(`模型已加载: ${event.model_id}`); break;
+        case 'ModelEvicted': console.info(`模型已淘汰: ${event.model_id}`); break;
+        case 'ModelReleased': console.info(`已释放模型显存: ${event.model_id}`); break;
+        case 'ModelDemotionFailed': console.warn(`模型${event.model_id}降级到内存失败: ${event.error}`); break;
+        case 'ModelPrefetchFailed': console.warn(`预取模型${event.model_id}失败: ${event.error}`); break;
+        case 'ModelTierChanged': console.info(`模型${event.model_id}: ${event.from} -> ${event.to}（${event.duration_ms}ms）`); break;
+      }
+    });
     
     try {
       // 构造工作流数据
-      const workflow = {
-        version: "1.0",
-        nodes: nodes.map(n => ({
-          id: n.id,
-          type: n.type,
-          position: n.position,
-          data: n.data
-        })),
-        edges: edges.map(e => ({
-          id: e.id,
-          source: e.source,
-          target: e.target,
-          animated: e.animated || false
-        }))
-      };
+      const workflow = toWorkflow(nodes, edges, parameters);
       
       const result = await invoke('execute_workflow', {
         workflow_json: JSON.stringify(workflow)
-      });
+      }) as ExecutionResult;
       
-      set({ executionResult: result as string });
+      set({ executionResult: `执行成功:\n${formatOutputs(result)}` });
     } catch (e) {
-      set({ executionResult: '错误: ' + e });
+      const error = e as CommandError;
+      set({
+        executionResult: error.kind === 'Validation'
+          ? `验证失败:\n${formatIssues(error.detail.issues)}`
+          : `错误: ${error.detail ?? e}`
+      });
     } finally {
+      unlisten();
       set({ isExecuting: false });
     }
   }